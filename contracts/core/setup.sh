@@ -73,7 +73,7 @@ resim run "$SCRIPT_DIR/transactions/token_funding.rtm"
 
 sed "$REPLACEMENT_LOOKUP" $SCRIPT_DIR/raw_transactions/component_creation.rtm > $SCRIPT_DIR/transactions/component_creation.rtm
 CP_OP=$(resim run "$SCRIPT_DIR/transactions/component_creation.rtm")
-export COMPONENT=$(echo "$CP_OP" | sed -nr "s/└─ Component: ([[:alnum:]_]+)/\1/p")
+export COMPONENT=$(echo "$CP_OP" | sed -nr "s/.*Component: ([[:alnum:]_]+)/\1/p")
 REPLACEMENT_LOOKUP+="s/<<<component_address>>>/$COMPONENT/g;"
 
 sed "$REPLACEMENT_LOOKUP" $SCRIPT_DIR/raw_transactions/creating_initial_liquidity_pools.rtm > $SCRIPT_DIR/transactions/creating_initial_liquidity_pools.rtm
@@ -0,0 +1,319 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::oracle::*;
+
+/// Percentage of the repaid value paid on top in collateral to liquidators of newly created markets
+pub fn default_liquidation_bonus() -> Decimal {
+  return dec!("5");
+}
+
+/// Percentage of the exposure of a position that newly created markets let a single liquidation repay
+pub fn default_close_factor() -> Decimal {
+  return dec!("50");
+}
+
+/// Position of collateral backing an exposure to another token
+///
+/// # Contains:
+/// * `collateral_address`: ResourceAddress - Token held as collateral
+/// * `collateral_amount`: Decimal - Units of collateral held
+/// * `exposure_address`: ResourceAddress - Token the position is exposed to
+/// * `exposure_amount`: Decimal - Units of the token the position is exposed to
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, Debug)]
+pub struct Position {
+  pub collateral_address: ResourceAddress,
+  pub collateral_amount: Decimal,
+  pub exposure_address: ResourceAddress,
+  pub exposure_amount: Decimal
+}
+
+/// Calculates the Collateralization Ratio: `CR = C * C^rc / A * A^rc`
+///
+/// # Arguments:
+/// * `collateral_amount`: Decimal - Units of collateral assets (`C`)
+/// * `collateral_price`: Decimal - Price of a single collateral unit (`C^rc`)
+/// * `exposure_amount`: Decimal - Units of underlying assets (`A`)
+/// * `exposure_price`: Decimal - Price of a single underlying unit (`A^rc`)
+///
+/// # Returns:
+/// * `Decimal` - Collateralization ratio, `1.5` being 150%
+pub fn collateralization_ratio(
+  collateral_amount: Decimal,
+  collateral_price: Decimal,
+  exposure_amount: Decimal,
+  exposure_price: Decimal
+) -> Decimal {
+  let exposure_value: Decimal = exposure_amount * exposure_price;
+  assert!(
+    exposure_value > Decimal::zero(),
+    "[Collateral]: Cannot calculate the collateralization ratio of a position without exposure."
+  );
+  return collateral_amount * collateral_price / exposure_value;
+}
+
+/// Calculates the exposure repaid and the collateral seized by a liquidation
+///
+/// # Arguments:
+/// * `collateral_amount`: Decimal - Units of collateral held by the position
+/// * `collateral_price`: Decimal - Price of a single collateral unit
+/// * `exposure_amount`: Decimal - Units of the token the position is exposed to
+/// * `exposure_price`: Decimal - Price of a single exposure unit
+/// * `repay_amount`: Decimal - Units of the exposure token offered by the liquidator
+/// * `close_factor`: Decimal - Percentage of the exposure a single liquidation can repay (0-100)
+/// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in collateral (0-100)
+///
+/// # Returns:
+/// * `Decimal` - Units of the exposure token repaid
+/// * `Decimal` - Units of collateral seized by the liquidator
+///
+/// # Note:
+/// * The collateral seized is worth `(100 + bonus) / 100` times the value repaid, when the position holds less
+///   than that all of its collateral is seized and the amount repaid is lowered to match
+pub fn liquidation_amounts(
+  collateral_amount: Decimal,
+  collateral_price: Decimal,
+  exposure_amount: Decimal,
+  exposure_price: Decimal,
+  repay_amount: Decimal,
+  close_factor: Decimal,
+  liquidation_bonus: Decimal
+) -> (Decimal, Decimal) {
+  let max_repay: Decimal = exposure_amount * close_factor / dec!("100");
+  let mut repaid: Decimal = if repay_amount < max_repay { repay_amount } else { max_repay };
+
+  // Collateral units owed per unit of exposure repaid
+  let seize_rate: Decimal = exposure_price * (dec!("100") + liquidation_bonus) / dec!("100") / collateral_price;
+  let mut seized: Decimal = repaid * seize_rate;
+  if seized > collateral_amount {
+    seized = collateral_amount;
+    repaid = seized / seize_rate;
+  }
+  return (repaid, seized);
+}
+
+/// Asserts that a Minimum Collateralization Ratio can be used
+///
+/// # Arguments:
+/// * `mcr`: Decimal - Minimum Collateralization Ratio, `1.5` being 150%
+/// * `label`: String - Label that called the assertion method
+pub fn assert_valid_mcr(
+  mcr: Decimal,
+  label: String
+) {
+  assert!(
+    mcr >= Decimal::one(),
+    "[{}]: Minimum collateralization ratio must be at least 1.",
+    label
+  );
+}
+
+/// Asserts that the parameters of liquidations can be used
+///
+/// # Arguments:
+/// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in collateral (0-100)
+/// * `close_factor`: Decimal - Percentage of the exposure a single liquidation can repay (0-100)
+/// * `label`: String - Label that called the assertion method
+pub fn assert_liquidation_parameters(
+  liquidation_bonus: Decimal,
+  close_factor: Decimal,
+  label: String
+) {
+  assert!(
+    (liquidation_bonus >= Decimal::zero()) & (liquidation_bonus <= dec!("100")),
+    "[{}]: Liquidation bonus must be between 0 and 100.",
+    label
+  );
+  assert!(
+    (close_factor > Decimal::zero()) & (close_factor <= dec!("100")),
+    "[{}]: Close factor must be greater than 0 and at most 100.",
+    label
+  );
+}
+
+impl Position {
+  /// Obtain the value of the collateral in USD
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral
+  ///
+  /// # Returns:
+  /// * `Decimal` - Value of the collateral
+  pub fn collateral_value(
+    &self,
+    price_oracle: &PriceOracle
+  ) -> Decimal {
+    return self.collateral_amount * price_oracle.get_price(self.collateral_address);
+  }
+
+  /// Obtain the value of the exposure in USD
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the exposure
+  ///
+  /// # Returns:
+  /// * `Decimal` - Value of the exposure
+  pub fn exposure_value(
+    &self,
+    price_oracle: &PriceOracle
+  ) -> Decimal {
+    return self.exposure_amount * price_oracle.get_price(self.exposure_address);
+  }
+
+  /// Calculates the collateralization ratio of the position using oracle prices
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral and exposure
+  ///
+  /// # Returns:
+  /// * `Decimal` - Collateralization ratio, `1.5` being 150%
+  ///
+  /// # Note:
+  /// * Prices cancel out when the collateral and exposure are the same token, so the oracle is not called
+  pub fn collateralization_ratio(
+    &self,
+    price_oracle: &PriceOracle
+  ) -> Decimal {
+    if self.collateral_address == self.exposure_address {
+      return collateralization_ratio(self.collateral_amount, Decimal::one(), self.exposure_amount, Decimal::one());
+    }
+    return collateralization_ratio(
+      self.collateral_amount,
+      price_oracle.get_price(self.collateral_address),
+      self.exposure_amount,
+      price_oracle.get_price(self.exposure_address)
+    );
+  }
+
+  /// Checks whether the position is at or above the Minimum Collateralization Ratio
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral and exposure
+  /// * `mcr`: Decimal - Minimum Collateralization Ratio, `1.5` being 150%
+  ///
+  /// # Returns:
+  /// * `bool` - True if the position is sufficiently collateralized, false otherwise
+  pub fn is_collateralized(
+    &self,
+    price_oracle: &PriceOracle,
+    mcr: Decimal
+  ) -> bool {
+    // A position without exposure cannot be undercollateralized
+    if self.exposure_amount == Decimal::zero() {
+      return true;
+    }
+    return self.collateralization_ratio(price_oracle) >= mcr;
+  }
+
+  /// Asserts that the position is at or above the Minimum Collateralization Ratio
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral and exposure
+  /// * `mcr`: Decimal - Minimum Collateralization Ratio, `1.5` being 150%
+  /// * `label`: String - Label that called the assertion method
+  pub fn assert_collateralized(
+    &self,
+    price_oracle: &PriceOracle,
+    mcr: Decimal,
+    label: String
+  ) {
+    assert!(
+      self.is_collateralized(price_oracle, mcr),
+      "[{}]: Operation would push the collateralization ratio below the minimum of {}.",
+      label, mcr
+    );
+  }
+
+  /// Asserts that the position is below the Minimum Collateralization Ratio and can be liquidated
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral and exposure
+  /// * `mcr`: Decimal - Minimum Collateralization Ratio, `1.5` being 150%
+  /// * `label`: String - Label that called the assertion method
+  pub fn assert_liquidatable(
+    &self,
+    price_oracle: &PriceOracle,
+    mcr: Decimal,
+    label: String
+  ) {
+    assert!(
+      !self.is_collateralized(price_oracle, mcr),
+      "[{}]: Position is not below the minimum collateralization ratio of {}.",
+      label, mcr
+    );
+  }
+
+  /// Calculates the exposure repaid and the collateral seized by a liquidation
+  ///
+  /// # Arguments:
+  /// * `price_oracle`: &PriceOracle - Oracle used to price the collateral and exposure
+  /// * `repay_amount`: Decimal - Units of the exposure token offered by the liquidator
+  /// * `close_factor`: Decimal - Percentage of the exposure a single liquidation can repay (0-100)
+  /// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in collateral (0-100)
+  ///
+  /// # Returns:
+  /// * `Decimal` - Units of the exposure token repaid
+  /// * `Decimal` - Units of collateral seized by the liquidator
+  pub fn liquidation_amounts(
+    &self,
+    price_oracle: &PriceOracle,
+    repay_amount: Decimal,
+    close_factor: Decimal,
+    liquidation_bonus: Decimal
+  ) -> (Decimal, Decimal) {
+    return liquidation_amounts(
+      self.collateral_amount,
+      price_oracle.get_price(self.collateral_address),
+      self.exposure_amount,
+      price_oracle.get_price(self.exposure_address),
+      repay_amount,
+      close_factor,
+      liquidation_bonus
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn collateralization_ratio_values_both_sides() {
+    assert_eq!(collateralization_ratio(dec!("150"), Decimal::one(), dec!("100"), Decimal::one()), dec!("1.5"));
+    assert_eq!(collateralization_ratio(dec!("10"), dec!("30"), dec!("100"), dec!("2")), dec!("1.5"));
+  }
+
+  #[test]
+  #[should_panic]
+  fn collateralization_ratio_rejects_no_exposure() {
+    collateralization_ratio(dec!("10"), Decimal::one(), Decimal::zero(), Decimal::one());
+  }
+
+  #[test]
+  fn liquidation_is_capped_by_close_factor() {
+    let (repaid, seized): (Decimal, Decimal) = liquidation_amounts(
+      dec!("1000"), Decimal::one(), dec!("100"), Decimal::one(), dec!("80"), dec!("50"), Decimal::zero()
+    );
+    assert_eq!(repaid, dec!("50"));
+    assert_eq!(seized, dec!("50"));
+  }
+
+  #[test]
+  fn liquidation_pays_bonus_in_collateral() {
+    // Repaying 40 exposure worth 2 each is owed 84 collateral worth 1 each with a bonus of 5%
+    let (repaid, seized): (Decimal, Decimal) = liquidation_amounts(
+      dec!("1000"), Decimal::one(), dec!("100"), dec!("2"), dec!("40"), dec!("50"), dec!("5")
+    );
+    assert_eq!(repaid, dec!("40"));
+    assert_eq!(seized, dec!("84"));
+  }
+
+  #[test]
+  fn liquidation_lowers_repayment_when_collateral_is_exhausted() {
+    // 50 exposure plus a bonus of 10% would be owed 55 collateral but only 44 is left
+    let (repaid, seized): (Decimal, Decimal) = liquidation_amounts(
+      dec!("44"), Decimal::one(), dec!("100"), Decimal::one(), dec!("50"), dec!("50"), dec!("10")
+    );
+    assert_eq!(seized, dec!("44"));
+    assert_eq!(repaid, dec!("40"));
+  }
+}
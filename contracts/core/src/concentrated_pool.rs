@@ -0,0 +1,1249 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::pricing::{ln, pow, sqrt};
+use crate::utils::*;
+use crate::pool_utils::*;
+
+/// Lowest tick a position can start from, a price of about `2.1e-9`
+pub const MIN_TICK: i64 = -200000;
+
+/// Highest tick a position can end at, a price of about `4.9e8`
+pub const MAX_TICK: i64 = 200000;
+
+/// Price ratio between two neighbouring ticks, every tick moving the price by 0.01%
+fn tick_base() -> Decimal {
+  return dec!("1.0001");
+}
+
+/// Calculates the square root of the price at a tick
+///
+/// # Arguments:
+/// * `tick`: i64 - Tick to convert, between `MIN_TICK` and `MAX_TICK`
+///
+/// # Returns:
+/// * `Decimal` - Square root of the price at the tick, `1.0001^(tick / 2)`
+pub fn tick_to_sqrt_price(tick: i64) -> Decimal {
+  return pow(tick_base(), Decimal::from(tick) / dec!("2"));
+}
+
+/// Calculates the tick that a price falls in
+///
+/// # Arguments:
+/// * `sqrt_price`: Decimal - Square root of the price
+///
+/// # Returns:
+/// * `i64` - Largest tick whose price is not above the given price
+///
+/// # Note:
+/// * The tick is estimated with `2 * ln(sqrt_price) / ln(1.0001)` and then moved until it is exact, since the
+///   logarithm is not precise enough on its own near tick boundaries
+pub fn sqrt_price_to_tick(sqrt_price: Decimal) -> i64 {
+  let estimate: Decimal = dec!("2") * ln(sqrt_price) / ln(tick_base());
+  let mut tick: i64 = (estimate.0 / Decimal::one().0) as i64;
+  while (tick > MIN_TICK) & (tick_to_sqrt_price(tick) > sqrt_price) {
+    tick -= 1;
+  }
+  while (tick < MAX_TICK) & (tick_to_sqrt_price(tick + 1) <= sqrt_price) {
+    tick += 1;
+  }
+  return tick;
+}
+
+/// Non-fungible data held by the owner of a concentrated liquidity position
+///
+/// # Contains:
+/// * `lower_tick`: i64 - Tick at which the range of the position starts
+/// * `upper_tick`: i64 - Tick at which the range of the position ends
+#[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
+pub struct PositionData {
+  pub lower_tick: i64,
+  pub upper_tick: i64
+}
+
+/// Liquidity referencing an initialized tick
+///
+/// # Contains:
+/// * `liquidity_gross`: Decimal - Total liquidity of the positions starting or ending at the tick
+/// * `liquidity_net`: Decimal - Liquidity added when the price crosses the tick upwards, removed when crossing downwards
+/// * `fee_growth_outside`: Vec<Decimal> - Fees per unit of liquidity earned on the other side of the tick, per token
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct Tick {
+  pub liquidity_gross: Decimal,
+  pub liquidity_net: Decimal,
+  pub fee_growth_outside: Vec<Decimal>
+}
+
+/// Liquidity provided over a price range by the owner of a position NFT
+///
+/// # Contains:
+/// * `lower_tick`: i64 - Tick at which the range of the position starts
+/// * `upper_tick`: i64 - Tick at which the range of the position ends
+/// * `liquidity`: Decimal - Liquidity of the position
+/// * `fee_growth_inside`: Vec<Decimal> - Fees per unit of liquidity inside the range when fees were last accounted
+/// * `fees_owed`: Vec<Decimal> - Fees earned by the position that have not been collected yet
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct Position {
+  pub lower_tick: i64,
+  pub upper_tick: i64,
+  pub liquidity: Decimal,
+  pub fee_growth_inside: Vec<Decimal>,
+  pub fees_owed: Vec<Decimal>
+}
+
+/// Result of walking a swap through the ticks of the pool, applied to the pool once the swap is executed
+///
+/// # Contains:
+/// * `amount_in`: Decimal - Input tokens used, fees included
+/// * `amount_out`: Decimal - Output tokens given
+/// * `protocol_amount`: Decimal - Input tokens diverted to the protocol
+/// * `sqrt_price`: Decimal - Square root of the price after the swap
+/// * `tick`: i64 - Tick after the swap
+/// * `liquidity`: Decimal - Active liquidity after the swap
+/// * `fee_growth_global`: Decimal - Fees per unit of liquidity earned in the input token after the swap
+/// * `crossings`: Vec<(i64, Decimal)> - Ticks crossed by the swap and the input fee growth when they were crossed
+/// * `filled`: bool - Whether the pool had enough liquidity to fill the whole swap
+struct SwapComputation {
+  amount_in: Decimal,
+  amount_out: Decimal,
+  protocol_amount: Decimal,
+  sqrt_price: Decimal,
+  tick: i64,
+  liquidity: Decimal,
+  fee_growth_global: Decimal,
+  crossings: Vec<(i64, Decimal)>,
+  filled: bool
+}
+
+/// Calculates the amounts of both tokens backing an amount of liquidity over a range at a price
+///
+/// # Arguments:
+/// * `sqrt_price`: Decimal - Square root of the current price
+/// * `lower_tick`: i64 - Tick at which the range starts
+/// * `upper_tick`: i64 - Tick at which the range ends
+/// * `liquidity`: Decimal - Liquidity over the range
+///
+/// # Returns:
+/// * `Decimal` - Amount of the first token
+/// * `Decimal` - Amount of the second token
+///
+/// # Note:
+/// * `L` - The liquidity, `sqrt(x * y)` of the virtual reserves within the range
+/// * `S`, `Sa`, `Sb` - The square roots of the current, lower and upper price
+/// * Below the range only the first token is held, `x = L * (1 / Sa - 1 / Sb)`
+/// * Above the range only the second token is held, `y = L * (Sb - Sa)`
+/// * Within the range `x = L * (1 / S - 1 / Sb)` and `y = L * (S - Sa)`
+pub fn amounts_for_liquidity(
+  sqrt_price: Decimal,
+  lower_tick: i64,
+  upper_tick: i64,
+  liquidity: Decimal
+) -> (Decimal, Decimal) {
+  let sqrt_lower: Decimal = tick_to_sqrt_price(lower_tick);
+  let sqrt_upper: Decimal = tick_to_sqrt_price(upper_tick);
+
+  return if sqrt_price <= sqrt_lower {
+    (liquidity * (Decimal::one() / sqrt_lower - Decimal::one() / sqrt_upper), Decimal::zero())
+  } else if sqrt_price >= sqrt_upper {
+    (Decimal::zero(), liquidity * (sqrt_upper - sqrt_lower))
+  } else {
+    (
+      liquidity * (Decimal::one() / sqrt_price - Decimal::one() / sqrt_upper),
+      liquidity * (sqrt_price - sqrt_lower)
+    )
+  };
+}
+
+/// Calculates the largest liquidity over a range that the given amounts of both tokens can back at a price
+///
+/// # Arguments:
+/// * `sqrt_price`: Decimal - Square root of the current price
+/// * `lower_tick`: i64 - Tick at which the range starts
+/// * `upper_tick`: i64 - Tick at which the range ends
+/// * `amount0`: Decimal - Amount of the first token available
+/// * `amount1`: Decimal - Amount of the second token available
+///
+/// # Returns:
+/// * `Decimal` - Liquidity over the range, the inverse of `amounts_for_liquidity`
+pub fn liquidity_for_amounts(
+  sqrt_price: Decimal,
+  lower_tick: i64,
+  upper_tick: i64,
+  amount0: Decimal,
+  amount1: Decimal
+) -> Decimal {
+  let sqrt_lower: Decimal = tick_to_sqrt_price(lower_tick);
+  let sqrt_upper: Decimal = tick_to_sqrt_price(upper_tick);
+
+  return if sqrt_price <= sqrt_lower {
+    amount0 / (Decimal::one() / sqrt_lower - Decimal::one() / sqrt_upper)
+  } else if sqrt_price >= sqrt_upper {
+    amount1 / (sqrt_upper - sqrt_lower)
+  } else {
+    let liquidity0: Decimal = amount0 / (Decimal::one() / sqrt_price - Decimal::one() / sqrt_upper);
+    let liquidity1: Decimal = amount1 / (sqrt_price - sqrt_lower);
+    if liquidity0 < liquidity1 { liquidity0 } else { liquidity1 }
+  };
+}
+
+/// Walks a swap through the initialized ticks of a pool without changing its state
+///
+/// # Arguments:
+/// * `computation`: SwapComputation - State of the pool before the swap, with nothing swapped yet
+/// * `ticks`: &HashMap<i64, Tick> - Ticks at which positions start or end
+/// * `initialized_ticks`: &[i64] - Keys of `ticks` in ascending order
+/// * `pool_fee`: Decimal - Fee imposed on the swap (0-100)
+/// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100)
+/// * `zero_for_one`: bool - Whether the first token is swapped for the second, lowering the price
+/// * `amount`: Decimal - Amount of input tokens for exact input swaps, of output tokens otherwise
+/// * `exact_input`: bool - Whether `amount` is the input of the swap
+///
+/// # Returns:
+/// * `SwapComputation` - Amounts swapped and state of the pool after the swap
+///
+/// # Note:
+/// * Within the range between two initialized ticks the pool behaves like a constant product pool of liquidity
+///   `L`, the price moving to `1 / S' = 1 / S + dx / L` for an input `dx` of the first token and to
+///   `S' = S + dy / L` for an input `dy` of the second token
+/// * Whenever the price reaches an initialized tick, the liquidity of the positions starting or ending there is
+///   added or removed and the swap continues with the rest of the amount
+#[allow(clippy::too_many_arguments)]
+fn swap_through_ticks(
+  mut computation: SwapComputation,
+  ticks: &HashMap<i64, Tick>,
+  initialized_ticks: &[i64],
+  pool_fee: Decimal,
+  protocol_fee: Decimal,
+  zero_for_one: bool,
+  amount: Decimal,
+  exact_input: bool
+) -> SwapComputation {
+  let r: Decimal = (dec!("100") - pool_fee) / dec!("100");
+
+  let mut remaining: Decimal = amount;
+  while remaining > Decimal::zero() {
+    // The price moves towards the next initialized tick, or the end of the supported range if there is none
+    let next_tick: Option<i64> = if zero_for_one {
+      initialized_ticks.iter().rev().find(|tick| **tick <= computation.tick).cloned()
+    } else {
+      initialized_ticks.iter().find(|tick| **tick > computation.tick).cloned()
+    };
+    let target_tick: i64 = next_tick.unwrap_or(if zero_for_one { MIN_TICK } else { MAX_TICK });
+    let target: Decimal = tick_to_sqrt_price(target_tick);
+    let liquidity: Decimal = computation.liquidity;
+    let sqrt_price: Decimal = computation.sqrt_price;
+
+    if liquidity > Decimal::zero() {
+      // Input, without fees, and output of moving the price all the way to the target
+      let (step_in, step_out): (Decimal, Decimal) = if zero_for_one {
+        (liquidity * (Decimal::one() / target - Decimal::one() / sqrt_price), liquidity * (sqrt_price - target))
+      } else {
+        (liquidity * (target - sqrt_price), liquidity * (Decimal::one() / sqrt_price - Decimal::one() / target))
+      };
+      let reaches_target: bool = if exact_input { remaining * r >= step_in } else { remaining >= step_out };
+
+      if !reaches_target {
+        // The swap ends between the two ticks
+        let (gross_in, net_in, step_out, next_sqrt_price): (Decimal, Decimal, Decimal, Decimal) = if exact_input {
+          let net_in: Decimal = remaining * r;
+          let next_sqrt_price: Decimal = if zero_for_one {
+            Decimal::one() / (Decimal::one() / sqrt_price + net_in / liquidity)
+          } else {
+            sqrt_price + net_in / liquidity
+          };
+          let step_out: Decimal = if zero_for_one {
+            liquidity * (sqrt_price - next_sqrt_price)
+          } else {
+            liquidity * (Decimal::one() / sqrt_price - Decimal::one() / next_sqrt_price)
+          };
+          (remaining, net_in, step_out, next_sqrt_price)
+        } else {
+          let next_sqrt_price: Decimal = if zero_for_one {
+            sqrt_price - remaining / liquidity
+          } else {
+            Decimal::one() / (Decimal::one() / sqrt_price - remaining / liquidity)
+          };
+          let net_in: Decimal = if zero_for_one {
+            liquidity * (Decimal::one() / next_sqrt_price - Decimal::one() / sqrt_price)
+          } else {
+            liquidity * (next_sqrt_price - sqrt_price)
+          };
+          (net_in / r, net_in, remaining, next_sqrt_price)
+        };
+        record_swap_step(&mut computation, protocol_fee, gross_in, net_in, step_out);
+
+        // The new tick cannot be past the target tick nor behind the tick the step started from
+        let tick: i64 = sqrt_price_to_tick(next_sqrt_price);
+        computation.tick = if zero_for_one {
+          std::cmp::max(std::cmp::min(tick, computation.tick), target_tick)
+        } else {
+          std::cmp::min(std::cmp::max(tick, computation.tick), target_tick - 1)
+        };
+        computation.sqrt_price = next_sqrt_price;
+        remaining = Decimal::zero();
+        break;
+      }
+
+      record_swap_step(&mut computation, protocol_fee, step_in / r, step_in, step_out);
+      remaining = if exact_input { remaining - step_in / r } else { remaining - step_out };
+      if remaining < Decimal::zero() {
+        remaining = Decimal::zero();
+      }
+    }
+
+    // The price reached the target, the liquidity changes as the tick is crossed
+    computation.sqrt_price = target;
+    match next_tick {
+      Some(tick) => {
+        let liquidity_net: Decimal = ticks[&tick].liquidity_net;
+        computation.liquidity = if zero_for_one { liquidity - liquidity_net } else { liquidity + liquidity_net };
+        computation.tick = if zero_for_one { tick - 1 } else { tick };
+        computation.crossings.push((tick, computation.fee_growth_global));
+      }
+      None => {
+        computation.tick = if zero_for_one { MIN_TICK } else { MAX_TICK - 1 };
+        break;
+      }
+    }
+  }
+
+  computation.filled = remaining == Decimal::zero();
+  return computation;
+}
+
+/// Adds a step of a swap to its totals and accrues the pool fee paid in it to the active positions
+///
+/// # Arguments:
+/// * `computation`: &mut SwapComputation - Swap being computed
+/// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100)
+/// * `gross_in`: Decimal - Input tokens paid in the step, fees included
+/// * `net_in`: Decimal - Input tokens moving the price in the step
+/// * `step_out`: Decimal - Output tokens given in the step
+fn record_swap_step(
+  computation: &mut SwapComputation,
+  protocol_fee: Decimal,
+  gross_in: Decimal,
+  net_in: Decimal,
+  step_out: Decimal
+) {
+  let fee: Decimal = gross_in - net_in;
+  let protocol_amount: Decimal = fee * protocol_fee / dec!("100");
+
+  computation.amount_in = computation.amount_in + gross_in;
+  computation.amount_out = computation.amount_out + step_out;
+  computation.protocol_amount = computation.protocol_amount + protocol_amount;
+  computation.fee_growth_global = computation.fee_growth_global + (fee - protocol_amount) / computation.liquidity;
+}
+
+blueprint! {
+  /// Structure representing a Concentrated Liquidity Pool for the Elision Exchange, where providers choose the price
+  /// range their liquidity is used in
+  ///
+  /// # Contains:
+  /// * `vaults`: HashMap<ResourceAddress, Vault> - Collection of addresses and associated vaults
+  /// * `addresses`: Vec<ResourceAddress> - Sorted addresses of the tokens, prices being of the first in the second
+  /// * `sqrt_price`: Decimal - Square root of the current price
+  /// * `tick`: i64 - Tick that the current price falls in
+  /// * `tick_spacing`: i64 - Ticks that positions can start and end at are multiples of this spacing
+  /// * `liquidity`: Decimal - Liquidity of the positions whose range contains the current price
+  /// * `ticks`: HashMap<i64, Tick> - Ticks at which positions start or end
+  /// * `initialized_ticks`: Vec<i64> - Keys of `ticks` in ascending order
+  /// * `fee_growth_global`: Vec<Decimal> - Fees per unit of liquidity earned since creation, per token
+  /// * `positions`: HashMap<NonFungibleId, Position> - Records of every open position
+  /// * `position_nft_address`: ResourceAddress - Token representing ownership of a position
+  /// * `position_minter_badge`: Vault - Badge that gives authority to mint and burn position tokens
+  /// * `position_counter`: u64 - Id of the next position
+  /// * `pool_fee`: Decimal - Value between 0 and 100 defining fees paid to liquidity pool
+  /// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100), off by default
+  /// * `protocol_fee_vaults`: HashMap<ResourceAddress, Vault> - Protocol fees collected until they are withdrawn
+  struct ConcentratedLiquidityPool {
+    vaults: HashMap<ResourceAddress, Vault>,
+    addresses: Vec<ResourceAddress>,
+    sqrt_price: Decimal,
+    tick: i64,
+    tick_spacing: i64,
+    liquidity: Decimal,
+    ticks: HashMap<i64, Tick>,
+    initialized_ticks: Vec<i64>,
+    fee_growth_global: Vec<Decimal>,
+    positions: HashMap<NonFungibleId, Position>,
+    position_nft_address: ResourceAddress,
+    position_minter_badge: Vault,
+    position_counter: u64,
+    pool_fee: Decimal,
+    protocol_fee: Decimal,
+    protocol_fee_vaults: HashMap<ResourceAddress, Vault>
+  }
+
+  impl ConcentratedLiquidityPool {
+    /// Create new Concentrated Liquidity Pool for a token pair, liquidity is added afterwards through positions
+    ///
+    /// # Arguments:
+    /// * `address0`: ResourceAddress - Address of the first token
+    /// * `address1`: ResourceAddress - Address of the second token
+    /// * `initial_price`: Decimal - Price of a single unit of the first token denominated in the second token
+    /// * `tick_spacing`: i64 - Ticks that positions can start and end at are multiples of this spacing
+    /// * `pool_fee`: Decimal - Fee imposed on all swaps from this liquidity pool (0-100).
+    /// * `fee_admin_badge`: ResourceAddress - Badge with the authority to change the fees and withdraw protocol fees
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - ConcentratedLiquidityPool component address for initialized pool
+    pub fn new(
+      address0: ResourceAddress,
+      address1: ResourceAddress,
+      initial_price: Decimal,
+      tick_spacing: i64,
+      pool_fee: Decimal,
+      fee_admin_badge: ResourceAddress
+    ) -> ComponentAddress {
+      assert_valid_pair(address0, address1, String::from("Concentrated Pool Creation"));
+      assert!(initial_price > Decimal::zero(), "[Concentrated Pool Creation]: Price must be greater than zero.");
+      assert!(
+        (tick_spacing > 0) & (tick_spacing <= MAX_TICK),
+        "[Concentrated Pool Creation]: Tick spacing must be between 1 and {}.",
+        MAX_TICK
+      );
+      assert_valid_pool_fee(pool_fee, String::from("Concentrated Pool Creation"));
+
+      // Prices are kept as the price of the first sorted token, inverting the given price if the tokens swap places
+      let (sorted0, sorted1): (ResourceAddress, ResourceAddress) = sort_addresses(address0, address1);
+      let price: Decimal = if sorted0 == address0 { initial_price } else { Decimal::one() / initial_price };
+      let sqrt_price: Decimal = sqrt(price);
+      assert!(
+        (sqrt_price >= tick_to_sqrt_price(MIN_TICK)) & (sqrt_price < tick_to_sqrt_price(MAX_TICK)),
+        "[Concentrated Pool Creation]: Price is outside of the range supported by the pool."
+      );
+      let tick: i64 = sqrt_price_to_tick(sqrt_price);
+
+      let pid: String = format!("{}-{}", sorted0, sorted1);
+      let pair_name: String = pair_symbol(sorted0, sorted1);
+      info!(
+        "[Concentrated Pool Creation]: Creating new pool from Tokens: {}, Name: {}, Price: {}, Tick: {}",
+        pid, pair_name, price, tick
+      );
+
+      // Create badge for the liquidity pool to give authority over position tokens
+      let position_minter_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Position Minter Badge")
+        .metadata("symbol", "PMB")
+        .metadata("description", "Badge with the authority to mint and burn position tokens")
+        .metadata("pid", format!("{}", pid))
+        .initial_supply(1);
+
+      let position_nft_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", format!("{} Concentrated LP Position", pair_name))
+        .metadata("symbol", "CLP")
+        .metadata("description", "Token giving the holder ownership of a liquidity position over a price range.")
+        .metadata("pid", format!("{}", pid))
+        .mintable(rule!(require(position_minter_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(position_minter_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = fee_admin_access_rules(fee_admin_badge, Vec::new());
+
+      let mut concentrated_pool = Self {
+        vaults: new_empty_vaults(&[sorted0, sorted1]),
+        addresses: vec![sorted0, sorted1],
+        sqrt_price: sqrt_price,
+        tick: tick,
+        tick_spacing: tick_spacing,
+        liquidity: Decimal::zero(),
+        ticks: HashMap::new(),
+        initialized_ticks: Vec::new(),
+        fee_growth_global: vec![Decimal::zero(), Decimal::zero()],
+        positions: HashMap::new(),
+        position_nft_address: position_nft_address,
+        position_minter_badge: Vault::with_bucket(position_minter_badge),
+        position_counter: 0,
+        pool_fee: pool_fee,
+        protocol_fee: Decimal::zero(),
+        protocol_fee_vaults: new_empty_vaults(&[sorted0, sorted1])
+      }
+      .instantiate();
+      concentrated_pool.add_access_check(access_rules);
+
+      return concentrated_pool.globalize();
+    }
+
+    /// Changes the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - New fee for the pool (0-100)
+    pub fn set_pool_fee(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      update_fee(&mut self.pool_fee, pool_fee, String::from("Set Pool Fee"));
+    }
+
+    /// Obtain the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fee of the pool (0-100)
+    pub fn pool_fee(&self) -> Decimal {
+      return self.pool_fee;
+    }
+
+    /// Changes the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Arguments:
+    /// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn set_protocol_fee(
+      &mut self,
+      protocol_fee: Decimal
+    ) {
+      update_fee(&mut self.protocol_fee, protocol_fee, String::from("Set Protocol Fee"));
+    }
+
+    /// Obtain the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn protocol_fee(&self) -> Decimal {
+      return self.protocol_fee;
+    }
+
+    /// Withdraws the protocol fees collected by the pool
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the protocol fees collected in the first token
+    /// * `Bucket` - Contains the protocol fees collected in the second token
+    pub fn withdraw_protocol_fees(&mut self) -> (Bucket, Bucket) {
+      return into_pair(take_protocol_fees(&mut self.protocol_fee_vaults));
+    }
+
+    /// Verifies that the given address belongs to liquidity pool or not
+    ///
+    /// # Arguments:
+    /// * `address`: ResourceAddress - Address that is going to be verified
+    ///
+    /// # Returns:
+    /// * `bool` - True if address belongs to pool, false otherwise
+    pub fn belongs_to_pool(
+      &self,
+      address: ResourceAddress
+    ) -> bool {
+      return self.vaults.contains_key(&address);
+    }
+
+    /// Obtain token addresses in liquidity pool, the pool price being of the first in the second
+    ///
+    /// # Returns:
+    /// * `Vec<ResourceAddress>` - Vector of addresses that belong to the pool
+    pub fn addresses(&self) -> Vec<ResourceAddress> {
+      return self.addresses.clone();
+    }
+
+    /// Obtain name of liquidity pool based on pair symbol.
+    ///
+    /// # Returns:
+    /// * `String` - Pair symbol string
+    pub fn name(&self) -> String {
+      return pool_name(&self.addresses);
+    }
+
+    /// Obtain the address of the tokens representing positions in this pool
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the position NFTs
+    pub fn position_nft_address(&self) -> ResourceAddress {
+      return self.position_nft_address;
+    }
+
+    /// Retrieve address of other resource if address belongs to the pool
+    /// # Arguments
+    /// * `resource_address`: ResourceAddress - Address for token from the pool
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of other token in the pool
+    pub fn other_resource_address(
+      &self,
+      resource_address: ResourceAddress
+    ) -> ResourceAddress {
+      return other_address(&self.vaults, resource_address);
+    }
+
+    /// Obtain the tick that the current price falls in
+    ///
+    /// # Returns:
+    /// * `i64` - Current tick of the pool
+    pub fn current_tick(&self) -> i64 {
+      return self.tick;
+    }
+
+    /// Obtain the liquidity available to swaps at the current price
+    ///
+    /// # Returns:
+    /// * `Decimal` - Liquidity of the positions whose range contains the current price
+    pub fn liquidity(&self) -> Decimal {
+      return self.liquidity;
+    }
+
+    /// Calculates the current price of a token in the pool
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Address of the token to price
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of a single unit of the token denominated in the other token
+    pub fn spot_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, resource_address, String::from("Spot Price"));
+
+      let price: Decimal = self.sqrt_price * self.sqrt_price;
+      return if resource_address == self.addresses[0] { price } else { Decimal::one() / price };
+    }
+
+    /// Obtain the range and liquidity of a position
+    ///
+    /// # Arguments:
+    /// * `position_id`: NonFungibleId - Id of the position
+    ///
+    /// # Returns:
+    /// * `i64` - Tick at which the range of the position starts
+    /// * `i64` - Tick at which the range of the position ends
+    /// * `Decimal` - Liquidity of the position
+    pub fn position(
+      &self,
+      position_id: NonFungibleId
+    ) -> (i64, i64, Decimal) {
+      assert!(self.positions.contains_key(&position_id), "[Position]: Position does not exist.");
+      let position: &Position = &self.positions[&position_id];
+      return (position.lower_tick, position.upper_tick, position.liquidity);
+    }
+
+    /// Calculates the fees a position could collect right now
+    ///
+    /// # Arguments:
+    /// * `position_id`: NonFungibleId - Id of the position
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fees owed in the first token
+    /// * `Decimal` - Fees owed in the second token
+    pub fn fees_owed(
+      &self,
+      position_id: NonFungibleId
+    ) -> (Decimal, Decimal) {
+      assert!(self.positions.contains_key(&position_id), "[Fees Owed]: Position does not exist.");
+      let position: &Position = &self.positions[&position_id];
+      let fee_growth_inside: Vec<Decimal> = self.fee_growth_inside(position.lower_tick, position.upper_tick);
+      return (
+        position.fees_owed[0] + position.liquidity * (fee_growth_inside[0] - position.fee_growth_inside[0]),
+        position.fees_owed[1] + position.liquidity * (fee_growth_inside[1] - position.fee_growth_inside[1])
+      );
+    }
+
+    /// Calculates amount of output that can be given based on the amount of input
+    /// # Arguments:
+    /// * `input_resource_address`: ResourceAddress - Input token address
+    /// * `input_amount`: Decimal - Input amount to calculate output with
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated output amount, zero if the pool does not have the liquidity to fill the swap
+    pub fn calculate_output_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      input_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Output"));
+
+      let computation: SwapComputation = self.compute_swap(
+        input_resource_address == self.addresses[0], input_amount, true
+      );
+      return if computation.filled { computation.amount_out } else { Decimal::zero() };
+    }
+
+    /// Calculates amount of input that is required for an amount of output
+    /// # Arguments:
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated input amount
+    pub fn calculate_input_amount(
+      &self,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Calculate Input"));
+
+      let computation: SwapComputation = self.compute_swap(
+        output_resource_address == self.addresses[1], output_amount, false
+      );
+      assert!(computation.filled, "[Calculate Input]: Not enough liquidity in the pool for the desired output.");
+      return computation.amount_in;
+    }
+
+    /// Quotes the amount of input required for an amount of output, without failing when the pool cannot give it
+    ///
+    /// # Arguments:
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Option<Decimal>` - Calculated input amount, None if the liquidity of the pool cannot give the output
+    pub fn quote_input_amount(
+      &self,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Option<Decimal> {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Quote Input"));
+
+      let computation: SwapComputation = self.compute_swap(
+        output_resource_address == self.addresses[1], output_amount, false
+      );
+      return if computation.filled { Some(computation.amount_in) } else { None };
+    }
+
+    /// Calculates the fees per unit of liquidity earned inside a range
+    ///
+    /// # Arguments:
+    /// * `lower_tick`: i64 - Tick at which the range starts
+    /// * `upper_tick`: i64 - Tick at which the range ends
+    ///
+    /// # Returns:
+    /// * `Vec<Decimal>` - Fees per unit of liquidity earned inside the range, per token
+    ///
+    /// # Note:
+    /// * Only differences between two readings of the same range are meaningful, a single reading can be negative
+    fn fee_growth_inside(
+      &self,
+      lower_tick: i64,
+      upper_tick: i64
+    ) -> Vec<Decimal> {
+      let lower: &Tick = &self.ticks[&lower_tick];
+      let upper: &Tick = &self.ticks[&upper_tick];
+
+      let mut fee_growth_inside: Vec<Decimal> = Vec::new();
+      for index in 0..2 {
+        let global: Decimal = self.fee_growth_global[index];
+        let below: Decimal = if self.tick >= lower_tick {
+          lower.fee_growth_outside[index]
+        } else {
+          global - lower.fee_growth_outside[index]
+        };
+        let above: Decimal = if self.tick < upper_tick {
+          upper.fee_growth_outside[index]
+        } else {
+          global - upper.fee_growth_outside[index]
+        };
+        fee_growth_inside.push(global - below - above);
+      }
+      return fee_growth_inside;
+    }
+
+    /// Moves the fees earned by a position since they were last accounted into its owed fees
+    ///
+    /// # Arguments:
+    /// * `position_id`: &NonFungibleId - Id of the position
+    fn update_position_fees(
+      &mut self,
+      position_id: &NonFungibleId
+    ) {
+      let (lower_tick, upper_tick): (i64, i64) = {
+        let position: &Position = &self.positions[position_id];
+        (position.lower_tick, position.upper_tick)
+      };
+      let fee_growth_inside: Vec<Decimal> = self.fee_growth_inside(lower_tick, upper_tick);
+
+      let position: &mut Position = self.positions.get_mut(position_id).unwrap();
+      for index in 0..2 {
+        position.fees_owed[index] = position.fees_owed[index]
+          + position.liquidity * (fee_growth_inside[index] - position.fee_growth_inside[index]);
+      }
+      position.fee_growth_inside = fee_growth_inside;
+    }
+
+    /// Adds or removes liquidity referencing a tick, initializing or clearing the tick as needed
+    ///
+    /// # Arguments:
+    /// * `tick`: i64 - Tick at which a position starts or ends
+    /// * `liquidity_delta`: Decimal - Liquidity added to the position, negative when removed
+    /// * `is_upper`: bool - Whether the tick is where the position ends
+    fn update_tick(
+      &mut self,
+      tick: i64,
+      liquidity_delta: Decimal,
+      is_upper: bool
+    ) {
+      if !self.ticks.contains_key(&tick) {
+        // Fees are assumed to have all been earned below the tick if the price is above it
+        let fee_growth_outside: Vec<Decimal> = if tick <= self.tick {
+          self.fee_growth_global.clone()
+        } else {
+          vec![Decimal::zero(), Decimal::zero()]
+        };
+        self.ticks.insert(tick, Tick {
+          liquidity_gross: Decimal::zero(),
+          liquidity_net: Decimal::zero(),
+          fee_growth_outside: fee_growth_outside
+        });
+        if let Err(index) = self.initialized_ticks.binary_search(&tick) {
+          self.initialized_ticks.insert(index, tick);
+        }
+      }
+
+      let tick_data: &mut Tick = self.ticks.get_mut(&tick).unwrap();
+      tick_data.liquidity_gross = tick_data.liquidity_gross + liquidity_delta;
+      tick_data.liquidity_net = if is_upper {
+        tick_data.liquidity_net - liquidity_delta
+      } else {
+        tick_data.liquidity_net + liquidity_delta
+      };
+
+      if tick_data.liquidity_gross == Decimal::zero() {
+        self.ticks.remove(&tick);
+        self.initialized_ticks.retain(|initialized_tick| *initialized_tick != tick);
+      }
+    }
+
+    /// Walks a swap through the ticks of the pool without changing its state
+    ///
+    /// # Arguments:
+    /// * `zero_for_one`: bool - Whether the first token is swapped for the second, lowering the price
+    /// * `amount`: Decimal - Amount of input tokens for exact input swaps, of output tokens otherwise
+    /// * `exact_input`: bool - Whether `amount` is the input of the swap
+    ///
+    /// # Returns:
+    /// * `SwapComputation` - Amounts swapped and state of the pool after the swap
+    fn compute_swap(
+      &self,
+      zero_for_one: bool,
+      amount: Decimal,
+      exact_input: bool
+    ) -> SwapComputation {
+      let input_index: usize = if zero_for_one { 0 } else { 1 };
+      let computation: SwapComputation = SwapComputation {
+        amount_in: Decimal::zero(),
+        amount_out: Decimal::zero(),
+        protocol_amount: Decimal::zero(),
+        sqrt_price: self.sqrt_price,
+        tick: self.tick,
+        liquidity: self.liquidity,
+        fee_growth_global: self.fee_growth_global[input_index],
+        crossings: Vec::new(),
+        filled: false
+      };
+      return swap_through_ticks(
+        computation, &self.ticks, &self.initialized_ticks, self.pool_fee, self.protocol_fee,
+        zero_for_one, amount, exact_input
+      );
+    }
+
+    /// Executes a computed swap, moving the price and the liquidity and taking in the input tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the input tokens, at least the computed input
+    /// * `computation`: SwapComputation - Swap computed for the input tokens
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the output tokens
+    /// * `Bucket` - Contains the remaining input tokens
+    fn execute_swap(
+      &mut self,
+      mut tokens: Bucket,
+      computation: SwapComputation
+    ) -> (Bucket, Bucket) {
+      let input_address: ResourceAddress = tokens.resource_address();
+      let output_address: ResourceAddress = self.other_resource_address(input_address);
+      let input_index: usize = if input_address == self.addresses[0] { 0 } else { 1 };
+      let output_index: usize = 1 - input_index;
+
+      // Fees earned on either side of every crossed tick are flipped as the price moves to the other side
+      for (tick, fee_growth) in computation.crossings {
+        let global_output: Decimal = self.fee_growth_global[output_index];
+        let tick_data: &mut Tick = self.ticks.get_mut(&tick).unwrap();
+        tick_data.fee_growth_outside[input_index] = fee_growth - tick_data.fee_growth_outside[input_index];
+        tick_data.fee_growth_outside[output_index] = global_output - tick_data.fee_growth_outside[output_index];
+      }
+      self.fee_growth_global[input_index] = computation.fee_growth_global;
+      self.sqrt_price = computation.sqrt_price;
+      self.tick = computation.tick;
+      self.liquidity = computation.liquidity;
+
+      let mut input_tokens: Bucket = tokens.take(computation.amount_in);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut input_tokens, computation.protocol_amount);
+      deposit_into(&mut self.vaults, input_tokens);
+      let output_tokens: Bucket = withdraw_from(&mut self.vaults, output_address, computation.amount_out);
+      info!("[Swap]: Price after swap: {}, Tick: {}", self.sqrt_price * self.sqrt_price, self.tick);
+
+      return (output_tokens, tokens);
+    }
+
+    /// Opens a position providing liquidity only while the price is within a range
+    ///
+    /// # Arguments:
+    /// * `token0`: Bucket - Contains the amount of the first token to add to the pool
+    /// * `token1`: Bucket - Contains the amount of the second token to add to the pool
+    /// * `lower_tick`: i64 - Tick at which the range starts, a multiple of the tick spacing
+    /// * `upper_tick`: i64 - Tick at which the range ends, a multiple of the tick spacing
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains remaining tokens of the first token
+    /// * `Bucket` - Contains remaining tokens of the second token
+    /// * `Bucket` - Contains the position NFT issued to the liquidity provider
+    ///
+    /// # Note:
+    /// * A range entirely above the current price only takes the first token and a range entirely below it only
+    ///   takes the second token, the bucket of the other token is returned untouched
+    pub fn add_liquidity(
+      &mut self,
+      token0: Bucket,
+      token1: Bucket,
+      lower_tick: i64,
+      upper_tick: i64,
+      deadline: u64
+    ) -> (Bucket, Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Add Liquidity"));
+      assert_belongs_to_pool(&self.vaults, token0.resource_address(), String::from("Add Liquidity"));
+      assert_belongs_to_pool(&self.vaults, token1.resource_address(), String::from("Add Liquidity"));
+      assert_ne!(
+        token0.resource_address(), token1.resource_address(),
+        "[Add Liquidity]: A bucket of both tokens of the pool must be provided."
+      );
+      assert!(lower_tick < upper_tick, "[Add Liquidity]: Lower tick must be below the upper tick.");
+      assert!(
+        (lower_tick >= MIN_TICK) & (upper_tick <= MAX_TICK),
+        "[Add Liquidity]: Ticks must be between {} and {}.",
+        MIN_TICK, MAX_TICK
+      );
+      assert!(
+        (lower_tick % self.tick_spacing == 0) & (upper_tick % self.tick_spacing == 0),
+        "[Add Liquidity]: Ticks must be multiples of the tick spacing {}.",
+        self.tick_spacing
+      );
+
+      let (mut bucket0, mut bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
+      let liquidity: Decimal = liquidity_for_amounts(
+        self.sqrt_price, lower_tick, upper_tick, bucket0.amount(), bucket1.amount()
+      );
+      assert!(liquidity > Decimal::zero(), "[Add Liquidity]: Not enough tokens provided for the range.");
+      let (amount0, amount1): (Decimal, Decimal) = amounts_for_liquidity(
+        self.sqrt_price, lower_tick, upper_tick, liquidity
+      );
+      info!(
+        "[Add Liquidity]: Adding liquidity {} over ticks {} to {}, amounts: {}: {}, {}: {}",
+        liquidity, lower_tick, upper_tick, bucket0.resource_address(), amount0, bucket1.resource_address(), amount1
+      );
+
+      deposit_into(&mut self.vaults, bucket0.take(amount0));
+      deposit_into(&mut self.vaults, bucket1.take(amount1));
+
+      self.update_tick(lower_tick, liquidity, false);
+      self.update_tick(upper_tick, liquidity, true);
+      if (lower_tick <= self.tick) & (self.tick < upper_tick) {
+        self.liquidity = self.liquidity + liquidity;
+      }
+
+      // Record the position and mint the NFT that gives ownership over it
+      let position_id: NonFungibleId = NonFungibleId::from_u64(self.position_counter);
+      self.position_counter += 1;
+      let fee_growth_inside: Vec<Decimal> = self.fee_growth_inside(lower_tick, upper_tick);
+      self.positions.insert(position_id.clone(), Position {
+        lower_tick: lower_tick,
+        upper_tick: upper_tick,
+        liquidity: liquidity,
+        fee_growth_inside: fee_growth_inside,
+        fees_owed: vec![Decimal::zero(), Decimal::zero()]
+      });
+
+      let position_manager: &ResourceManager = borrow_resource_manager!(self.position_nft_address);
+      let position_nft: Bucket = self.position_minter_badge.authorize(|| {
+        position_manager.mint_non_fungible(&position_id, PositionData {
+          lower_tick: lower_tick,
+          upper_tick: upper_tick
+        })
+      });
+
+      return (bucket0, bucket1, position_nft);
+    }
+
+    /// Collects the fees earned by a position while keeping its liquidity in the pool
+    ///
+    /// # Arguments:
+    /// * `position_proof`: Proof - Proof of the position NFT
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the fees earned in the first token
+    /// * `Bucket` - Contains the fees earned in the second token
+    pub fn collect_fees(
+      &mut self,
+      position_proof: Proof
+    ) -> (Bucket, Bucket) {
+      assert_eq!(
+        position_proof.resource_address(), self.position_nft_address,
+        "[Collect Fees]: Position token does not belong to this liquidity pool."
+      );
+      assert_eq!(position_proof.amount(), Decimal::one(), "[Collect Fees]: Fees are collected one position at a time.");
+
+      let position_id: NonFungibleId = position_proof.non_fungible::<PositionData>().id();
+      self.update_position_fees(&position_id);
+
+      let fees_owed: Vec<Decimal> = self.positions[&position_id].fees_owed.clone();
+      self.positions.get_mut(&position_id).unwrap().fees_owed = vec![Decimal::zero(), Decimal::zero()];
+      info!("[Collect Fees]: Collecting {:?} from position {}", fees_owed, position_id);
+
+      let bucket0: Bucket = withdraw_from(&mut self.vaults, self.addresses[0], fees_owed[0]);
+      let bucket1: Bucket = withdraw_from(&mut self.vaults, self.addresses[1], fees_owed[1]);
+      return (bucket0, bucket1);
+    }
+
+    /// Closes positions, returning their liquidity and uncollected fees
+    ///
+    /// # Arguments:
+    /// * `position_nfts`: Bucket - Contains the NFTs of the positions to close
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the first token owed to the positions
+    /// * `Bucket` - Contains the second token owed to the positions
+    pub fn remove_liquidity(
+      &mut self,
+      position_nfts: Bucket,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Remove Liquidity"));
+      assert_eq!(
+        position_nfts.resource_address(), self.position_nft_address,
+        "[Remove Liquidity]: Position token does not belong to this liquidity pool"
+      );
+
+      let mut bucket0: Bucket = Bucket::new(self.addresses[0]);
+      let mut bucket1: Bucket = Bucket::new(self.addresses[1]);
+      for position_id in position_nfts.non_fungible_ids() {
+        self.update_position_fees(&position_id);
+        let position: Position = self.positions.remove(&position_id).unwrap();
+        let (amount0, amount1): (Decimal, Decimal) = amounts_for_liquidity(
+          self.sqrt_price, position.lower_tick, position.upper_tick, position.liquidity
+        );
+
+        self.update_tick(position.lower_tick, -position.liquidity, false);
+        self.update_tick(position.upper_tick, -position.liquidity, true);
+        if (position.lower_tick <= self.tick) & (self.tick < position.upper_tick) {
+          self.liquidity = self.liquidity - position.liquidity;
+        }
+
+        info!(
+          "[Remove Liquidity]: Closing position {} with liquidity {}, fees: {:?}",
+          position_id, position.liquidity, position.fees_owed
+        );
+        bucket0.put(withdraw_from(&mut self.vaults, self.addresses[0], amount0 + position.fees_owed[0]));
+        bucket1.put(withdraw_from(&mut self.vaults, self.addresses[1], amount1 + position.fees_owed[1]));
+      }
+
+      self.position_minter_badge.authorize(|| {
+        position_nfts.burn();
+      });
+
+      return (bucket0, bucket1);
+    }
+
+    /// Execute token swap through the positions covering the price and take pool fee
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the input tokens that will be swapped for other tokens
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the other tokens
+    pub fn swap(
+      &mut self,
+      tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap"));
+      assert_deadline(deadline, String::from("Swap"));
+
+      let computation: SwapComputation = self.compute_swap(
+        tokens.resource_address() == self.addresses[0], tokens.amount(), true
+      );
+      assert!(computation.filled, "[Swap]: Not enough liquidity in the pool to fill the swap.");
+      assert!(computation.amount_out >= min_amount_out, "[Swap]: min_amount_out not satisfied.");
+
+      // Rounding can leave dust of the input unused, it is given to the pool with the rest of the input
+      let (output_tokens, remaining): (Bucket, Bucket) = self.execute_swap(tokens, computation);
+      deposit_into(&mut self.vaults, remaining);
+      return output_tokens;
+    }
+
+    /// Swaps exact input tokens for output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains input tokens that will be swapped
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains other tokens
+    pub fn swap_exact_tokens_for_tokens(
+      &mut self,
+      tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      return self.swap(tokens, min_amount_out, deadline);
+    }
+
+    /// Swaps input tokens for an exact amount of output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains tokens that the user wishes to swap
+    /// * `output_amount`: Decimal - Specific amount of output that the user wishes to receive
+    /// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains output tokens
+    /// * `Bucket` - Contains remaining input tokens
+    pub fn swap_tokens_for_exact_tokens(
+      &mut self,
+      tokens: Bucket,
+      output_amount: Decimal,
+      max_input: Decimal,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap For Exact"));
+      assert_deadline(deadline, String::from("Swap For Exact"));
+
+      let computation: SwapComputation = self.compute_swap(
+        tokens.resource_address() == self.addresses[0], output_amount, false
+      );
+      assert!(computation.filled, "[Swap For Exact]: Not enough liquidity in the pool for the desired output.");
+      assert_input_sufficient(tokens.amount(), computation.amount_in, max_input);
+
+      return self.execute_swap(tokens, computation);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Asserts that two values are within `tolerance` of each other
+  fn assert_close(
+    actual: Decimal,
+    expected: Decimal,
+    tolerance: Decimal
+  ) {
+    let difference: Decimal = if actual > expected { actual - expected } else { expected - actual };
+    assert!(difference <= tolerance, "Expected {} but got {}", expected, actual);
+  }
+
+  /// Ticks of two positions of liquidity 1000 around a price of 1, over -600 to 600 and over -1200 to 1200
+  fn nested_positions() -> (HashMap<i64, Tick>, Vec<i64>) {
+    let mut ticks: HashMap<i64, Tick> = HashMap::new();
+    for (tick, liquidity_net) in [(-1200, 1000), (-600, 1000), (600, -1000), (1200, -1000)] {
+      ticks.insert(tick, Tick {
+        liquidity_gross: dec!("1000"),
+        liquidity_net: Decimal::from(liquidity_net),
+        fee_growth_outside: vec![Decimal::zero(), Decimal::zero()]
+      });
+    }
+    return (ticks, vec![-1200, -600, 600, 1200]);
+  }
+
+  /// State of a pool holding `nested_positions` at a price of 1, before any swap
+  fn start_at_price_one() -> SwapComputation {
+    return SwapComputation {
+      amount_in: Decimal::zero(),
+      amount_out: Decimal::zero(),
+      protocol_amount: Decimal::zero(),
+      sqrt_price: Decimal::one(),
+      tick: 0,
+      liquidity: dec!("2000"),
+      fee_growth_global: Decimal::zero(),
+      crossings: Vec::new(),
+      filled: false
+    };
+  }
+
+  #[test]
+  fn tick_round_trips_at_the_bounds() {
+    for tick in [MIN_TICK, MIN_TICK + 1, -1, 0, 1, MAX_TICK - 1, MAX_TICK] {
+      assert_eq!(sqrt_price_to_tick(tick_to_sqrt_price(tick)), tick);
+    }
+    assert_close(tick_to_sqrt_price(MAX_TICK), dec!("22015.456048552198645701"), dec!("0.000001"));
+    assert_close(tick_to_sqrt_price(MIN_TICK), dec!("0.000045422633889328"), dec!("0.000000000001"));
+  }
+
+  #[test]
+  fn price_between_ticks_falls_in_the_lower_tick() {
+    let between: Decimal = (tick_to_sqrt_price(-963) + tick_to_sqrt_price(-962)) / dec!("2");
+    assert_eq!(sqrt_price_to_tick(between), -963);
+  }
+
+  #[test]
+  fn liquidity_for_amounts_inverts_amounts_for_liquidity() {
+    // Within, above and below the range of the position
+    for (lower_tick, upper_tick) in [(-600, 600), (-1200, -600), (600, 1200)] {
+      let (amount0, amount1): (Decimal, Decimal) = amounts_for_liquidity(
+        Decimal::one(), lower_tick, upper_tick, dec!("1000")
+      );
+      let liquidity: Decimal = liquidity_for_amounts(Decimal::one(), lower_tick, upper_tick, amount0, amount1);
+      assert_close(liquidity, dec!("1000"), dec!("0.000001"));
+    }
+  }
+
+  #[test]
+  fn amounts_for_liquidity_fit_in_the_provided_amounts() {
+    let liquidity: Decimal = liquidity_for_amounts(Decimal::one(), -600, 1200, dec!("100"), dec!("100"));
+    let (amount0, amount1): (Decimal, Decimal) = amounts_for_liquidity(Decimal::one(), -600, 1200, liquidity);
+
+    // The range reaches further above the price, so the first token limits the liquidity
+    assert!(amount1 <= dec!("100"));
+    assert_close(amount0, dec!("100"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn swap_crossing_a_tick_accrues_fees_on_both_sides() {
+    let (ticks, initialized_ticks): (HashMap<i64, Tick>, Vec<i64>) = nested_positions();
+    let computation: SwapComputation = swap_through_ticks(
+      start_at_price_one(), &ticks, &initialized_ticks, dec!("0.3"), Decimal::zero(), true, dec!("80"), true
+    );
+
+    assert!(computation.filled);
+    assert_eq!(computation.amount_in, dec!("80"));
+    assert_eq!(computation.liquidity, dec!("1000"));
+    assert!((computation.tick < -600) & (computation.tick >= -1200));
+
+    // Fees are earned by 2000 liquidity until the tick is crossed and by 1000 liquidity after
+    assert_eq!(computation.crossings.len(), 1);
+    let (crossed_tick, fee_growth_at_crossing): (i64, Decimal) = computation.crossings[0];
+    assert_eq!(crossed_tick, -600);
+    assert!(fee_growth_at_crossing > Decimal::zero());
+    assert!(computation.fee_growth_global > fee_growth_at_crossing);
+    let fees: Decimal = fee_growth_at_crossing * dec!("2000")
+      + (computation.fee_growth_global - fee_growth_at_crossing) * dec!("1000");
+    assert_close(fees, dec!("80") * dec!("0.003"), dec!("0.000000001"));
+  }
+
+  #[test]
+  fn protocol_fee_is_not_accrued_to_positions() {
+    let (ticks, initialized_ticks): (HashMap<i64, Tick>, Vec<i64>) = nested_positions();
+    let computation: SwapComputation = swap_through_ticks(
+      start_at_price_one(), &ticks, &initialized_ticks, dec!("0.3"), dec!("50"), false, dec!("10"), true
+    );
+
+    assert!(computation.crossings.is_empty());
+    assert_close(computation.protocol_amount, dec!("0.015"), dec!("0.000000001"));
+    assert_close(computation.fee_growth_global * dec!("2000"), dec!("0.015"), dec!("0.000000001"));
+  }
+
+  #[test]
+  fn swap_beyond_the_liquidity_is_not_filled() {
+    let (ticks, initialized_ticks): (HashMap<i64, Tick>, Vec<i64>) = nested_positions();
+    let computation: SwapComputation = swap_through_ticks(
+      start_at_price_one(), &ticks, &initialized_ticks, dec!("0.3"), Decimal::zero(), true, dec!("100"), false
+    );
+
+    assert!(!computation.filled);
+    assert_eq!(computation.liquidity, Decimal::zero());
+    assert_eq!(computation.crossings.len(), 2);
+  }
+}
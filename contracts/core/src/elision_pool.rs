@@ -1,30 +1,717 @@
-pub struct PriceOracle {}
+use scrypto::prelude::*;
+use sbor::*;
+use crate::collateral::*;
+use crate::options::{Option, OptionState, OptionType, OptionWriter, OptionsController, collateral_required};
 
-#[derive(TypeId, Encode, Decode, Describe)]
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
 pub enum TrancheState {
   Invalid,
   Open,
   Closed
 }
 
+/// Non-fungible data held by the liquidity provider of a tranche
+///
+/// # Contains:
+/// * `index`: u64 - Position of the tranche in the pool
+/// * `hedged`: bool - Whether the tranche was provided to the hedged side of the pool
+/// * `amount`: Decimal - Amount of tokens provided
+/// * `creation_epoch`: u64 - Epoch at which the tranche was opened
+#[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
+pub struct TrancheToken {
+  pub index: u64,
+  pub hedged: bool,
+  pub amount: Decimal,
+  pub creation_epoch: u64
+}
+
 #[derive(TypeId, Encode, Decode, Describe)]
 pub struct Tranche {
-  state: TrancheState,
-  amount: Decimal,
-  share: Decimal,
-  creation_epoch: u64,
-  hedged: Boolean,
+  pub state: TrancheState,
+  pub amount: Decimal,
+  pub share: Decimal,
+  pub creation_epoch: u64,
+  pub lockup_epochs: u64,
+  pub hedged: bool,
 }
 
 blueprint! {
+  /// Structure representing the Elision Pool that underwrites options from pooled liquidity
+  ///
+  /// # Contains:
+  /// * `ep_admin_badge`: Vault - Badge that gives authority to mint and burn tranche tokens
+  /// * `options_controller`: OptionsController - Options market the pool writes options on
+  /// * `option_type`: OptionType - Type of options the pool writes, calls for the underlying, puts for the quote
+  /// * `liquidity`: Vault - Liquidity that is not locked as collateral of written options
+  /// * `tranche_nft_address`: ResourceAddress - Non-fungible tokens issued to liquidity providers
+  /// * `writer_nfts`: Vault - `OptionWriter` tokens of the options written by the pool
+  /// * `hedged_balance`: Decimal - Liquidity owned by the hedged tranches, only charged for losses the unhedged side cannot cover
+  /// * `unhedged_balance`: Decimal - Liquidity owned by the unhedged tranches, charged first for losses of exercised options
+  /// * `hedged_share`: Decimal - Total shares of the hedged tranches
+  /// * `unhedged_share`: Decimal - Total shares of the unhedged tranches
+  /// * `locked_amount`: Decimal - Liquidity locked as collateral of active options
+  /// * `minimum_collateralization_ratio`: Decimal - Minimum ratio of pool balance to locked collateral
+  /// * `lockup_epochs`: u64 - Number of epochs a tranche has to stay open before it can be withdrawn
+  /// * `early_withdrawal_penalty`: Decimal - Percentage of a tranche paid to the remaining tranches when withdrawn early (0-100)
+  /// * `max_utilization`: Decimal - Maximum percentage of the pool balance that can be locked by options (0-100)
+  /// * `utilization_multiplier`: Decimal - Rate at which the premium rises with the utilization of the pool
+  /// * `options`: HashMap<NonFungibleId, Option> - Records of the options written by the pool
+  /// * `tranches`: Vec<Tranche> - Tranches provided to the pool
   struct ElisionPool {
     ep_admin_badge: Vault,
-    price_oracle: Option<PriceOracle>,
+    options_controller: OptionsController,
+    option_type: OptionType,
+    liquidity: Vault,
+    tranche_nft_address: ResourceAddress,
+    writer_nfts: Vault,
     hedged_balance: Decimal,
     unhedged_balance: Decimal,
-    options: HashMap<ResourceAddress, Option>
+    hedged_share: Decimal,
+    unhedged_share: Decimal,
+    locked_amount: Decimal,
+    minimum_collateralization_ratio: Decimal,
+    lockup_epochs: u64,
+    early_withdrawal_penalty: Decimal,
+    max_utilization: Decimal,
+    utilization_multiplier: Decimal,
+    options: HashMap<NonFungibleId, Option>,
     tranches: Vec<Tranche>
   }
 
-  impl ElisionPool {}
-}
\ No newline at end of file
+  impl ElisionPool {
+    /// Create new Elision Pool that writes options on the given options market
+    ///
+    /// # Arguments:
+    /// * `liquidity_address`: ResourceAddress - Token provided to the pool, the underlying for calls or the quote for puts
+    /// * `options_controller`: ComponentAddress - OptionsController component the pool writes options on
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - ElisionPool component address
+    /// * `Bucket` - Contains the admin badge that has the authority to update pool parameters
+    pub fn new(
+      liquidity_address: ResourceAddress,
+      options_controller: ComponentAddress
+    ) -> (ComponentAddress, Bucket) {
+      // Determine which type of options can be covered by the pool liquidity
+      let options_controller: OptionsController = options_controller.into();
+      let option_type: OptionType = if options_controller.collateral_address(OptionType::Call) == liquidity_address {
+        OptionType::Call
+      } else if options_controller.collateral_address(OptionType::Put) == liquidity_address {
+        OptionType::Put
+      } else {
+        panic!("[Pool Creation]: Token cannot be used as collateral on the options market.")
+      };
+      info!("[Pool Creation]: Creating new pool writing {:?} options", option_type);
+
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Elision Pool Admin Badge")
+        .metadata("symbol", "EPAB")
+        .metadata("description", "Admin Badge with the authority to update the parameters of an Elision Pool")
+        .initial_supply(1);
+
+      // Create badge that gives the pool authority over tranche tokens
+      let ep_admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Tranche Minter Badge")
+        .metadata("symbol", "TMB")
+        .metadata("description", "Badge with the authority to mint and burn tranche tokens")
+        .initial_supply(1);
+
+      let tranche_nft_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", "Elision Pool Tranche")
+        .metadata("symbol", "EPT")
+        .metadata("description", "Token used to track a tranche of liquidity provided to an Elision Pool.")
+        .mintable(rule!(require(ep_admin_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(ep_admin_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = AccessRules::new()
+        .method("set_minimum_collateralization_ratio", rule!(require(admin_badge.resource_address())))
+        .method("set_lockup", rule!(require(admin_badge.resource_address())))
+        .method("set_utilization_parameters", rule!(require(admin_badge.resource_address())))
+        .default(rule!(allow_all));
+
+      let mut elision_pool = Self {
+        ep_admin_badge: Vault::with_bucket(ep_admin_badge),
+        writer_nfts: Vault::new(options_controller.writer_nft_address()),
+        options_controller: options_controller,
+        option_type: option_type,
+        liquidity: Vault::new(liquidity_address),
+        tranche_nft_address: tranche_nft_address,
+        hedged_balance: Decimal::zero(),
+        unhedged_balance: Decimal::zero(),
+        hedged_share: Decimal::zero(),
+        unhedged_share: Decimal::zero(),
+        locked_amount: Decimal::zero(),
+        minimum_collateralization_ratio: Decimal::one(),
+        lockup_epochs: 0,
+        early_withdrawal_penalty: Decimal::zero(),
+        max_utilization: dec!("80"),
+        utilization_multiplier: Decimal::one(),
+        options: HashMap::new(),
+        tranches: Vec::new()
+      }
+      .instantiate();
+      elision_pool.add_access_check(access_rules);
+
+      return (elision_pool.globalize(), admin_badge);
+    }
+
+    /// Updates the Minimum Collateralization Ratio of pool balance to locked collateral
+    ///
+    /// # Arguments:
+    /// * `mcr`: Decimal - Minimum Collateralization Ratio, `1.5` being 150%
+    pub fn set_minimum_collateralization_ratio(
+      &mut self,
+      mcr: Decimal
+    ) {
+      assert_valid_mcr(mcr, String::from("Set MCR"));
+      self.minimum_collateralization_ratio = mcr;
+      info!("[Set MCR]: Minimum collateralization ratio set to {}", mcr);
+    }
+
+    /// Updates the lockup period of tranches and the penalty for withdrawing within it
+    ///
+    /// # Arguments:
+    /// * `lockup_epochs`: u64 - Number of epochs a tranche has to stay open before it can be withdrawn
+    /// * `early_withdrawal_penalty`: Decimal - Percentage of a tranche paid to the remaining tranches when withdrawn early (0-100)
+    ///
+    /// # Note:
+    /// * Tranches keep the lockup period they were opened with, only tranches opened afterwards use the new one
+    pub fn set_lockup(
+      &mut self,
+      lockup_epochs: u64,
+      early_withdrawal_penalty: Decimal
+    ) {
+      assert!(
+        (early_withdrawal_penalty >= Decimal::zero()) & (early_withdrawal_penalty <= dec!("100")),
+        "[Set Lockup]: Penalty must be between 0 and 100."
+      );
+      self.lockup_epochs = lockup_epochs;
+      self.early_withdrawal_penalty = early_withdrawal_penalty;
+      info!("[Set Lockup]: Lockup set to {} epochs with an early-withdrawal penalty of {}", lockup_epochs, early_withdrawal_penalty);
+    }
+
+    /// Updates the utilization cap of the pool and the rate at which premiums rise with utilization
+    ///
+    /// # Arguments:
+    /// * `max_utilization`: Decimal - Maximum percentage of the pool balance that can be locked by options (0-100)
+    /// * `utilization_multiplier`: Decimal - Premiums are multiplied by `1 + utilization_multiplier * utilization / 100`
+    pub fn set_utilization_parameters(
+      &mut self,
+      max_utilization: Decimal,
+      utilization_multiplier: Decimal
+    ) {
+      assert!(
+        (max_utilization > Decimal::zero()) & (max_utilization <= dec!("100")),
+        "[Set Utilization]: Maximum utilization must be greater than 0 and at most 100."
+      );
+      assert!(utilization_multiplier >= Decimal::zero(), "[Set Utilization]: Multiplier cannot be negative.");
+      self.max_utilization = max_utilization;
+      self.utilization_multiplier = utilization_multiplier;
+      info!("[Set Utilization]: Maximum utilization: {}, Multiplier: {}", max_utilization, utilization_multiplier);
+    }
+
+    /// Obtain the liquidity owned by all tranches, including locked collateral
+    ///
+    /// # Returns:
+    /// * `Decimal` - Sum of the hedged and unhedged balances
+    pub fn total_balance(&self) -> Decimal {
+      return self.hedged_balance + self.unhedged_balance;
+    }
+
+    /// Obtain the liquidity that is not locked as collateral
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of tokens available to write options or pay out withdrawals
+    pub fn available_balance(&self) -> Decimal {
+      return self.liquidity.amount();
+    }
+
+    /// Obtain the percentage of the pool balance locked as collateral of active options
+    ///
+    /// # Returns:
+    /// * `Decimal` - Utilization of the pool (0-100)
+    pub fn utilization(&self) -> Decimal {
+      return self.utilization_after(Decimal::zero());
+    }
+
+    /// Calculates the utilization of the pool once more collateral has been locked
+    ///
+    /// # Arguments:
+    /// * `additional_locked`: Decimal - Amount of collateral about to be locked
+    ///
+    /// # Returns:
+    /// * `Decimal` - Utilization of the pool (0-100)
+    fn utilization_after(
+      &self,
+      additional_locked: Decimal
+    ) -> Decimal {
+      if self.total_balance() == Decimal::zero() {
+        return Decimal::zero();
+      }
+      return (self.locked_amount + additional_locked) * dec!("100") / self.total_balance();
+    }
+
+    /// Calculates the amount of liquidity locked to write an option
+    ///
+    /// # Arguments:
+    /// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+    /// * `amount`: Decimal - Units of the underlying token covered by the option
+    ///
+    /// # Returns:
+    /// * `Decimal` - Collateral satisfying the collateral requirement of the options market
+    fn locked_amount_for(
+      &self,
+      strike: Decimal,
+      amount: Decimal
+    ) -> Decimal {
+      let mcr: Decimal = self.options_controller.minimum_collateralization_ratio();
+      return collateral_required(self.option_type, strike, amount) * mcr;
+    }
+
+    /// Quotes the premium charged by the pool for an option, including the utilization surcharge
+    ///
+    /// # Arguments:
+    /// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+    /// * `amount`: Decimal - Units of the underlying token covered by the option
+    /// * `expiry_epoch`: u64 - Epoch from which the option can no longer be exercised
+    ///
+    /// # Returns:
+    /// * `Decimal` - Premium in the pool token
+    ///
+    /// # Note:
+    /// * The premium quoted by the options market is multiplied by `1 + utilization_multiplier * utilization / 100`,
+    ///   where the utilization is measured after the option has been written
+    pub fn quote_premium(
+      &self,
+      strike: Decimal,
+      amount: Decimal,
+      expiry_epoch: u64
+    ) -> Decimal {
+      let utilization: Decimal = self.utilization_after(self.locked_amount_for(strike, amount));
+      let multiplier: Decimal = Decimal::one() + self.utilization_multiplier * utilization / dec!("100");
+      return self.options_controller.quote_premium(self.option_type, strike, amount, expiry_epoch) * multiplier;
+    }
+
+    /// Asserts that the pool balance left after a withdrawal still covers the locked collateral at the MCR
+    ///
+    /// # Arguments:
+    /// * `withdrawn_amount`: Decimal - Amount of liquidity about to leave the pool
+    /// * `label`: String - Label for the assertion output
+    ///
+    /// # Note:
+    /// * The balance and the locked collateral are both in the pool token, so they are compared without prices
+    fn assert_collateralized_after(
+      &self,
+      withdrawn_amount: Decimal,
+      label: String
+    ) {
+      // A pool without locked collateral cannot be undercollateralized
+      if self.locked_amount == Decimal::zero() {
+        return;
+      }
+      let ratio: Decimal = collateralization_ratio(
+        self.total_balance() - withdrawn_amount, Decimal::one(), self.locked_amount, Decimal::one()
+      );
+      assert!(
+        ratio >= self.minimum_collateralization_ratio,
+        "[{}]: Operation would push the collateralization ratio below the minimum of {}.",
+        label, self.minimum_collateralization_ratio
+      );
+    }
+
+    /// Settles every option of the pool that has been exercised or has reached its expiry, so that its loss is
+    /// booked before the pool is valued
+    fn settle_finished_options(&mut self) {
+      let current_epoch: u64 = Runtime::current_epoch();
+      let finished: Vec<NonFungibleId> = self.options.iter()
+        .filter(|(option_id, option)| {
+          (option.state == OptionState::Active) & (
+            (current_epoch >= option.expiry_epoch)
+              | (self.options_controller.option_state((*option_id).clone()) != OptionState::Active)
+          )
+        })
+        .map(|(option_id, _)| option_id.clone())
+        .collect();
+      for option_id in finished {
+        self.settle(option_id);
+      }
+    }
+
+    /// Credits earned premiums to the hedged and unhedged balances in proportion to their size
+    ///
+    /// # Arguments:
+    /// * `amount`: Decimal - Amount of tokens earned by the pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Part of the amount credited to the hedged side
+    /// * `Decimal` - Part of the amount credited to the unhedged side
+    fn distribute_profit(
+      &mut self,
+      amount: Decimal
+    ) -> (Decimal, Decimal) {
+      let hedged_part: Decimal = amount * self.hedged_balance / self.total_balance();
+      let unhedged_part: Decimal = amount - hedged_part;
+      self.hedged_balance = self.hedged_balance + hedged_part;
+      self.unhedged_balance = self.unhedged_balance + unhedged_part;
+      return (hedged_part, unhedged_part);
+    }
+
+    /// Charges losses of exercised options to the unhedged side, which covers the hedged side
+    ///
+    /// # Arguments:
+    /// * `amount`: Decimal - Amount of tokens lost by the pool
+    ///
+    /// # Note:
+    /// * The hedged side is only charged for the part of the loss that exceeds the unhedged balance
+    fn distribute_loss(
+      &mut self,
+      amount: Decimal
+    ) {
+      let unhedged_part: Decimal = if amount < self.unhedged_balance { amount } else { self.unhedged_balance };
+      self.unhedged_balance = self.unhedged_balance - unhedged_part;
+      self.hedged_balance = self.hedged_balance - (amount - unhedged_part);
+      info!("[Distribute Loss]: Unhedged side charged {}, hedged side charged {}", unhedged_part, amount - unhedged_part);
+    }
+
+    /// Clears the shares of a side of the pool whose balance has been lost
+    ///
+    /// # Arguments:
+    /// * `hedged`: bool - Whether the hedged or unhedged side is reset
+    fn reset_side(
+      &mut self,
+      hedged: bool
+    ) {
+      for tranche in self.tranches.iter_mut() {
+        if (tranche.hedged == hedged) & (tranche.state == TrancheState::Open) {
+          tranche.share = Decimal::zero();
+        }
+      }
+      if hedged {
+        self.hedged_share = Decimal::zero();
+      } else {
+        self.unhedged_share = Decimal::zero();
+      }
+      info!("[Reset Side]: Side of the pool was depleted, hedged: {}", hedged);
+    }
+
+    /// Provides liquidity to the pool by opening a new tranche
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the tokens to provide
+    /// * `hedged`: bool - Whether the tranche joins the hedged or unhedged side of the pool
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tranche token issued to the liquidity provider
+    pub fn provide_liquidity(
+      &mut self,
+      tokens: Bucket,
+      hedged: bool
+    ) -> Bucket {
+      assert_eq!(
+        tokens.resource_address(), self.liquidity.resource_address(),
+        "[Provide Liquidity]: Token does not belong to this pool."
+      );
+      assert!(!tokens.is_empty(), "[Provide Liquidity]: Cannot provide liquidity from an empty bucket.");
+
+      // A side drained by losses starts a fresh share base, its remaining tranches have nothing left to claim
+      let depleted: bool = if hedged {
+        (self.hedged_balance == Decimal::zero()) & (self.hedged_share > Decimal::zero())
+      } else {
+        (self.unhedged_balance == Decimal::zero()) & (self.unhedged_share > Decimal::zero())
+      };
+      if depleted {
+        self.reset_side(hedged);
+      }
+
+      // Compute the share of the tranche on its side of the pool
+      let amount: Decimal = tokens.amount();
+      let (balance, total_share): (&mut Decimal, &mut Decimal) = if hedged {
+        (&mut self.hedged_balance, &mut self.hedged_share)
+      } else {
+        (&mut self.unhedged_balance, &mut self.unhedged_share)
+      };
+      let share: Decimal = if *total_share == Decimal::zero() {
+        amount
+      } else {
+        amount * *total_share / *balance
+      };
+      *balance = *balance + amount;
+      *total_share = *total_share + share;
+
+      let index: u64 = self.tranches.len() as u64;
+      let creation_epoch: u64 = Runtime::current_epoch();
+      self.tranches.push(Tranche {
+        state: TrancheState::Open,
+        amount: amount,
+        share: share,
+        creation_epoch: creation_epoch,
+        lockup_epochs: self.lockup_epochs,
+        hedged: hedged
+      });
+      self.liquidity.put(tokens);
+      info!("[Provide Liquidity]: Opened tranche {} of {} with {} shares, hedged: {}", index, amount, share, hedged);
+
+      // Mint the tranche token for the liquidity provider
+      let tranche_manager: &ResourceManager = borrow_resource_manager!(self.tranche_nft_address);
+      return self.ep_admin_badge.authorize(|| {
+        tranche_manager.mint_non_fungible(&NonFungibleId::from_u64(index), TrancheToken {
+          index: index,
+          hedged: hedged,
+          amount: amount,
+          creation_epoch: creation_epoch
+        })
+      });
+    }
+
+    /// Closes a tranche and pays out its share of the pool
+    ///
+    /// # Arguments:
+    /// * `tranche_nft`: Bucket - Contains the tranche token to close
+    /// * `penalty_rate`: Decimal - Percentage of the share kept by the pool for the remaining tranches (0-100)
+    /// * `label`: String - Label of the method closing the tranche
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens owed to the liquidity provider
+    ///
+    /// # Note:
+    /// * Options of the pool that have been exercised or have expired are settled before the tranche is valued
+    fn close_tranche(
+      &mut self,
+      tranche_nft: Bucket,
+      penalty_rate: Decimal,
+      label: String
+    ) -> Bucket {
+      assert_eq!(
+        tranche_nft.resource_address(), self.tranche_nft_address,
+        "[{}]: Tranche token does not belong to this pool.", label
+      );
+      assert_eq!(tranche_nft.amount(), Decimal::one(), "[{}]: Only one tranche can be withdrawn at a time.", label);
+
+      let index: usize = tranche_nft.non_fungible::<TrancheToken>().data().index as usize;
+      assert!(
+        self.tranches[index].state == TrancheState::Open,
+        "[{}]: Tranche has already been closed.", label
+      );
+
+      // Losses of exercised options are booked first so that the tranche cannot leave at the value before them
+      self.settle_finished_options();
+
+      // Compute the value of the share of the tranche on its side of the pool
+      let share: Decimal = self.tranches[index].share;
+      let value: Decimal = if share == Decimal::zero() {
+        Decimal::zero()
+      } else if self.tranches[index].hedged {
+        share * self.hedged_balance / self.hedged_share
+      } else {
+        share * self.unhedged_balance / self.unhedged_share
+      };
+
+      // The penalty can only be paid when other tranches remain in the pool
+      let penalty: Decimal = if self.total_balance() > value {
+        value * penalty_rate / dec!("100")
+      } else {
+        Decimal::zero()
+      };
+      let payout: Decimal = value - penalty;
+
+      // Verify the pool stays collateralized and has enough free liquidity
+      self.assert_collateralized_after(payout, label.clone());
+      assert!(
+        self.liquidity.amount() >= payout,
+        "[{}]: Not enough unlocked liquidity available for the withdraw.", label
+      );
+
+      if self.tranches[index].hedged {
+        self.hedged_balance = self.hedged_balance - value;
+        self.hedged_share = self.hedged_share - share;
+      } else {
+        self.unhedged_balance = self.unhedged_balance - value;
+        self.unhedged_share = self.unhedged_share - share;
+      }
+      if penalty > Decimal::zero() {
+        self.distribute_profit(penalty);
+      }
+      self.tranches[index].state = TrancheState::Closed;
+      info!("[{}]: Closed tranche {} paying out {} with a penalty of {}", label, index, payout, penalty);
+
+      self.ep_admin_badge.authorize(|| {
+        tranche_nft.burn();
+      });
+
+      return self.liquidity.take(payout);
+    }
+
+    /// Obtain the epoch from which a tranche can be withdrawn without penalty
+    ///
+    /// # Arguments:
+    /// * `tranche_nft`: &Bucket - Contains the tranche token
+    ///
+    /// # Returns:
+    /// * `u64` - First epoch after the lockup period the tranche was opened with
+    fn unlock_epoch(
+      &self,
+      tranche_nft: &Bucket
+    ) -> u64 {
+      let index: usize = tranche_nft.non_fungible::<TrancheToken>().data().index as usize;
+      return self.tranches[index].creation_epoch + self.tranches[index].lockup_epochs;
+    }
+
+    /// Closes a tranche that has passed its lockup period and pays out its share of the pool
+    ///
+    /// # Arguments:
+    /// * `tranche_nft`: Bucket - Contains the tranche token to close
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens owed to the liquidity provider
+    pub fn withdraw(
+      &mut self,
+      tranche_nft: Bucket
+    ) -> Bucket {
+      assert!(
+        Runtime::current_epoch() >= self.unlock_epoch(&tranche_nft),
+        "[Withdraw]: Tranche is still within its lockup period."
+      );
+      return self.close_tranche(tranche_nft, Decimal::zero(), String::from("Withdraw"));
+    }
+
+    /// Closes a tranche within its lockup period, leaving the early-withdrawal penalty to the remaining tranches
+    ///
+    /// # Arguments:
+    /// * `tranche_nft`: Bucket - Contains the tranche token to close
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens owed to the liquidity provider after the penalty
+    pub fn withdraw_early(
+      &mut self,
+      tranche_nft: Bucket
+    ) -> Bucket {
+      let penalty_rate: Decimal = if Runtime::current_epoch() >= self.unlock_epoch(&tranche_nft) {
+        Decimal::zero()
+      } else {
+        self.early_withdrawal_penalty
+      };
+      return self.close_tranche(tranche_nft, penalty_rate, String::from("Withdraw Early"));
+    }
+
+    /// Writes an option from the pool liquidity and sells it to the caller at the premium quoted by the pool
+    ///
+    /// # Arguments:
+    /// * `payment`: Bucket - Contains the premium, paid in the pool token
+    /// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+    /// * `amount`: Decimal - Units of the underlying token covered by the option
+    /// * `expiry_epoch`: u64 - Epoch from which the option can no longer be exercised
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the `OptionTrade` token of the bought option
+    /// * `Bucket` - Contains the remaining payment tokens
+    pub fn buy_option(
+      &mut self,
+      mut payment: Bucket,
+      strike: Decimal,
+      amount: Decimal,
+      expiry_epoch: u64
+    ) -> (Bucket, Bucket) {
+      // Lock enough liquidity to satisfy the collateral requirement of the options market
+      let locked_amount: Decimal = self.locked_amount_for(strike, amount);
+      assert!(
+        self.liquidity.amount() >= locked_amount,
+        "[Buy Option]: Not enough unlocked liquidity to write the option."
+      );
+      assert!(
+        self.utilization_after(locked_amount) <= self.max_utilization,
+        "[Buy Option]: Option would push the pool above its maximum utilization."
+      );
+
+      // Collect the utilization surcharge on top of the premium charged by the options market
+      let market_premium: Decimal = self.options_controller.quote_premium(self.option_type, strike, amount, expiry_epoch);
+      let surcharge: Decimal = self.quote_premium(strike, amount, expiry_epoch) - market_premium;
+      assert_eq!(
+        payment.resource_address(), self.liquidity.resource_address(),
+        "[Buy Option]: Premium must be paid in the pool token."
+      );
+      assert!(payment.amount() >= surcharge, "[Buy Option]: Not enough tokens to pay the premium.");
+      let mut premium: Bucket = payment.take(surcharge);
+
+      // Write the option, sell it to the caller and collect the premium
+      let writer_nft: Bucket = self.options_controller.write(
+        self.liquidity.take(locked_amount),
+        self.option_type,
+        strike,
+        amount,
+        expiry_epoch
+      );
+      let option_id: NonFungibleId = writer_nft.non_fungible::<OptionWriter>().id();
+      let (option_nft, change): (Bucket, Bucket) = self.options_controller.buy(option_id.clone(), payment, market_premium);
+      premium.put(self.options_controller.claim(writer_nft.create_proof()));
+      self.writer_nfts.put(writer_nft);
+
+      // Split the premium between the hedged and unhedged sides of the pool
+      let (hedge_premium, unhedge_premium): (Decimal, Decimal) = self.distribute_profit(premium.amount());
+      self.options.insert(option_id.clone(), Option {
+        state: OptionState::Active,
+        option_type: self.option_type,
+        locked_amount: locked_amount,
+        premium: premium.amount(),
+        hedge_premium: hedge_premium,
+        unhedge_premium: unhedge_premium,
+        amount: amount,
+        created_epoch: Runtime::current_epoch(),
+        expiry_epoch: expiry_epoch,
+        settlement_fee_address: self.liquidity.resource_address(),
+        strike: strike
+      });
+      self.locked_amount = self.locked_amount + locked_amount;
+      info!(
+        "[Buy Option]: Written option {} locking {} for a premium of {}, hedged: {}, unhedged: {}",
+        option_id, locked_amount, premium.amount(), hedge_premium, unhedge_premium
+      );
+
+      self.liquidity.put(premium);
+
+      return (option_nft, change);
+    }
+
+    /// Releases the collateral of an option written by the pool once it has been exercised or expired
+    ///
+    /// Can be called by anyone. Options that reached their expiry without being exercised are expired first.
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option to settle
+    pub fn settle(
+      &mut self,
+      option_id: NonFungibleId
+    ) {
+      assert!(self.options.contains_key(&option_id), "[Settle]: Option was not written by this pool.");
+      assert!(
+        self.options[&option_id].state == OptionState::Active,
+        "[Settle]: Option has already been settled."
+      );
+
+      let mut state: OptionState = self.options_controller.option_state(option_id.clone());
+      if state == OptionState::Active {
+        self.options_controller.expire(option_id.clone());
+        state = OptionState::Expired;
+      }
+
+      // Claim the collateral that was not paid out to the option holder
+      let writer_nft: Bucket = self.writer_nfts.take_non_fungible(&option_id);
+      let returned: Bucket = self.options_controller.claim(writer_nft.create_proof());
+      self.writer_nfts.put(writer_nft);
+
+      let option: &mut Option = self.options.get_mut(&option_id).unwrap();
+      option.state = state;
+      let locked_amount: Decimal = option.locked_amount;
+      let loss: Decimal = locked_amount - returned.amount();
+
+      self.locked_amount = self.locked_amount - locked_amount;
+      self.liquidity.put(returned);
+      if loss > Decimal::zero() {
+        self.distribute_loss(loss);
+      }
+      info!("[Settle]: Settled option {} as {:?} with a loss of {}", option_id, state, loss);
+    }
+  }
+}
@@ -0,0 +1,209 @@
+use scrypto::prelude::*;
+use sbor::*;
+
+/// Curve giving the interest rates of a lending market as a function of its utilization
+pub trait InterestRateModel {
+  /// Calculates the interest rate per epoch paid by borrowers
+  ///
+  /// # Arguments:
+  /// * `utilization`: Decimal - Share of the deposits that is lent out, between 0 and 1
+  ///
+  /// # Returns:
+  /// * `Decimal` - Borrow rate per epoch, `0.0001` being 0.01%
+  fn borrow_rate(&self, utilization: Decimal) -> Decimal;
+
+  /// Calculates the interest rate per epoch earned by depositors
+  ///
+  /// # Arguments:
+  /// * `utilization`: Decimal - Share of the deposits that is lent out, between 0 and 1
+  ///
+  /// # Returns:
+  /// * `Decimal` - Supply rate where `S = R * U`, the interest of the borrowers spread over all deposits
+  fn supply_rate(&self, utilization: Decimal) -> Decimal {
+    return self.borrow_rate(utilization) * utilization;
+  }
+
+  /// Asserts that the parameters of the curve can be used
+  ///
+  /// # Arguments:
+  /// * `label`: String - Label that called the assertion method
+  fn assert_valid(&self, label: String);
+}
+
+/// Linear curve that steepens once utilization passes a kink, pushing borrowers to repay before deposits run out
+///
+/// # Contains:
+/// * `base_rate`: Decimal - Borrow rate per epoch when nothing is borrowed
+/// * `multiplier`: Decimal - Increase of the borrow rate per epoch from no utilization up to the kink
+/// * `kink`: Decimal - Utilization above which the jump multiplier applies, greater than 0 and at most 1
+/// * `jump_multiplier`: Decimal - Increase of the borrow rate per epoch from the kink up to full utilization
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
+pub struct KinkedJumpRateModel {
+  pub base_rate: Decimal,
+  pub multiplier: Decimal,
+  pub kink: Decimal,
+  pub jump_multiplier: Decimal
+}
+
+impl InterestRateModel for KinkedJumpRateModel {
+  /// # Note:
+  /// * Below the kink `R = base_rate + U * multiplier`
+  /// * Above the kink `R = base_rate + kink * multiplier + (U - kink) * jump_multiplier`
+  fn borrow_rate(&self, utilization: Decimal) -> Decimal {
+    if utilization <= self.kink {
+      return self.base_rate + utilization * self.multiplier;
+    }
+    return self.base_rate + self.kink * self.multiplier + (utilization - self.kink) * self.jump_multiplier;
+  }
+
+  fn assert_valid(&self, label: String) {
+    assert!(self.base_rate >= Decimal::zero(), "[{}]: Base rate cannot be negative.", label);
+    assert!(self.multiplier >= Decimal::zero(), "[{}]: Multiplier cannot be negative.", label);
+    assert!(
+      (self.kink > Decimal::zero()) & (self.kink <= Decimal::one()),
+      "[{}]: Kink must be greater than 0 and at most 1.",
+      label
+    );
+    assert!(self.jump_multiplier >= Decimal::zero(), "[{}]: Jump multiplier cannot be negative.", label);
+  }
+}
+
+/// Curve charging the same borrow rate at any utilization
+///
+/// # Contains:
+/// * `rate`: Decimal - Borrow rate per epoch
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
+pub struct FixedRateModel {
+  pub rate: Decimal
+}
+
+impl InterestRateModel for FixedRateModel {
+  fn borrow_rate(&self, _utilization: Decimal) -> Decimal {
+    return self.rate;
+  }
+
+  fn assert_valid(&self, label: String) {
+    assert!(self.rate >= Decimal::zero(), "[{}]: Rate cannot be negative.", label);
+  }
+}
+
+/// Any interest rate model a lending market can be configured with
+///
+/// # Variants:
+/// * `KinkedJump`: KinkedJumpRateModel - Linear curve that steepens above a kink in utilization
+/// * `Fixed`: FixedRateModel - Same borrow rate at any utilization
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
+pub enum RateModel {
+  KinkedJump(KinkedJumpRateModel),
+  Fixed(FixedRateModel)
+}
+
+impl InterestRateModel for RateModel {
+  fn borrow_rate(&self, utilization: Decimal) -> Decimal {
+    return match self {
+      RateModel::KinkedJump(model) => model.borrow_rate(utilization),
+      RateModel::Fixed(model) => model.borrow_rate(utilization)
+    };
+  }
+
+  fn supply_rate(&self, utilization: Decimal) -> Decimal {
+    return match self {
+      RateModel::KinkedJump(model) => model.supply_rate(utilization),
+      RateModel::Fixed(model) => model.supply_rate(utilization)
+    };
+  }
+
+  fn assert_valid(&self, label: String) {
+    match self {
+      RateModel::KinkedJump(model) => model.assert_valid(label),
+      RateModel::Fixed(model) => model.assert_valid(label)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinked_jump() -> KinkedJumpRateModel {
+    return KinkedJumpRateModel {
+      base_rate: dec!("0.01"),
+      multiplier: dec!("0.1"),
+      kink: dec!("0.8"),
+      jump_multiplier: dec!("2")
+    };
+  }
+
+  #[test]
+  fn kinked_jump_charges_base_rate_without_utilization() {
+    assert_eq!(kinked_jump().borrow_rate(Decimal::zero()), dec!("0.01"));
+  }
+
+  #[test]
+  fn kinked_jump_is_linear_below_kink() {
+    assert_eq!(kinked_jump().borrow_rate(dec!("0.5")), dec!("0.06"));
+    assert_eq!(kinked_jump().borrow_rate(dec!("0.8")), dec!("0.09"));
+  }
+
+  #[test]
+  fn kinked_jump_steepens_above_kink() {
+    assert_eq!(kinked_jump().borrow_rate(dec!("0.9")), dec!("0.29"));
+    assert_eq!(kinked_jump().borrow_rate(Decimal::one()), dec!("0.49"));
+  }
+
+  #[test]
+  fn kinked_jump_supply_rate_spreads_interest_over_deposits() {
+    assert_eq!(kinked_jump().supply_rate(Decimal::zero()), Decimal::zero());
+    assert_eq!(kinked_jump().supply_rate(dec!("0.5")), dec!("0.03"));
+    assert_eq!(kinked_jump().supply_rate(Decimal::one()), dec!("0.49"));
+  }
+
+  #[test]
+  fn kinked_jump_accepts_valid_parameters() {
+    kinked_jump().assert_valid(String::from("Test"));
+  }
+
+  #[test]
+  #[should_panic(expected = "[Test]: Kink must be greater than 0 and at most 1.")]
+  fn kinked_jump_rejects_kink_above_one() {
+    let model: KinkedJumpRateModel = KinkedJumpRateModel { kink: dec!("1.5"), ..kinked_jump() };
+    model.assert_valid(String::from("Test"));
+  }
+
+  #[test]
+  #[should_panic(expected = "[Test]: Base rate cannot be negative.")]
+  fn kinked_jump_rejects_negative_base_rate() {
+    let model: KinkedJumpRateModel = KinkedJumpRateModel { base_rate: dec!("-0.01"), ..kinked_jump() };
+    model.assert_valid(String::from("Test"));
+  }
+
+  #[test]
+  fn fixed_rate_ignores_utilization() {
+    let model: FixedRateModel = FixedRateModel { rate: dec!("0.02") };
+    assert_eq!(model.borrow_rate(Decimal::zero()), dec!("0.02"));
+    assert_eq!(model.borrow_rate(dec!("0.5")), dec!("0.02"));
+    assert_eq!(model.borrow_rate(Decimal::one()), dec!("0.02"));
+  }
+
+  #[test]
+  fn fixed_rate_supply_rate_follows_utilization() {
+    let model: FixedRateModel = FixedRateModel { rate: dec!("0.02") };
+    assert_eq!(model.supply_rate(Decimal::zero()), Decimal::zero());
+    assert_eq!(model.supply_rate(dec!("0.25")), dec!("0.005"));
+  }
+
+  #[test]
+  #[should_panic(expected = "[Test]: Rate cannot be negative.")]
+  fn fixed_rate_rejects_negative_rate() {
+    FixedRateModel { rate: dec!("-0.02") }.assert_valid(String::from("Test"));
+  }
+
+  #[test]
+  fn rate_model_delegates_to_its_curve() {
+    let kinked: RateModel = RateModel::KinkedJump(kinked_jump());
+    let fixed: RateModel = RateModel::Fixed(FixedRateModel { rate: dec!("0.02") });
+    assert_eq!(kinked.borrow_rate(dec!("0.9")), kinked_jump().borrow_rate(dec!("0.9")));
+    assert_eq!(kinked.supply_rate(dec!("0.9")), kinked_jump().supply_rate(dec!("0.9")));
+    assert_eq!(fixed.borrow_rate(dec!("0.9")), dec!("0.02"));
+  }
+}
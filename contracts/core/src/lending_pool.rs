@@ -0,0 +1,805 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::utils::*;
+use crate::oracle::*;
+use crate::pricing::pow;
+use crate::collateral::*;
+use crate::interest_rate::*;
+
+/// Non-fungible data held by a borrower of a lending pool
+///
+/// # Contains:
+/// * `collateral_address`: ResourceAddress - Token locked as collateral for the loan
+#[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
+pub struct LoanData {
+  pub collateral_address: ResourceAddress
+}
+
+/// Record of a loan drawn from a lending pool
+///
+/// # Contains:
+/// * `collateral_address`: ResourceAddress - Token locked as collateral for the loan
+/// * `scaled_debt`: Decimal - Debt of the loan divided by the borrow index, grows with the index as interest accrues
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, Debug)]
+pub struct Loan {
+  pub collateral_address: ResourceAddress,
+  pub scaled_debt: Decimal
+}
+
+blueprint! {
+  /// Structure representing a no-loss lending pool of a single token
+  ///
+  /// # Contains:
+  /// * `vault`: Vault - Deposited tokens that are not lent out
+  /// * `receipt_token_address`: ResourceAddress - Interest-bearing tokens issued to depositors
+  /// * `receipt_token_admin_badge`: Vault - Badge that gives authority to mint and burn receipt and loan tokens
+  /// * `loan_nft_address`: ResourceAddress - Non-fungible `LoanData` tokens issued to borrowers
+  /// * `price_oracle`: PriceOracle - Oracle used to value collateral against debt
+  /// * `minimum_collateralization_ratio`: Decimal - Minimum ratio of collateral value to debt value of a loan
+  /// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in collateral to liquidators (0-100)
+  /// * `close_factor`: Decimal - Percentage of the debt of a loan a single liquidation can repay (0-100)
+  /// * `collateral_tokens`: HashSet<ResourceAddress> - Tokens accepted as collateral
+  /// * `rate_model`: RateModel - Interest rate curve chosen by governance
+  /// * `total_borrows`: Decimal - Outstanding debt of all loans including accrued interest
+  /// * `borrow_index`: Decimal - Growth of a unit of debt since the pool was created
+  /// * `last_accrual_epoch`: u64 - Epoch in which interest was last accrued
+  /// * `loans`: HashMap<NonFungibleId, Loan> - Records of every open loan
+  /// * `collateral_vaults`: HashMap<NonFungibleId, Vault> - Collateral held for each loan
+  /// * `loan_counter`: u64 - Id assigned to the next loan
+  struct LendingPool {
+    vault: Vault,
+    receipt_token_address: ResourceAddress,
+    receipt_token_admin_badge: Vault,
+    loan_nft_address: ResourceAddress,
+    price_oracle: PriceOracle,
+    minimum_collateralization_ratio: Decimal,
+    liquidation_bonus: Decimal,
+    close_factor: Decimal,
+    collateral_tokens: HashSet<ResourceAddress>,
+    rate_model: RateModel,
+    total_borrows: Decimal,
+    borrow_index: Decimal,
+    last_accrual_epoch: u64,
+    loans: HashMap<NonFungibleId, Loan>,
+    collateral_vaults: HashMap<NonFungibleId, Vault>,
+    loan_counter: u64
+  }
+
+  impl LendingPool {
+    /// Creates a new lending pool for the given token
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token lent out by the pool
+    /// * `price_oracle`: ComponentAddress - PriceOracle component used to value collateral against debt
+    /// * `mcr`: Decimal - Minimum ratio of collateral value to debt value of a loan, `1.5` being 150%
+    /// * `rate_model`: RateModel - Interest rate curve of the pool, rates being per epoch
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - LendingPool component address for the new pool
+    /// * `Bucket` - Contains the admin badge that has the authority to update pool parameters
+    pub fn new(
+      resource_address: ResourceAddress,
+      price_oracle: ComponentAddress,
+      mcr: Decimal,
+      rate_model: RateModel
+    ) -> (ComponentAddress, Bucket) {
+      assert_ne!(
+        borrow_resource_manager!(resource_address).resource_type(),
+        ResourceType::NonFungible,
+        "[Lending Pool Creation]: Lent token must be fungible."
+      );
+      assert_valid_mcr(mcr, String::from("Lending Pool Creation"));
+      rate_model.assert_valid(String::from("Lending Pool Creation"));
+
+      let symbol: String = token_symbol(resource_address);
+      info!("[Lending Pool Creation]: Creating new lending pool for {}", symbol);
+
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Lending Pool Admin Badge")
+        .metadata("symbol", "LPAB")
+        .metadata("description", "Admin Badge with the authority to update the parameters of a lending pool")
+        .initial_supply(1);
+
+      // Create badge for the lending pool to give authority to mint and burn
+      let receipt_token_admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Receipt Token Admin Badge")
+        .metadata("symbol", "RTAB")
+        .metadata("description", "Admin Badge with the authority to mint and burn receipt and loan tokens")
+        .initial_supply(1);
+
+      let receipt_token_address: ResourceAddress = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_MAXIMUM)
+        .metadata("name", format!("{} Lending Receipt", symbol))
+        .metadata("symbol", format!("e{}", symbol))
+        .metadata("description", "Token used to track the share of a depositor in a lending pool, including interest.")
+        .mintable(rule!(require(receipt_token_admin_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(receipt_token_admin_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let loan_nft_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", format!("{} Loan", symbol))
+        .metadata("symbol", "ELOAN")
+        .metadata("description", "Token giving the holder the claim on the collateral of a loan drawn on Elision.")
+        .mintable(rule!(require(receipt_token_admin_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(receipt_token_admin_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = AccessRules::new()
+        .method("set_rate_model", rule!(require(admin_badge.resource_address())))
+        .method("set_minimum_collateralization_ratio", rule!(require(admin_badge.resource_address())))
+        .method("set_liquidation_parameters", rule!(require(admin_badge.resource_address())))
+        .method("add_collateral_token", rule!(require(admin_badge.resource_address())))
+        .method("remove_collateral_token", rule!(require(admin_badge.resource_address())))
+        .default(rule!(allow_all));
+
+      let mut lending_pool = Self {
+        vault: Vault::new(resource_address),
+        receipt_token_address: receipt_token_address,
+        receipt_token_admin_badge: Vault::with_bucket(receipt_token_admin_badge),
+        loan_nft_address: loan_nft_address,
+        price_oracle: price_oracle.into(),
+        minimum_collateralization_ratio: mcr,
+        liquidation_bonus: default_liquidation_bonus(),
+        close_factor: default_close_factor(),
+        collateral_tokens: HashSet::new(),
+        rate_model: rate_model,
+        total_borrows: Decimal::zero(),
+        borrow_index: Decimal::one(),
+        last_accrual_epoch: Runtime::current_epoch(),
+        loans: HashMap::new(),
+        collateral_vaults: HashMap::new(),
+        loan_counter: 0
+      }
+      .instantiate();
+      lending_pool.add_access_check(access_rules);
+
+      return (lending_pool.globalize(), admin_badge);
+    }
+
+    /// Replaces the interest rate curve of the pool, interest up to the current epoch accrues at the old rates
+    ///
+    /// # Arguments:
+    /// * `rate_model`: RateModel - New interest rate curve, rates being per epoch
+    pub fn set_rate_model(
+      &mut self,
+      rate_model: RateModel
+    ) {
+      rate_model.assert_valid(String::from("Set Rate Model"));
+      self.accrue_interest();
+
+      self.rate_model = rate_model;
+      info!("[Set Rate Model]: Interest rate model set to {:?}", rate_model);
+    }
+
+    /// Obtain the interest rate curve of the pool
+    ///
+    /// # Returns:
+    /// * `RateModel` - Interest rate curve, rates being per epoch
+    pub fn rate_model(&self) -> RateModel {
+      return self.rate_model;
+    }
+
+    /// Updates the Minimum Collateralization Ratio that loans have to satisfy
+    ///
+    /// # Arguments:
+    /// * `mcr`: Decimal - Minimum ratio of collateral value to debt value, `1.5` being 150%
+    pub fn set_minimum_collateralization_ratio(
+      &mut self,
+      mcr: Decimal
+    ) {
+      assert_valid_mcr(mcr, String::from("Set MCR"));
+      self.minimum_collateralization_ratio = mcr;
+      info!("[Set MCR]: Minimum collateralization ratio set to {}", mcr);
+    }
+
+    /// Updates what liquidators of undercollateralized loans can repay and seize
+    ///
+    /// # Arguments:
+    /// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in collateral (0-100)
+    /// * `close_factor`: Decimal - Percentage of the debt of a loan a single liquidation can repay (0-100)
+    pub fn set_liquidation_parameters(
+      &mut self,
+      liquidation_bonus: Decimal,
+      close_factor: Decimal
+    ) {
+      assert_liquidation_parameters(liquidation_bonus, close_factor, String::from("Liquidation Parameters"));
+      self.liquidation_bonus = liquidation_bonus;
+      self.close_factor = close_factor;
+      info!("[Liquidation Parameters]: Liquidation bonus: {}, Close factor: {}", liquidation_bonus, close_factor);
+    }
+
+    /// Accepts a token as collateral for new loans
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to accept, must be priced by the oracle
+    pub fn add_collateral_token(
+      &mut self,
+      resource_address: ResourceAddress
+    ) {
+      assert_ne!(
+        resource_address, self.vault.resource_address(),
+        "[Add Collateral Token]: The lent token cannot be used as collateral."
+      );
+      assert_ne!(
+        borrow_resource_manager!(resource_address).resource_type(),
+        ResourceType::NonFungible,
+        "[Add Collateral Token]: Collateral must be fungible."
+      );
+      self.collateral_tokens.insert(resource_address);
+      info!("[Add Collateral Token]: Accepting {} as collateral", token_symbol(resource_address));
+    }
+
+    /// Stops accepting a token as collateral for new loans, open loans keep their collateral
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to stop accepting
+    pub fn remove_collateral_token(
+      &mut self,
+      resource_address: ResourceAddress
+    ) {
+      assert!(
+        self.collateral_tokens.remove(&resource_address),
+        "[Remove Collateral Token]: Token is not accepted as collateral."
+      );
+      info!("[Remove Collateral Token]: No longer accepting {} as collateral", token_symbol(resource_address));
+    }
+
+    /// Obtain the address of the token lent out by the pool
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the lent token
+    pub fn resource_address(&self) -> ResourceAddress {
+      return self.vault.resource_address();
+    }
+
+    /// Obtain the address of the tokens issued to depositors
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the receipt tokens
+    pub fn receipt_token_address(&self) -> ResourceAddress {
+      return self.receipt_token_address;
+    }
+
+    /// Obtain the address of the tokens issued to borrowers
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the `LoanData` tokens
+    pub fn loan_nft_address(&self) -> ResourceAddress {
+      return self.loan_nft_address;
+    }
+
+    /// Obtain the Minimum Collateralization Ratio that loans have to satisfy
+    ///
+    /// # Returns:
+    /// * `Decimal` - Minimum ratio of collateral value to debt value
+    pub fn minimum_collateralization_ratio(&self) -> Decimal {
+      return self.minimum_collateralization_ratio;
+    }
+
+    /// Obtain what liquidators of undercollateralized loans can repay and seize
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the repaid value paid on top in collateral
+    /// * `Decimal` - Percentage of the debt of a loan a single liquidation can repay
+    pub fn liquidation_parameters(&self) -> (Decimal, Decimal) {
+      return (self.liquidation_bonus, self.close_factor);
+    }
+
+    /// Obtain the tokens accepted as collateral for new loans
+    ///
+    /// # Returns:
+    /// * `Vec<ResourceAddress>` - Addresses of the accepted tokens
+    pub fn collateral_tokens(&self) -> Vec<ResourceAddress> {
+      return self.collateral_tokens.iter().cloned().collect();
+    }
+
+    /// Obtain the tokens available to borrow or redeem
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of deposited tokens that are not lent out
+    pub fn cash(&self) -> Decimal {
+      return self.vault.amount();
+    }
+
+    /// Obtain the outstanding debt of all loans including interest accrued up to the current epoch
+    ///
+    /// # Returns:
+    /// * `Decimal` - Total debt owed to the pool
+    pub fn total_borrows(&self) -> Decimal {
+      return self.total_borrows * self.pending_interest_factor();
+    }
+
+    /// Calculates the share of the deposits of the pool that is lent out
+    ///
+    /// # Returns:
+    /// * `Decimal` - Utilization where `U = B / (C + B)`, between 0 and 1
+    ///
+    /// # Note:
+    /// * `B` - The total borrows of the pool
+    /// * `C` - The cash of the pool
+    pub fn utilization(&self) -> Decimal {
+      let total_borrows: Decimal = self.total_borrows();
+      let total_assets: Decimal = self.vault.amount() + total_borrows;
+      if total_assets == Decimal::zero() {
+        return Decimal::zero();
+      }
+      return total_borrows / total_assets;
+    }
+
+    /// Calculates the interest rate per epoch paid by borrowers at the current utilization
+    ///
+    /// # Returns:
+    /// * `Decimal` - Borrow rate given by the interest rate model
+    pub fn borrow_rate(&self) -> Decimal {
+      return self.rate_model.borrow_rate(self.utilization());
+    }
+
+    /// Calculates the interest rate per epoch earned by depositors at the current utilization
+    ///
+    /// # Returns:
+    /// * `Decimal` - Supply rate given by the interest rate model
+    pub fn supply_rate(&self) -> Decimal {
+      return self.rate_model.supply_rate(self.utilization());
+    }
+
+    /// Calculates the amount of lent tokens a single receipt token can be redeemed for
+    ///
+    /// # Returns:
+    /// * `Decimal` - Exchange rate where `E = (C + B) / S`, one while no receipt tokens exist
+    ///
+    /// # Note:
+    /// * `S` - The total supply of receipt tokens
+    pub fn exchange_rate(&self) -> Decimal {
+      let receipt_supply: Decimal = borrow_resource_manager!(self.receipt_token_address).total_supply();
+      if receipt_supply == Decimal::zero() {
+        return Decimal::one();
+      }
+      return (self.vault.amount() + self.total_borrows()) / receipt_supply;
+    }
+
+    /// Asserts that a loan exists in this pool
+    ///
+    /// # Arguments:
+    /// * `loan_id`: &NonFungibleId - Id of the loan
+    /// * `label`: String - Label that called the assertion method
+    pub fn assert_loan_exists(
+      &self,
+      loan_id: &NonFungibleId,
+      label: String
+    ) {
+      assert!(
+        self.loans.contains_key(loan_id),
+        "[{}]: Loan does not exist in this lending pool.",
+        label
+      );
+    }
+
+    /// Obtain the debt of a loan including interest accrued up to the current epoch
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of lent tokens owed on the loan
+    pub fn debt(
+      &self,
+      loan_id: NonFungibleId
+    ) -> Decimal {
+      self.assert_loan_exists(&loan_id, String::from("Debt"));
+      return self.loans[&loan_id].scaled_debt * self.borrow_index * self.pending_interest_factor();
+    }
+
+    /// Obtain the collateral locked for a loan
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of collateral tokens locked
+    pub fn collateral(
+      &self,
+      loan_id: NonFungibleId
+    ) -> Decimal {
+      self.assert_loan_exists(&loan_id, String::from("Collateral"));
+      return self.collateral_vaults[&loan_id].amount();
+    }
+
+    /// Calculates the collateralization ratio of a loan
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    ///
+    /// # Returns:
+    /// * `Decimal` - Ratio of the value of the collateral to the value of the debt
+    pub fn collateralization_ratio(
+      &self,
+      loan_id: NonFungibleId
+    ) -> Decimal {
+      let debt: Decimal = self.debt(loan_id.clone());
+      return self.loan_position(&loan_id, debt).collateralization_ratio(&self.price_oracle);
+    }
+
+    /// Calculates the growth of debt since interest was last accrued
+    ///
+    /// # Returns:
+    /// * `Decimal` - Factor where `F = (1 + R)^n`
+    ///
+    /// # Note:
+    /// * `R` - The borrow rate at the utilization of the last accrual
+    /// * `n` - The number of epochs since the last accrual
+    fn pending_interest_factor(&self) -> Decimal {
+      let epochs: u64 = Runtime::current_epoch() - self.last_accrual_epoch;
+      if (epochs == 0) | (self.total_borrows == Decimal::zero()) {
+        return Decimal::one();
+      }
+
+      let total_assets: Decimal = self.vault.amount() + self.total_borrows;
+      let rate: Decimal = self.rate_model.borrow_rate(self.total_borrows / total_assets);
+      return pow(Decimal::one() + rate, Decimal::from(epochs));
+    }
+
+    /// Compounds the interest of every epoch since the last accrual into the debt of the pool
+    fn accrue_interest(&mut self) {
+      let factor: Decimal = self.pending_interest_factor();
+      if factor != Decimal::one() {
+        self.total_borrows = self.total_borrows * factor;
+        self.borrow_index = self.borrow_index * factor;
+        info!("[Accrue Interest]: Borrow index: {}, Total borrows: {}", self.borrow_index, self.total_borrows);
+      }
+      self.last_accrual_epoch = Runtime::current_epoch();
+    }
+
+    /// Obtain the collateral position of a loan
+    ///
+    /// # Arguments:
+    /// * `loan_id`: &NonFungibleId - Id of the loan
+    /// * `debt`: Decimal - Debt of the loan to value against the collateral
+    ///
+    /// # Returns:
+    /// * `Position` - Locked collateral against the debt of the loan
+    fn loan_position(
+      &self,
+      loan_id: &NonFungibleId,
+      debt: Decimal
+    ) -> Position {
+      return Position {
+        collateral_address: self.loans[loan_id].collateral_address,
+        collateral_amount: self.collateral_vaults[loan_id].amount(),
+        exposure_address: self.vault.resource_address(),
+        exposure_amount: debt
+      }
+    }
+
+    /// Obtain the id of the loan of a borrower
+    ///
+    /// # Arguments:
+    /// * `loan_proof`: &Proof - Proof of the `LoanData` token of the loan
+    /// * `label`: String - Label that called the method
+    ///
+    /// # Returns:
+    /// * `NonFungibleId` - Id of the loan
+    fn loan_id(
+      &self,
+      loan_proof: &Proof,
+      label: String
+    ) -> NonFungibleId {
+      assert_eq!(
+        loan_proof.resource_address(), self.loan_nft_address,
+        "[{}]: Loan token does not belong to this lending pool.",
+        label
+      );
+      assert_eq!(loan_proof.amount(), Decimal::one(), "[{}]: Only one loan can be managed at a time.", label);
+      return loan_proof.non_fungible::<LoanData>().id();
+    }
+
+    /// Deposits tokens into the pool in exchange for interest-bearing receipt tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the lent token to deposit
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the receipt tokens owed to the depositor
+    pub fn deposit(
+      &mut self,
+      tokens: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        tokens.resource_address(), self.vault.resource_address(),
+        "[Deposit]: Token is not lent by this lending pool."
+      );
+      assert!(!tokens.is_empty(), "[Deposit]: Cannot deposit an empty bucket.");
+      self.accrue_interest();
+
+      // Receipt tokens are minted at the exchange rate before the deposit so existing depositors keep their interest
+      let receipt_amount: Decimal = tokens.amount() / self.exchange_rate();
+      info!("[Deposit]: Depositing {}, owed receipt tokens: {}", tokens.amount(), receipt_amount);
+      self.vault.put(tokens);
+
+      let receipt_manager: &ResourceManager = borrow_resource_manager!(self.receipt_token_address);
+      return self.receipt_token_admin_badge.authorize(|| {
+        receipt_manager.mint(receipt_amount)
+      });
+    }
+
+    /// Redeems receipt tokens for the deposit they represent and the interest it earned
+    ///
+    /// # Arguments:
+    /// * `receipt_tokens`: Bucket - Contains the receipt tokens to redeem
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the lent tokens owed to the depositor
+    pub fn redeem(
+      &mut self,
+      receipt_tokens: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        receipt_tokens.resource_address(), self.receipt_token_address,
+        "[Redeem]: Receipt token does not belong to this lending pool."
+      );
+      self.accrue_interest();
+
+      let amount: Decimal = receipt_tokens.amount() * self.exchange_rate();
+      assert!(
+        amount <= self.vault.amount(),
+        "[Redeem]: Only {} tokens are available until loans are repaid.",
+        self.vault.amount()
+      );
+      info!("[Redeem]: Redeeming {} receipt tokens for {}", receipt_tokens.amount(), amount);
+
+      self.receipt_token_admin_badge.authorize(|| {
+        receipt_tokens.burn();
+      });
+      return self.vault.take(amount);
+    }
+
+    /// Locks collateral and borrows tokens from the pool
+    ///
+    /// # Arguments:
+    /// * `collateral`: Bucket - Contains the collateral to lock, in a token accepted by the pool
+    /// * `amount`: Decimal - Amount of lent tokens to borrow
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the `LoanData` token of the new loan
+    /// * `Bucket` - Contains the borrowed tokens
+    pub fn borrow(
+      &mut self,
+      collateral: Bucket,
+      amount: Decimal
+    ) -> (Bucket, Bucket) {
+      assert!(
+        self.collateral_tokens.contains(&collateral.resource_address()),
+        "[Borrow]: Token is not accepted as collateral."
+      );
+      assert!(amount > Decimal::zero(), "[Borrow]: Amount borrowed must be greater than zero.");
+      assert!(
+        amount <= self.vault.amount(),
+        "[Borrow]: Only {} tokens are available to borrow.",
+        self.vault.amount()
+      );
+      self.accrue_interest();
+
+      let loan_id: NonFungibleId = NonFungibleId::from_u64(self.loan_counter);
+      self.loan_counter += 1;
+
+      // Record the loan and verify the collateral covers the debt as required by the MCR
+      let collateral_address: ResourceAddress = collateral.resource_address();
+      self.loans.insert(loan_id.clone(), Loan {
+        collateral_address: collateral_address,
+        scaled_debt: amount / self.borrow_index
+      });
+      self.collateral_vaults.insert(loan_id.clone(), Vault::with_bucket(collateral));
+      self.loan_position(&loan_id, amount).assert_collateralized(
+        &self.price_oracle,
+        self.minimum_collateralization_ratio,
+        String::from("Borrow")
+      );
+
+      self.total_borrows = self.total_borrows + amount;
+      info!("[Borrow]: Loan {} borrowed {} against {}", loan_id, amount, token_symbol(collateral_address));
+
+      let loan_manager: &ResourceManager = borrow_resource_manager!(self.loan_nft_address);
+      let loan_nft: Bucket = self.receipt_token_admin_badge.authorize(|| {
+        loan_manager.mint_non_fungible(&loan_id, LoanData {
+          collateral_address: collateral_address
+        })
+      });
+
+      return (loan_nft, self.vault.take(amount));
+    }
+
+    /// Borrows more tokens against the collateral of an open loan
+    ///
+    /// # Arguments:
+    /// * `loan_proof`: Proof - Proof of the `LoanData` token of the loan
+    /// * `amount`: Decimal - Amount of lent tokens to borrow
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the borrowed tokens
+    pub fn borrow_more(
+      &mut self,
+      loan_proof: Proof,
+      amount: Decimal
+    ) -> Bucket {
+      let loan_id: NonFungibleId = self.loan_id(&loan_proof, String::from("Borrow More"));
+      assert!(amount > Decimal::zero(), "[Borrow More]: Amount borrowed must be greater than zero.");
+      assert!(
+        amount <= self.vault.amount(),
+        "[Borrow More]: Only {} tokens are available to borrow.",
+        self.vault.amount()
+      );
+      self.accrue_interest();
+
+      let debt: Decimal = self.debt(loan_id.clone()) + amount;
+      self.loan_position(&loan_id, debt).assert_collateralized(
+        &self.price_oracle,
+        self.minimum_collateralization_ratio,
+        String::from("Borrow More")
+      );
+
+      self.loans.get_mut(&loan_id).unwrap().scaled_debt = debt / self.borrow_index;
+      self.total_borrows = self.total_borrows + amount;
+      info!("[Borrow More]: Loan {} borrowed {}, debt: {}", loan_id, amount, debt);
+
+      return self.vault.take(amount);
+    }
+
+    /// Repays part or all of the debt of a loan, anyone can repay a loan
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    /// * `repayment`: Bucket - Contains the lent tokens to repay
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens of the repayment above the debt of the loan
+    pub fn repay(
+      &mut self,
+      loan_id: NonFungibleId,
+      mut repayment: Bucket
+    ) -> Bucket {
+      self.assert_loan_exists(&loan_id, String::from("Repay"));
+      assert_eq!(
+        repayment.resource_address(), self.vault.resource_address(),
+        "[Repay]: Loan must be repaid in the lent token."
+      );
+      self.accrue_interest();
+
+      let debt: Decimal = self.debt(loan_id.clone());
+      let repaid: Decimal = if repayment.amount() < debt { repayment.amount() } else { debt };
+      self.vault.put(repayment.take(repaid));
+
+      // Rounding of the index can leave the total slightly below the sum of the loans
+      self.total_borrows = if repaid < self.total_borrows { self.total_borrows - repaid } else { Decimal::zero() };
+      self.loans.get_mut(&loan_id).unwrap().scaled_debt = (debt - repaid) / self.borrow_index;
+      info!("[Repay]: Loan {} repaid {}, remaining debt: {}", loan_id, repaid, debt - repaid);
+
+      return repayment;
+    }
+
+    /// Locks more collateral for an open loan
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    /// * `collateral`: Bucket - Contains the collateral token of the loan
+    pub fn add_collateral(
+      &mut self,
+      loan_id: NonFungibleId,
+      collateral: Bucket
+    ) {
+      self.assert_loan_exists(&loan_id, String::from("Add Collateral"));
+      assert_eq!(
+        collateral.resource_address(), self.loans[&loan_id].collateral_address,
+        "[Add Collateral]: Incorrect collateral token for the loan."
+      );
+      info!("[Add Collateral]: Loan {} locked {} more collateral", loan_id, collateral.amount());
+      self.collateral_vaults.get_mut(&loan_id).unwrap().put(collateral);
+    }
+
+    /// Unlocks collateral of an open loan that is not needed to satisfy the MCR
+    ///
+    /// # Arguments:
+    /// * `loan_proof`: Proof - Proof of the `LoanData` token of the loan
+    /// * `amount`: Decimal - Amount of collateral to unlock
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the unlocked collateral
+    pub fn withdraw_collateral(
+      &mut self,
+      loan_proof: Proof,
+      amount: Decimal
+    ) -> Bucket {
+      let loan_id: NonFungibleId = self.loan_id(&loan_proof, String::from("Withdraw Collateral"));
+      self.accrue_interest();
+
+      let collateral: Bucket = self.collateral_vaults.get_mut(&loan_id).unwrap().take(amount);
+      let debt: Decimal = self.debt(loan_id.clone());
+      if debt > Decimal::zero() {
+        self.loan_position(&loan_id, debt).assert_collateralized(
+          &self.price_oracle,
+          self.minimum_collateralization_ratio,
+          String::from("Withdraw Collateral")
+        );
+      }
+      info!("[Withdraw Collateral]: Loan {} unlocked {} collateral", loan_id, amount);
+
+      return collateral;
+    }
+
+    /// Repays the whole debt of a loan, burns its token and returns the collateral
+    ///
+    /// # Arguments:
+    /// * `loan_nft`: Bucket - Contains the `LoanData` token of the loan
+    /// * `repayment`: Bucket - Contains at least the debt of the loan in the lent token
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the collateral of the loan
+    /// * `Bucket` - Contains the tokens of the repayment above the debt of the loan
+    pub fn close_loan(
+      &mut self,
+      loan_nft: Bucket,
+      repayment: Bucket
+    ) -> (Bucket, Bucket) {
+      assert_eq!(
+        loan_nft.resource_address(), self.loan_nft_address,
+        "[Close Loan]: Loan token does not belong to this lending pool."
+      );
+      assert_eq!(loan_nft.amount(), Decimal::one(), "[Close Loan]: Loans are closed one at a time.");
+      let loan_id: NonFungibleId = loan_nft.non_fungible::<LoanData>().id();
+
+      let change: Bucket = self.repay(loan_id.clone(), repayment);
+      assert_eq!(
+        self.loans[&loan_id].scaled_debt, Decimal::zero(),
+        "[Close Loan]: Repayment does not cover the debt of the loan."
+      );
+
+      self.loans.remove(&loan_id);
+      let collateral: Bucket = self.collateral_vaults.get_mut(&loan_id).unwrap().take_all();
+      self.receipt_token_admin_badge.authorize(|| {
+        loan_nft.burn();
+      });
+      info!("[Close Loan]: Loan {} closed", loan_id);
+
+      return (collateral, change);
+    }
+
+    /// Repays part of the debt of a loan below the MCR in exchange for its collateral at a bonus, anyone can liquidate
+    ///
+    /// # Arguments:
+    /// * `loan_id`: NonFungibleId - Id of the loan
+    /// * `repayment`: Bucket - Contains the lent tokens to repay, at most the close factor of the debt is used
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the seized collateral
+    /// * `Bucket` - Contains the tokens of the repayment that were not used
+    pub fn liquidate(
+      &mut self,
+      loan_id: NonFungibleId,
+      mut repayment: Bucket
+    ) -> (Bucket, Bucket) {
+      self.assert_loan_exists(&loan_id, String::from("Liquidate"));
+      assert_eq!(
+        repayment.resource_address(), self.vault.resource_address(),
+        "[Liquidate]: Loan must be repaid in the lent token."
+      );
+      self.accrue_interest();
+
+      let debt: Decimal = self.debt(loan_id.clone());
+      let position: Position = self.loan_position(&loan_id, debt);
+      position.assert_liquidatable(&self.price_oracle, self.minimum_collateralization_ratio, String::from("Liquidate"));
+
+      let (repaid, seized): (Decimal, Decimal) = position.liquidation_amounts(
+        &self.price_oracle,
+        repayment.amount(),
+        self.close_factor,
+        self.liquidation_bonus
+      );
+      let change: Bucket = self.repay(loan_id.clone(), repayment.take(repaid));
+      repayment.put(change);
+      info!("[Liquidate]: Loan {} liquidated, repaid {} for {} collateral", loan_id, repaid, seized);
+
+      let collateral: Bucket = self.collateral_vaults.get_mut(&loan_id).unwrap().take(seized);
+      return (collateral, repayment);
+    }
+  }
+}
@@ -0,0 +1,871 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::utils::*;
+use crate::oracle::*;
+use crate::pricing::pow;
+use crate::collateral::*;
+use crate::swap::ElisionSwap;
+
+/// Highest target leverage a leveraged token can be created with
+fn max_leverage() -> Decimal {
+  return dec!("3");
+}
+
+/// Distance from the target leverage at which newly created leveraged tokens can be rebalanced
+fn default_leverage_band() -> Decimal {
+  return dec!("0.5");
+}
+
+/// Percentage of the value traded by a rebalance paid to its caller by newly created leveraged tokens
+fn default_keeper_reward() -> Decimal {
+  return dec!("0.1");
+}
+
+/// Percentage below the oracle value of the input that the swaps of a rebalance of newly created leveraged tokens
+/// accept as output
+fn default_max_slippage() -> Decimal {
+  return dec!("1");
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
+pub enum LeverageDirection {
+  Long,
+  Short
+}
+
+/// Asserts that a funding rate can be charged on the debt of a leveraged token
+///
+/// # Arguments:
+/// * `funding_rate`: Decimal - Interest per epoch paid to lenders on the debt
+/// * `label`: String - Label that called the assertion method
+fn assert_valid_funding_rate(
+  funding_rate: Decimal,
+  label: String
+) {
+  assert!(funding_rate >= Decimal::zero(), "[{}]: Funding rate cannot be negative.", label);
+}
+
+/// Asserts that a position held at the target leverage satisfies the Minimum Collateralization Ratio
+///
+/// # Arguments:
+/// * `direction`: LeverageDirection - Whether the position is long or short
+/// * `leverage`: Decimal - Target leverage of the position
+/// * `mcr`: Decimal - Minimum ratio of the value of the exposure vault to the value of the debt
+/// * `label`: String - Label that called the assertion method
+///
+/// # Note:
+/// * Longs hold `L` for a debt of `L - 1`, shorts hold `L + 1` for a debt of `L`, per unit of net asset value
+fn assert_leverage_satisfies_mcr(
+  direction: LeverageDirection,
+  leverage: Decimal,
+  mcr: Decimal,
+  label: String
+) {
+  assert_valid_mcr(mcr, label.clone());
+  let target_ratio: Decimal = match direction {
+    LeverageDirection::Long => leverage / (leverage - Decimal::one()),
+    LeverageDirection::Short => (leverage + Decimal::one()) / leverage
+  };
+  assert!(
+    mcr <= target_ratio,
+    "[{}]: Positions at the target leverage have a collateralization ratio of {}, below the MCR.",
+    label, target_ratio
+  );
+}
+
+blueprint! {
+  /// Structure representing a leveraged token, a fungible share of a position held at a target leverage
+  ///
+  /// # Contains:
+  /// * `underlying_address`: ResourceAddress - Token the leveraged token is exposed to
+  /// * `quote_address`: ResourceAddress - Token the leveraged token is minted and redeemed with
+  /// * `direction`: LeverageDirection - Whether the token gains or loses as the underlying rises
+  /// * `leverage`: Decimal - Target ratio of the exposure to the net asset value of the position
+  /// * `token_address`: ResourceAddress - Fungible leveraged tokens issued to holders
+  /// * `lender_token_address`: ResourceAddress - Fungible tokens issued to lenders tracking their share of the lender vault
+  /// * `token_admin_badge`: Vault - Badge that gives authority to mint and burn leveraged and lender tokens
+  /// * `exposure_vault`: Vault - Tokens bought with the deposits and the borrowed tokens, underlying for longs, quote for shorts
+  /// * `lender_vault`: Vault - Tokens provided by lenders that are not borrowed, quote for longs, underlying for shorts
+  /// * `debt`: Decimal - Tokens borrowed from the lender vault including accrued funding
+  /// * `funding_rate`: Decimal - Interest per epoch paid to lenders on the debt
+  /// * `last_accrual_epoch`: u64 - Epoch in which funding was last accrued
+  /// * `elision_swap`: ElisionSwap - Exchange used to buy and sell exposure
+  /// * `price_oracle`: PriceOracle - Oracle used to value the position
+  /// * `minimum_collateralization_ratio`: Decimal - Minimum ratio of the value of the exposure vault to the value of the debt
+  /// * `leverage_band`: Decimal - Distance from the target leverage beyond which the position can be rebalanced
+  /// * `keeper_reward`: Decimal - Percentage of the value traded by a rebalance paid to its caller (0-100)
+  /// * `max_slippage`: Decimal - Percentage below the oracle value of the input that the swaps of a rebalance accept
+  ///   as output (0-100)
+  /// * `last_rebalance_epoch`: u64 - Epoch in which the position was last rebalanced
+  /// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in exposure to liquidators (0-100)
+  /// * `close_factor`: Decimal - Percentage of the debt a single liquidation can repay (0-100)
+  struct LeveragedToken {
+    underlying_address: ResourceAddress,
+    quote_address: ResourceAddress,
+    direction: LeverageDirection,
+    leverage: Decimal,
+    token_address: ResourceAddress,
+    lender_token_address: ResourceAddress,
+    token_admin_badge: Vault,
+    exposure_vault: Vault,
+    lender_vault: Vault,
+    debt: Decimal,
+    funding_rate: Decimal,
+    last_accrual_epoch: u64,
+    elision_swap: ElisionSwap,
+    price_oracle: PriceOracle,
+    minimum_collateralization_ratio: Decimal,
+    leverage_band: Decimal,
+    keeper_reward: Decimal,
+    max_slippage: Decimal,
+    last_rebalance_epoch: u64,
+    liquidation_bonus: Decimal,
+    close_factor: Decimal
+  }
+
+  impl LeveragedToken {
+    /// Creates a new leveraged token on an underlying token
+    ///
+    /// # Arguments:
+    /// * `underlying_address`: ResourceAddress - Token the leveraged token is exposed to
+    /// * `quote_address`: ResourceAddress - Token the leveraged token is minted and redeemed with
+    /// * `direction`: LeverageDirection - Whether the token gains or loses as the underlying rises
+    /// * `leverage`: Decimal - Target leverage, greater than one and at most `max_leverage()`
+    /// * `elision_swap`: ComponentAddress - ElisionSwap component with a pool of the underlying and quote tokens
+    /// * `price_oracle`: ComponentAddress - PriceOracle component used to value the position
+    /// * `funding_rate`: Decimal - Interest per epoch paid to lenders on the debt, `0.0001` being 0.01%
+    /// * `mcr`: Decimal - Minimum ratio of the value of the exposure vault to the value of the debt
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - LeveragedToken component address for the new token
+    /// * `Bucket` - Contains the admin badge that has the authority to update the token parameters
+    pub fn new(
+      underlying_address: ResourceAddress,
+      quote_address: ResourceAddress,
+      direction: LeverageDirection,
+      leverage: Decimal,
+      elision_swap: ComponentAddress,
+      price_oracle: ComponentAddress,
+      funding_rate: Decimal,
+      mcr: Decimal
+    ) -> (ComponentAddress, Bucket) {
+      assert_ne!(
+        underlying_address, quote_address,
+        "[Leveraged Token Creation]: Underlying and quote tokens must be different."
+      );
+      assert!(
+        (leverage > Decimal::one()) & (leverage <= max_leverage()),
+        "[Leveraged Token Creation]: Leverage must be greater than 1 and at most {}.",
+        max_leverage()
+      );
+      assert_valid_funding_rate(funding_rate, String::from("Leveraged Token Creation"));
+      assert_leverage_satisfies_mcr(direction, leverage, mcr, String::from("Leveraged Token Creation"));
+
+      // Longs borrow the quote token to buy the underlying, shorts borrow the underlying to sell it
+      let (exposure_address, debt_address): (ResourceAddress, ResourceAddress) = match direction {
+        LeverageDirection::Long => (underlying_address, quote_address),
+        LeverageDirection::Short => (quote_address, underlying_address)
+      };
+      let symbol: String = format!(
+        "{}{}{}",
+        token_symbol(underlying_address),
+        leverage,
+        if direction == LeverageDirection::Long { "L" } else { "S" }
+      );
+      info!("[Leveraged Token Creation]: Creating new leveraged token {}", symbol);
+
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Leveraged Token Admin Badge")
+        .metadata("symbol", "LTAB")
+        .metadata("description", "Admin Badge with the authority to update the parameters of a leveraged token")
+        .initial_supply(1);
+
+      // Create badge for the leveraged token to give authority to mint and burn
+      let token_admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Leveraged Token Minter Badge")
+        .metadata("symbol", "LTMB")
+        .metadata("description", "Badge with the authority to mint and burn leveraged and lender tokens")
+        .initial_supply(1);
+
+      let token_address: ResourceAddress = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_MAXIMUM)
+        .metadata("name", format!("{} Leveraged Token", symbol))
+        .metadata("symbol", symbol.clone())
+        .metadata("description", "Token representing a share of a position held at a target leverage on Elision.")
+        .mintable(rule!(require(token_admin_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(token_admin_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let lender_token_address: ResourceAddress = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_MAXIMUM)
+        .metadata("name", format!("{} Lender Token", symbol))
+        .metadata("symbol", format!("{}-LEND", symbol))
+        .metadata("description", "Token used to track the share of a lender in the funds lent to a leveraged token.")
+        .mintable(rule!(require(token_admin_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(token_admin_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = AccessRules::new()
+        .method("set_funding_rate", rule!(require(admin_badge.resource_address())))
+        .method("set_minimum_collateralization_ratio", rule!(require(admin_badge.resource_address())))
+        .method("set_rebalance_parameters", rule!(require(admin_badge.resource_address())))
+        .method("set_liquidation_parameters", rule!(require(admin_badge.resource_address())))
+        .default(rule!(allow_all));
+
+      let mut leveraged_token = Self {
+        underlying_address: underlying_address,
+        quote_address: quote_address,
+        direction: direction,
+        leverage: leverage,
+        token_address: token_address,
+        lender_token_address: lender_token_address,
+        token_admin_badge: Vault::with_bucket(token_admin_badge),
+        exposure_vault: Vault::new(exposure_address),
+        lender_vault: Vault::new(debt_address),
+        debt: Decimal::zero(),
+        funding_rate: funding_rate,
+        last_accrual_epoch: Runtime::current_epoch(),
+        elision_swap: elision_swap.into(),
+        price_oracle: price_oracle.into(),
+        minimum_collateralization_ratio: mcr,
+        leverage_band: default_leverage_band(),
+        keeper_reward: default_keeper_reward(),
+        max_slippage: default_max_slippage(),
+        last_rebalance_epoch: Runtime::current_epoch(),
+        liquidation_bonus: default_liquidation_bonus(),
+        close_factor: default_close_factor()
+      }
+      .instantiate();
+      leveraged_token.add_access_check(access_rules);
+
+      return (leveraged_token.globalize(), admin_badge);
+    }
+
+    /// Updates the interest paid to lenders, funding up to the current epoch accrues at the old rate
+    ///
+    /// # Arguments:
+    /// * `funding_rate`: Decimal - Interest per epoch paid to lenders on the debt
+    pub fn set_funding_rate(
+      &mut self,
+      funding_rate: Decimal
+    ) {
+      assert_valid_funding_rate(funding_rate, String::from("Set Funding Rate"));
+      self.accrue_funding();
+      self.funding_rate = funding_rate;
+      info!("[Set Funding Rate]: Funding rate set to {}", funding_rate);
+    }
+
+    /// Updates the Minimum Collateralization Ratio that rebalancing cannot push the position below
+    ///
+    /// # Arguments:
+    /// * `mcr`: Decimal - Minimum ratio of the value of the exposure vault to the value of the debt
+    pub fn set_minimum_collateralization_ratio(
+      &mut self,
+      mcr: Decimal
+    ) {
+      assert_leverage_satisfies_mcr(self.direction, self.leverage, mcr, String::from("Set MCR"));
+      self.minimum_collateralization_ratio = mcr;
+      info!("[Set MCR]: Minimum collateralization ratio set to {}", mcr);
+    }
+
+    /// Updates when the position can be rebalanced, what the caller earns for it and the slippage its swaps accept
+    ///
+    /// # Arguments:
+    /// * `leverage_band`: Decimal - Distance from the target leverage beyond which the position can be rebalanced
+    /// * `keeper_reward`: Decimal - Percentage of the value traded by a rebalance paid to its caller (0-100)
+    /// * `max_slippage`: Decimal - Percentage below the oracle value of the input that the swaps of a rebalance accept
+    ///   as output (0-100)
+    pub fn set_rebalance_parameters(
+      &mut self,
+      leverage_band: Decimal,
+      keeper_reward: Decimal,
+      max_slippage: Decimal
+    ) {
+      assert!(
+        (leverage_band > Decimal::zero()) & (leverage_band < self.leverage),
+        "[Rebalance Parameters]: Leverage band must be greater than zero and below the target leverage."
+      );
+      assert!(
+        (keeper_reward >= Decimal::zero()) & (keeper_reward <= dec!("100")),
+        "[Rebalance Parameters]: Keeper reward must be between 0 and 100."
+      );
+      assert!(
+        (max_slippage >= Decimal::zero()) & (max_slippage < dec!("100")),
+        "[Rebalance Parameters]: Max slippage must be at least 0 and below 100."
+      );
+      self.leverage_band = leverage_band;
+      self.keeper_reward = keeper_reward;
+      self.max_slippage = max_slippage;
+      info!(
+        "[Rebalance Parameters]: Leverage band: {}, Keeper reward: {}, Max slippage: {}",
+        leverage_band, keeper_reward, max_slippage
+      );
+    }
+
+    /// Updates what liquidators of the position can repay and seize once it falls below the MCR
+    ///
+    /// # Arguments:
+    /// * `liquidation_bonus`: Decimal - Percentage of the repaid value paid on top in exposure (0-100)
+    /// * `close_factor`: Decimal - Percentage of the debt a single liquidation can repay (0-100)
+    pub fn set_liquidation_parameters(
+      &mut self,
+      liquidation_bonus: Decimal,
+      close_factor: Decimal
+    ) {
+      assert_liquidation_parameters(liquidation_bonus, close_factor, String::from("Liquidation Parameters"));
+      self.liquidation_bonus = liquidation_bonus;
+      self.close_factor = close_factor;
+      info!("[Liquidation Parameters]: Liquidation bonus: {}, Close factor: {}", liquidation_bonus, close_factor);
+    }
+
+    /// Obtain the address of the leveraged tokens
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the leveraged tokens
+    pub fn token_address(&self) -> ResourceAddress {
+      return self.token_address;
+    }
+
+    /// Obtain the address of the tokens issued to lenders
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the lender tokens
+    pub fn lender_token_address(&self) -> ResourceAddress {
+      return self.lender_token_address;
+    }
+
+    /// Obtain the token lenders provide and the position borrows
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Quote token for longs, underlying token for shorts
+    pub fn debt_address(&self) -> ResourceAddress {
+      return self.lender_vault.resource_address();
+    }
+
+    /// Obtain the debt of the position including funding accrued up to the current epoch
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of tokens owed to the lender vault
+    pub fn debt(&self) -> Decimal {
+      return self.debt * self.pending_funding_factor();
+    }
+
+    /// Obtain the interest paid to lenders
+    ///
+    /// # Returns:
+    /// * `Decimal` - Funding rate per epoch
+    pub fn funding_rate(&self) -> Decimal {
+      return self.funding_rate;
+    }
+
+    /// Obtain the target leverage of the token
+    ///
+    /// # Returns:
+    /// * `Decimal` - Target ratio of the exposure to the net asset value
+    pub fn target_leverage(&self) -> Decimal {
+      return self.leverage;
+    }
+
+    /// Calculates the value of the position held for the leveraged token holders
+    ///
+    /// # Returns:
+    /// * `Decimal` - Net asset value in USD where `NAV = E - D`, zero if the debt exceeds the exposure
+    ///
+    /// # Note:
+    /// * `E` - The value of the exposure vault
+    /// * `D` - The value of the debt
+    pub fn net_asset_value(&self) -> Decimal {
+      let position: Position = self.position();
+      let exposure_value: Decimal = position.collateral_value(&self.price_oracle);
+      let debt_value: Decimal = position.exposure_value(&self.price_oracle);
+      return if exposure_value > debt_value { exposure_value - debt_value } else { Decimal::zero() };
+    }
+
+    /// Calculates the value of a single leveraged token
+    ///
+    /// # Returns:
+    /// * `Decimal` - Net asset value per token in USD, the price of a quote token while no tokens exist
+    pub fn nav_per_token(&self) -> Decimal {
+      let supply: Decimal = borrow_resource_manager!(self.token_address).total_supply();
+      if supply == Decimal::zero() {
+        return self.price_oracle.get_price(self.quote_address);
+      }
+      return self.net_asset_value() / supply;
+    }
+
+    /// Calculates the current leverage of the position
+    ///
+    /// # Returns:
+    /// * `Decimal` - Value of the underlying the position is exposed to divided by the net asset value
+    ///
+    /// # Note:
+    /// * Longs are exposed to the underlying held in the exposure vault, shorts to the underlying they owe
+    pub fn leverage(&self) -> Decimal {
+      let nav: Decimal = self.net_asset_value();
+      if nav == Decimal::zero() {
+        return Decimal::zero();
+      }
+
+      let position: Position = self.position();
+      return match self.direction {
+        LeverageDirection::Long => position.collateral_value(&self.price_oracle) / nav,
+        LeverageDirection::Short => position.exposure_value(&self.price_oracle) / nav
+      }
+    }
+
+    /// Calculates the ratio of the value of the exposure vault to the value of the debt
+    ///
+    /// # Returns:
+    /// * `Decimal` - Collateralization ratio of the position, `1.5` being 150%
+    pub fn collateralization_ratio(&self) -> Decimal {
+      return self.position().collateralization_ratio(&self.price_oracle);
+    }
+
+    /// Obtain the Minimum Collateralization Ratio that rebalancing cannot push the position below
+    ///
+    /// # Returns:
+    /// * `Decimal` - Minimum ratio of the value of the exposure vault to the value of the debt
+    pub fn minimum_collateralization_ratio(&self) -> Decimal {
+      return self.minimum_collateralization_ratio;
+    }
+
+    /// Checks whether the leverage of the position has left the band around the target leverage
+    ///
+    /// # Returns:
+    /// * `bool` - True if the position can be rebalanced, false otherwise
+    pub fn needs_rebalance(&self) -> bool {
+      if borrow_resource_manager!(self.token_address).total_supply() == Decimal::zero() {
+        return false;
+      }
+
+      let leverage: Decimal = self.leverage();
+      return (leverage > self.leverage + self.leverage_band) | (leverage < self.leverage - self.leverage_band);
+    }
+
+    /// Calculates the amount of lent tokens a single lender token can be redeemed for
+    ///
+    /// # Returns:
+    /// * `Decimal` - Exchange rate where `E = (L + D) / S`, one while no lender tokens exist
+    ///
+    /// # Note:
+    /// * `L` - The tokens in the lender vault
+    /// * `D` - The debt of the position
+    /// * `S` - The total supply of lender tokens
+    pub fn lender_exchange_rate(&self) -> Decimal {
+      let supply: Decimal = borrow_resource_manager!(self.lender_token_address).total_supply();
+      if supply == Decimal::zero() {
+        return Decimal::one();
+      }
+      return (self.lender_vault.amount() + self.debt()) / supply;
+    }
+
+    /// Obtain the position backing the leveraged tokens
+    ///
+    /// # Returns:
+    /// * `Position` - Exposure vault held as collateral against the debt
+    fn position(&self) -> Position {
+      return Position {
+        collateral_address: self.exposure_vault.resource_address(),
+        collateral_amount: self.exposure_vault.amount(),
+        exposure_address: self.lender_vault.resource_address(),
+        exposure_amount: self.debt()
+      }
+    }
+
+    /// Calculates the growth of the debt since funding was last accrued
+    ///
+    /// # Returns:
+    /// * `Decimal` - Factor where `F = (1 + R)^n`, `R` being the funding rate and `n` the epochs since the last accrual
+    fn pending_funding_factor(&self) -> Decimal {
+      let epochs: u64 = Runtime::current_epoch() - self.last_accrual_epoch;
+      if (epochs == 0) | (self.debt == Decimal::zero()) {
+        return Decimal::one();
+      }
+      return pow(Decimal::one() + self.funding_rate, Decimal::from(epochs));
+    }
+
+    /// Compounds the funding of every epoch since the last accrual into the debt
+    fn accrue_funding(&mut self) {
+      self.debt = self.debt();
+      self.last_accrual_epoch = Runtime::current_epoch();
+    }
+
+    /// Borrows tokens from the lender vault
+    ///
+    /// # Arguments:
+    /// * `amount`: Decimal - Amount of tokens to borrow
+    /// * `label`: String - Label that called the method
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the borrowed tokens
+    fn borrow(
+      &mut self,
+      amount: Decimal,
+      label: String
+    ) -> Bucket {
+      assert!(
+        amount <= self.lender_vault.amount(),
+        "[{}]: Only {} tokens are available to borrow from lenders.",
+        label, self.lender_vault.amount()
+      );
+      self.debt = self.debt + amount;
+      return self.lender_vault.take(amount);
+    }
+
+    /// Repays tokens to the lender vault
+    ///
+    /// # Arguments:
+    /// * `repayment`: Bucket - Contains the tokens to repay
+    fn repay(
+      &mut self,
+      repayment: Bucket
+    ) {
+      let amount: Decimal = repayment.amount();
+      self.debt = if amount < self.debt { self.debt - amount } else { Decimal::zero() };
+      self.lender_vault.put(repayment);
+    }
+
+    /// Provides tokens for the position to borrow in exchange for lender tokens that earn the funding rate
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the quote token for longs or the underlying token for shorts
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the lender tokens owed to the lender
+    pub fn lend(
+      &mut self,
+      tokens: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        tokens.resource_address(), self.lender_vault.resource_address(),
+        "[Lend]: Token cannot be lent to this leveraged token."
+      );
+      assert!(!tokens.is_empty(), "[Lend]: Cannot lend an empty bucket.");
+      self.accrue_funding();
+
+      let lender_amount: Decimal = tokens.amount() / self.lender_exchange_rate();
+      info!("[Lend]: Lending {}, owed lender tokens: {}", tokens.amount(), lender_amount);
+      self.lender_vault.put(tokens);
+
+      let lender_manager: &ResourceManager = borrow_resource_manager!(self.lender_token_address);
+      return self.token_admin_badge.authorize(|| {
+        lender_manager.mint(lender_amount)
+      });
+    }
+
+    /// Redeems lender tokens for the tokens they represent and the funding they earned
+    ///
+    /// # Arguments:
+    /// * `lender_tokens`: Bucket - Contains the lender tokens to redeem
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens owed to the lender
+    pub fn withdraw_lending(
+      &mut self,
+      lender_tokens: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        lender_tokens.resource_address(), self.lender_token_address,
+        "[Withdraw Lending]: Lender token does not belong to this leveraged token."
+      );
+      self.accrue_funding();
+
+      let amount: Decimal = lender_tokens.amount() * self.lender_exchange_rate();
+      assert!(
+        amount <= self.lender_vault.amount(),
+        "[Withdraw Lending]: Only {} tokens are available until the position is redeemed.",
+        self.lender_vault.amount()
+      );
+      info!("[Withdraw Lending]: Redeeming {} lender tokens for {}", lender_tokens.amount(), amount);
+
+      self.token_admin_badge.authorize(|| {
+        lender_tokens.burn();
+      });
+      return self.lender_vault.take(amount);
+    }
+
+    /// Borrows from the lender vault to buy exposure at the target leverage and mints leveraged tokens at NAV
+    ///
+    /// # Arguments:
+    /// * `payment`: Bucket - Contains the quote tokens to invest
+    /// * `min_tokens`: Decimal - Minimum amount of leveraged tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the tokens can be minted
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the minted leveraged tokens
+    ///
+    /// # Note:
+    /// * Longs borrow `(L - 1) * d` quote tokens and buy the underlying with `L * d`, `d` being the payment
+    /// * Shorts borrow underlying tokens worth `L * d` and sell them, holding `d` and the proceeds
+    /// * Tokens are minted for the NAV the payment added, so slippage of the swap is paid by the minter
+    pub fn mint(
+      &mut self,
+      mut payment: Bucket,
+      min_tokens: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_deadline(deadline, String::from("Mint"));
+      assert_eq!(
+        payment.resource_address(), self.quote_address,
+        "[Mint]: Leveraged tokens are minted with the quote token."
+      );
+      assert!(!payment.is_empty(), "[Mint]: Cannot mint from an empty bucket.");
+      self.accrue_funding();
+
+      let nav_before: Decimal = self.net_asset_value();
+      let nav_per_token: Decimal = self.nav_per_token();
+      assert!(nav_per_token > Decimal::zero(), "[Mint]: Position has no net asset value left.");
+
+      let amount: Decimal = payment.amount();
+      match self.direction {
+        LeverageDirection::Long => {
+          let borrowed: Bucket = self.borrow((self.leverage - Decimal::one()) * amount, String::from("Mint"));
+          payment.put(borrowed);
+          let exposure: Bucket = self.elision_swap.swap(payment, self.underlying_address, Decimal::zero(), deadline);
+          self.exposure_vault.put(exposure);
+        }
+        LeverageDirection::Short => {
+          let borrow_amount: Decimal = self.leverage * amount * self.price_oracle.get_price(self.quote_address)
+            / self.price_oracle.get_price(self.underlying_address);
+          let borrowed: Bucket = self.borrow(borrow_amount, String::from("Mint"));
+          let proceeds: Bucket = self.elision_swap.swap(borrowed, self.quote_address, Decimal::zero(), deadline);
+          self.exposure_vault.put(payment);
+          self.exposure_vault.put(proceeds);
+        }
+      }
+
+      let nav_after: Decimal = self.net_asset_value();
+      let nav_added: Decimal = if nav_after > nav_before { nav_after - nav_before } else { Decimal::zero() };
+      let token_amount: Decimal = nav_added / nav_per_token;
+      assert!(token_amount > Decimal::zero(), "[Mint]: Payment added no net asset value.");
+      assert!(token_amount >= min_tokens, "[Mint]: min_tokens not satisfied.");
+      info!("[Mint]: Minting {} tokens at a NAV of {} per token", token_amount, nav_per_token);
+
+      let token_manager: &ResourceManager = borrow_resource_manager!(self.token_address);
+      return self.token_admin_badge.authorize(|| {
+        token_manager.mint(token_amount)
+      });
+    }
+
+    /// Burns leveraged tokens, unwinds their share of the position and repays its share of the debt
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the leveraged tokens to redeem
+    /// * `min_amount_out`: Decimal - Minimum amount of quote tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the tokens can be redeemed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the quote tokens left once the debt share is repaid
+    pub fn redeem(
+      &mut self,
+      tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_deadline(deadline, String::from("Redeem"));
+      assert_eq!(
+        tokens.resource_address(), self.token_address,
+        "[Redeem]: Token does not belong to this leveraged token."
+      );
+      assert!(!tokens.is_empty(), "[Redeem]: Cannot redeem an empty bucket.");
+      self.accrue_funding();
+
+      // Share of the position owned by the redeemed tokens
+      let share: Decimal = tokens.amount() / borrow_resource_manager!(self.token_address).total_supply();
+      let exposure: Bucket = self.exposure_vault.take(self.exposure_vault.amount() * share);
+      let debt_share: Decimal = self.debt * share;
+      self.token_admin_badge.authorize(|| {
+        tokens.burn();
+      });
+
+      let output: Bucket = match self.direction {
+        LeverageDirection::Long => {
+          let mut proceeds: Bucket = self.elision_swap.swap(exposure, self.quote_address, Decimal::zero(), deadline);
+          assert!(proceeds.amount() >= debt_share, "[Redeem]: Position cannot cover its debt.");
+          self.repay(proceeds.take(debt_share));
+          proceeds
+        }
+        LeverageDirection::Short => {
+          let max_input: Decimal = exposure.amount();
+          let (bought, remaining): (Bucket, Bucket) = self.elision_swap.swap_tokens_for_exact_tokens(
+            exposure, self.underlying_address, debt_share, max_input, deadline
+          );
+          self.repay(bought);
+          remaining
+        }
+      };
+
+      assert!(output.amount() >= min_amount_out, "[Redeem]: min_amount_out not satisfied.");
+      info!("[Redeem]: Redeemed for {} quote tokens", output.amount());
+      return output;
+    }
+
+    /// Moves the leverage of the position back to the target by trading exposure, anyone can call it once per epoch
+    ///
+    /// # Arguments:
+    /// * `deadline`: u64 - Last epoch in which the position can be rebalanced
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the keeper reward in quote tokens
+    ///
+    /// # Note:
+    /// * `N` - The net asset value of the position
+    /// * `D*` - The target value of the debt, `(L - 1) * N` for longs and `L * N` for shorts
+    /// * Debt above `D*` is repaid by selling exposure, debt below it is borrowed to buy exposure
+    /// * Borrowing `x` more adds `x` to both the exposure and the debt, so it is capped at
+    ///   `x = (E - MCR * D) / (MCR - 1)` to keep the position at or above the MCR
+    /// * Every swap must return at least the oracle value of its input less the max slippage, and the keeper reward
+    ///   is only taken once the swap has filled
+    pub fn rebalance(
+      &mut self,
+      deadline: u64
+    ) -> Bucket {
+      assert_deadline(deadline, String::from("Rebalance"));
+      assert!(
+        Runtime::current_epoch() > self.last_rebalance_epoch,
+        "[Rebalance]: Position was already rebalanced in this epoch."
+      );
+      self.accrue_funding();
+      assert!(self.needs_rebalance(), "[Rebalance]: Leverage is within the band around the target.");
+      self.last_rebalance_epoch = Runtime::current_epoch();
+
+      let position: Position = self.position();
+      let exposure_value: Decimal = position.collateral_value(&self.price_oracle);
+      let debt_value: Decimal = position.exposure_value(&self.price_oracle);
+      assert!(exposure_value > debt_value, "[Rebalance]: Position has no net asset value left.");
+      let nav: Decimal = exposure_value - debt_value;
+      let target_debt_value: Decimal = match self.direction {
+        LeverageDirection::Long => (self.leverage - Decimal::one()) * nav,
+        LeverageDirection::Short => self.leverage * nav
+      };
+      info!(
+        "[Rebalance]: Leverage: {}, Debt value: {}, Target debt value: {}",
+        self.leverage(), debt_value, target_debt_value
+      );
+
+      let quote_price: Decimal = self.price_oracle.get_price(self.quote_address);
+      let underlying_price: Decimal = self.price_oracle.get_price(self.underlying_address);
+      let mcr: Decimal = self.minimum_collateralization_ratio;
+      let reward: Bucket = if target_debt_value > debt_value {
+        let mut increase: Decimal = target_debt_value - debt_value;
+        if mcr > Decimal::one() {
+          let max_increase: Decimal = (exposure_value - mcr * debt_value) / (mcr - Decimal::one());
+          if max_increase < increase {
+            increase = max_increase;
+          }
+        }
+        assert!(
+          increase > Decimal::zero(),
+          "[Rebalance]: Position cannot borrow more without falling below the MCR."
+        );
+
+        let reward: Bucket = match self.direction {
+          LeverageDirection::Long => {
+            // The share of the borrowed tokens kept out of the swap is the keeper reward
+            let mut borrowed: Bucket = self.borrow(increase / quote_price, String::from("Rebalance"));
+            let traded: Bucket = borrowed.take(
+              borrowed.amount() * (dec!("100") - self.keeper_reward) / dec!("100")
+            );
+            let exposure: Bucket = self.rebalance_swap(traded, self.underlying_address, deadline);
+            self.exposure_vault.put(exposure);
+            borrowed
+          }
+          LeverageDirection::Short => {
+            let borrowed: Bucket = self.borrow(increase / underlying_price, String::from("Rebalance"));
+            let mut proceeds: Bucket = self.rebalance_swap(borrowed, self.quote_address, deadline);
+            let reward: Bucket = proceeds.take(proceeds.amount() * self.keeper_reward / dec!("100"));
+            self.exposure_vault.put(proceeds);
+            reward
+          }
+        };
+
+        // Slippage and the reward lower the exposure bought, which the cap above does not account for
+        self.position().assert_collateralized(&self.price_oracle, mcr, String::from("Rebalance"));
+        reward
+      } else {
+        let decrease: Decimal = debt_value - target_debt_value;
+        match self.direction {
+          LeverageDirection::Long => {
+            let sold: Bucket = self.exposure_vault.take(decrease / underlying_price);
+            let mut proceeds: Bucket = self.rebalance_swap(sold, self.quote_address, deadline);
+            let reward: Bucket = proceeds.take(proceeds.amount() * self.keeper_reward / dec!("100"));
+            self.repay(proceeds);
+            reward
+          }
+          LeverageDirection::Short => {
+            // The share of the exposure kept out of the swap is the keeper reward
+            let mut sold: Bucket = self.exposure_vault.take(decrease / quote_price);
+            let traded: Bucket = sold.take(sold.amount() * (dec!("100") - self.keeper_reward) / dec!("100"));
+            let bought: Bucket = self.rebalance_swap(traded, self.underlying_address, deadline);
+            self.repay(bought);
+            sold
+          }
+        }
+      };
+
+      info!("[Rebalance]: Leverage after rebalance: {}, Keeper reward: {}", self.leverage(), reward.amount());
+      return reward;
+    }
+
+    /// Swaps tokens of a rebalance, failing unless the output is within the max slippage of the oracle value
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the tokens to swap
+    /// * `output_address`: ResourceAddress - Address of the token to receive
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the output tokens
+    fn rebalance_swap(
+      &mut self,
+      tokens: Bucket,
+      output_address: ResourceAddress,
+      deadline: u64
+    ) -> Bucket {
+      let expected_output: Decimal = tokens.amount() * self.price_oracle.get_price(tokens.resource_address())
+        / self.price_oracle.get_price(output_address);
+      let min_amount_out: Decimal = expected_output * (dec!("100") - self.max_slippage) / dec!("100");
+      info!("[Rebalance]: Expected output: {}, Minimum output: {}", expected_output, min_amount_out);
+      return self.elision_swap.swap(tokens, output_address, min_amount_out, deadline);
+    }
+
+    /// Repays part of the debt of the position once it is below the MCR in exchange for exposure at a bonus,
+    /// anyone can liquidate
+    ///
+    /// # Arguments:
+    /// * `repayment`: Bucket - Contains the debt token to repay, at most the close factor of the debt is used
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the seized exposure tokens
+    /// * `Bucket` - Contains the tokens of the repayment that were not used
+    ///
+    /// # Note:
+    /// * The leveraged token holds a single position shared by all holders, so no position id is taken
+    pub fn liquidate(
+      &mut self,
+      mut repayment: Bucket
+    ) -> (Bucket, Bucket) {
+      assert_eq!(
+        repayment.resource_address(), self.lender_vault.resource_address(),
+        "[Liquidate]: Debt must be repaid in the borrowed token."
+      );
+      self.accrue_funding();
+
+      let position: Position = self.position();
+      position.assert_liquidatable(&self.price_oracle, self.minimum_collateralization_ratio, String::from("Liquidate"));
+
+      let (repaid, seized): (Decimal, Decimal) = position.liquidation_amounts(
+        &self.price_oracle,
+        repayment.amount(),
+        self.close_factor,
+        self.liquidation_bonus
+      );
+      self.repay(repayment.take(repaid));
+      info!("[Liquidate]: Position liquidated, repaid {} for {} exposure", repaid, seized);
+
+      return (self.exposure_vault.take(seized), repayment);
+    }
+  }
+}
@@ -1,3 +1,16 @@
 mod utils;
+mod pool_utils;
 mod swap;
 mod liquidity_pool;
+mod stable_swap_pool;
+mod weighted_pool;
+mod concentrated_pool;
+mod pool;
+mod oracle;
+mod options;
+mod pricing;
+mod collateral;
+mod interest_rate;
+mod lending_pool;
+mod leveraged_token;
+mod elision_pool;
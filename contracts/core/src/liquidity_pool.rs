@@ -1,5 +1,29 @@
 use scrypto::prelude::*;
+use sbor::*;
 use crate::utils::*;
+use crate::pool_utils::*;
+use crate::pricing::sqrt;
+
+/// Fee charged on flash loans of new pools, in percent of the amount borrowed
+pub fn default_flash_loan_fee() -> Decimal {
+  return dec!("0.09");
+}
+
+/// Longest window in epochs that time-weighted prices can be averaged over, bounds the price history kept
+pub const MAX_TWAP_WINDOW: u64 = 1000;
+
+/// Non-fungible data of the receipt of a flash loan, which must be returned to the pool in the same transaction
+///
+/// # Contains:
+/// * `resource_address`: ResourceAddress - Token borrowed from the pool
+/// * `amount`: Decimal - Amount of tokens borrowed
+/// * `fee`: Decimal - Fee owed on top of the amount borrowed
+#[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
+pub struct FlashLoanReceipt {
+  pub resource_address: ResourceAddress,
+  pub amount: Decimal,
+  pub fee: Decimal
+}
 
 blueprint! {
   /// Structure representing a Liquidity Pool for the Elision Exchange
@@ -9,11 +33,31 @@ blueprint! {
   /// * `provider_token_address`: ResourceAddress - Token that providers receive for adding liquidity
   /// * `provider_token_admin_badge`: Vault - Badge that gives authority to mint and burn tokens
   /// * `pool_fee`: Decimal - Value between 0 and 100 defining fees paid to liquidity pool
+  /// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100), off by default
+  /// * `protocol_fee_vaults`: HashMap<ResourceAddress, Vault> - Protocol fees collected until they are withdrawn
+  /// * `flash_loan_fee`: Decimal - Fee charged on flash loans in percent of the amount borrowed (0-100)
+  /// * `flash_loan_receipt_address`: ResourceAddress - Transient token that has to be burned to close a flash loan
+  /// * `flash_loan_counter`: u64 - Id of the next flash loan receipt
+  /// * `flash_loan_outstanding`: bool - Whether a flash loan has been taken out and not yet repaid, the reserves are
+  ///   short of the loan until then
+  /// * `price_cumulatives`: HashMap<ResourceAddress, Decimal> - Sum of the price of each token over every past epoch
+  /// * `price_accumulators`: HashMap<u64, HashMap<ResourceAddress, Decimal>> - Price cumulatives at the start of
+  ///   every epoch in which the reserves changed
+  /// * `accumulator_epochs`: Vec<u64> - Keys of `price_accumulators` in ascending order
   struct LiquidityPool {
     vaults: HashMap<ResourceAddress, Vault>,
     provider_token_address: ResourceAddress,
     provider_token_admin_badge: Vault,
-    pool_fee: Decimal
+    pool_fee: Decimal,
+    protocol_fee: Decimal,
+    protocol_fee_vaults: HashMap<ResourceAddress, Vault>,
+    flash_loan_fee: Decimal,
+    flash_loan_receipt_address: ResourceAddress,
+    flash_loan_counter: u64,
+    flash_loan_outstanding: bool,
+    price_cumulatives: HashMap<ResourceAddress, Decimal>,
+    price_accumulators: HashMap<u64, HashMap<ResourceAddress, Decimal>>,
+    accumulator_epochs: Vec<u64>
   }
 
   impl LiquidityPool {
@@ -23,6 +67,7 @@ blueprint! {
     /// * `token0`: Bucket - Contains first token to initialize the pool
     /// * `token1`: Bucket - Contains second token to initialize the pool
     /// * `pool_fee`: Decimal - Fee imposed on all swaps from this liquidity pool (0-100).
+    /// * `fee_admin_badge`: ResourceAddress - Badge with the authority to change the fees and withdraw protocol fees
     ///
     /// # Returns:
     /// * `ComponentAddress` - LiquidityPool component address for initialized pool
@@ -30,35 +75,16 @@ blueprint! {
     pub fn new(
       token0: Bucket,
       token1: Bucket,
-      pool_fee: Decimal
+      pool_fee: Decimal,
+      fee_admin_badge: ResourceAddress
     ) -> (ComponentAddress, Bucket) {
-      // Check to see if the liquidity pool has been created or not
-      assert_ne!(
-        token0.resource_address(), token1.resource_address(),
-        "[Pool Creation]: Liquidity Pool must be created using two different tokens."
-      );
-
-      assert_ne!(
-        borrow_resource_manager!(token0.resource_address()).resource_type(),
-        ResourceType::NonFungible,
-        "[Pool Creation]: Both assets must be fungible."
-      );
-
-      assert_ne!(
-        borrow_resource_manager!(token1.resource_address()).resource_type(),
-        ResourceType::NonFungible,
-        "[Pool Creation]: Both assets must be fungible."
-      );
-
+      assert_valid_pair(token0.resource_address(), token1.resource_address(), String::from("Pool Creation"));
       assert!(
         !token0.is_empty() & !token1.is_empty(),
         "[Pool Creation]: Cannot create a pool from an empty bucket."
       );
 
-      assert!(
-        (pool_fee >= Decimal::zero()) & (pool_fee <= dec!("100")),
-        "[Pool Creation]: Fee must be between 0 and 100."
-      );
+      assert_valid_pool_fee(pool_fee, String::from("Pool Creation"));
 
       // Sort buckets and create hashmap between vaults and buckets
       let (bucket0, bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
@@ -75,37 +101,118 @@ blueprint! {
       vaults.insert(bucket0.resource_address(), Vault::with_bucket(bucket0));
       vaults.insert(bucket1.resource_address(), Vault::with_bucket(bucket1));
 
-      // Create admin badge for the liquidity pool to give authority to mint and burn
-      let provider_token_admin_badge: Bucket = ResourceBuilder::new_fungible()
-        .divisibility(DIVISIBILITY_NONE)
-        .metadata("name", "Provider Token Admin Badge")
-        .metadata("symbol", "PTAB")
-        .metadata("description", "Admin Badge with the authority to mint and burn provider tokens")
-        .metadata("pid", format!("{}", pid))
-        .initial_supply(1);
-
-      // Create provider tokens and mint amount owed to initial liquidity provider
-      let provider_tokens: Bucket = ResourceBuilder::new_fungible()
-        .divisibility(DIVISIBILITY_MAXIMUM)
-        .metadata("name", format!("{} LP Provider Token", pair_name))
-        .metadata("symbol", "PT")
-        .metadata("description", "Token used to track liquidity provider ownership percentage over liquidity pool.")
+      // Price history starts empty at the creation epoch
+      let mut price_cumulatives: HashMap<ResourceAddress, Decimal> = HashMap::new();
+      price_cumulatives.insert(addresses.0, Decimal::zero());
+      price_cumulatives.insert(addresses.1, Decimal::zero());
+      let creation_epoch: u64 = Runtime::current_epoch();
+      let mut price_accumulators: HashMap<u64, HashMap<ResourceAddress, Decimal>> = HashMap::new();
+      price_accumulators.insert(creation_epoch, price_cumulatives.clone());
+
+      let (provider_token_admin_badge, provider_tokens): (Bucket, Bucket) = new_provider_tokens(
+        &pid, format!("{} LP Provider Token", pair_name)
+      );
+
+      // Flash loan receipts cannot be deposited anywhere, so a transaction only succeeds if they are burned by
+      // repaying the loan
+      let flash_loan_receipt_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", format!("{} Flash Loan Receipt", pair_name))
+        .metadata("symbol", "FLR")
+        .metadata("description", "Transient token recording a flash loan that has to be repaid in the same transaction.")
         .metadata("pid", format!("{}", pid))
         .mintable(rule!(require(provider_token_admin_badge.resource_address())), LOCKED)
         .burnable(rule!(require(provider_token_admin_badge.resource_address())), LOCKED)
-        .initial_supply(100);
+        .restrict_deposit(rule!(deny_all), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = fee_admin_access_rules(fee_admin_badge, vec!["set_flash_loan_fee"]);
 
       // Create and instantiate liquidity pool component
-      let liquidity_pool: ComponentAddress = Self {
+      let mut liquidity_pool = Self {
         vaults: vaults,
         provider_token_address: provider_tokens.resource_address(),
         provider_token_admin_badge: Vault::with_bucket(provider_token_admin_badge),
-        pool_fee: pool_fee
+        pool_fee: pool_fee,
+        protocol_fee: Decimal::zero(),
+        protocol_fee_vaults: new_empty_vaults(&[addresses.0, addresses.1]),
+        flash_loan_fee: default_flash_loan_fee(),
+        flash_loan_receipt_address: flash_loan_receipt_address,
+        flash_loan_counter: 0,
+        flash_loan_outstanding: false,
+        price_cumulatives: price_cumulatives,
+        price_accumulators: price_accumulators,
+        accumulator_epochs: vec![creation_epoch]
       }
-      .instantiate()
-      .globalize();
+      .instantiate();
+      liquidity_pool.add_access_check(access_rules);
+
+      return (liquidity_pool.globalize(), provider_tokens);
+    }
+
+    /// Changes the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - New fee for the pool (0-100)
+    pub fn set_pool_fee(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      update_fee(&mut self.pool_fee, pool_fee, String::from("Set Pool Fee"));
+    }
+
+    /// Obtain the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fee of the pool (0-100)
+    pub fn pool_fee(&self) -> Decimal {
+      return self.pool_fee;
+    }
+
+    /// Changes the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Arguments:
+    /// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn set_protocol_fee(
+      &mut self,
+      protocol_fee: Decimal
+    ) {
+      update_fee(&mut self.protocol_fee, protocol_fee, String::from("Set Protocol Fee"));
+    }
+
+    /// Obtain the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn protocol_fee(&self) -> Decimal {
+      return self.protocol_fee;
+    }
+
+    /// Changes the fee charged on flash loans from this liquidity pool
+    ///
+    /// # Arguments:
+    /// * `flash_loan_fee`: Decimal - Fee in percent of the amount borrowed (0-100)
+    pub fn set_flash_loan_fee(
+      &mut self,
+      flash_loan_fee: Decimal
+    ) {
+      update_fee(&mut self.flash_loan_fee, flash_loan_fee, String::from("Set Flash Loan Fee"));
+    }
+
+    /// Obtain the fee charged on flash loans from this liquidity pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fee in percent of the amount borrowed (0-100)
+    pub fn flash_loan_fee(&self) -> Decimal {
+      return self.flash_loan_fee;
+    }
 
-      return (liquidity_pool, provider_tokens);
+    /// Withdraws the protocol fees collected by the pool
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the protocol fees collected in the first token
+    /// * `Bucket` - Contains the protocol fees collected in the second token
+    pub fn withdraw_protocol_fees(&mut self) -> (Bucket, Bucket) {
+      return into_pair(take_protocol_fees(&mut self.protocol_fee_vaults));
     }
 
     /// Verifies that the given address belongs to liquidity pool or not
@@ -122,29 +229,12 @@ blueprint! {
       return self.vaults.contains_key(&address);
     }
 
-    /// Asserts that the given address belongs to the pool
-    ///
-    /// # Arguments:
-    /// * `address`: ResourceAddress - Address to verify belongs to the pool
-    /// * `label`: String - Label that called the assertion method
-    pub fn assert_belongs_to_pool(
-      &self,
-      address: ResourceAddress,
-      label: String
-    ) {
-      assert!(
-        self.belongs_to_pool(address),
-        "[{}]: Address does not belong to the pool",
-        label
-      );
-    }
-
-    /// Obtain token addresses in liquidity pool and returns them as a `Vec<ResourceAddress>`
+    /// Obtain token addresses in liquidity pool, sorted like the keys of the ElisionSwap registry
     ///
     /// # Returns:
     /// * `Vec<ResourceAddress>` - Vector of addresses that belong to the pool
     pub fn addresses(&self) -> Vec<ResourceAddress> {
-      return self.vaults.keys().cloned().collect::<Vec<ResourceAddress>>();
+      return vault_addresses(&self.vaults);
     }
 
     /// Obtain name of liquidity pool based on pair symbol.
@@ -152,11 +242,9 @@ blueprint! {
     /// # Returns:
     /// * `String` - Pair symbol string
     pub fn name(&self) -> String {
-      let addresses: Vec<ResourceAddress> = self.addresses();
-      return pair_symbol(addresses[0], addresses[1]);
+      return pool_name(&self.addresses());
     }
 
-
     /// Retrieve address of other resource if address belongs to the pool
     /// # Arguments
     /// * `resource_address`: ResourceAddress - Address for token from the pool
@@ -167,12 +255,7 @@ blueprint! {
       &self,
       resource_address: ResourceAddress
     ) -> ResourceAddress {
-      // Verify address belongs to the pool
-      self.assert_belongs_to_pool(resource_address, String::from("Other Resource ResourceAddress"));
-
-      // Check which address was passed in and return the other address
-      let addresses: Vec<ResourceAddress> = self.addresses();
-      return if addresses[0] == resource_address {addresses[1]} else {addresses[0]};
+      return other_address(&self.vaults, resource_address);
     }
 
     /// Calculates Market Maker Equation: `x * y = k`.
@@ -184,6 +267,158 @@ blueprint! {
       return self.vaults[&addresses[0]].amount() * self.vaults[&addresses[1]].amount()
     }
 
+    /// Calculates the current price of a token in the pool based on the reserves
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Address of the token to price
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of a single unit of the token denominated in the other token
+    pub fn spot_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, resource_address, String::from("Spot Price"));
+      self.assert_no_flash_loan(String::from("Spot Price"));
+      return self.reserve_price(resource_address);
+    }
+
+    /// Calculates the price of a token from the reserves as they are, without checking for an open flash loan
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Address of the token to price
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of a single unit of the token denominated in the other token
+    fn reserve_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> Decimal {
+      let x: Decimal = self.vaults[&resource_address].amount();
+      let y: Decimal = self.vaults[&self.other_resource_address(resource_address)].amount();
+      return y / x;
+    }
+
+    /// Assert that no flash loan is open, the reserves do not reflect the pool while the loaned tokens are out
+    ///
+    /// # Arguments:
+    /// * `label`: String - Label for the assertion output
+    fn assert_no_flash_loan(
+      &self,
+      label: String
+    ) {
+      assert!(
+        !self.flash_loan_outstanding,
+        "[{}]: Not available while a flash loan of the pool is open.",
+        label
+      );
+    }
+
+    /// Adds the prices of the epochs since the last change of the reserves to the price cumulatives
+    ///
+    /// # Note:
+    /// * Called before the reserves change, so the prices added are the ones the reserves had until now and a price
+    ///   set within the current epoch only starts counting once the epoch is over
+    /// * Only the first call of an epoch changes the cumulatives, a transaction moving the reserves back and forth
+    ///   within an epoch has no effect on the time-weighted price
+    fn update_price_accumulators(&mut self) {
+      let current_epoch: u64 = Runtime::current_epoch();
+      let last_epoch: u64 = *self.accumulator_epochs.last().unwrap();
+      if current_epoch <= last_epoch {
+        return;
+      }
+
+      let price_cumulatives: HashMap<ResourceAddress, Decimal> = self.cumulative_prices_at(current_epoch);
+      self.price_cumulatives = price_cumulatives.clone();
+      self.price_accumulators.insert(current_epoch, price_cumulatives);
+      self.accumulator_epochs.push(current_epoch);
+
+      // Keep a single accumulator from before the longest window so that it can still be interpolated
+      let cutoff_epoch: u64 = current_epoch.saturating_sub(MAX_TWAP_WINDOW);
+      while (self.accumulator_epochs.len() > 1) & (self.accumulator_epochs[1] <= cutoff_epoch) {
+        let epoch: u64 = self.accumulator_epochs.remove(0);
+        self.price_accumulators.remove(&epoch);
+      }
+    }
+
+    /// Calculates the price cumulatives of both tokens at the start of an epoch
+    ///
+    /// # Arguments:
+    /// * `epoch`: u64 - Epoch to calculate the cumulatives at, not before the oldest accumulator
+    ///
+    /// # Returns:
+    /// * `HashMap<ResourceAddress, Decimal>` - Sum of the price of each token over every epoch before `epoch`
+    ///
+    /// # Note:
+    /// * The price stays the same between two accumulators, so the cumulatives grow linearly between them
+    /// * After the last accumulator, the current reserves give the price of every epoch since then
+    fn cumulative_prices_at(
+      &self,
+      epoch: u64
+    ) -> HashMap<ResourceAddress, Decimal> {
+      let last_epoch: u64 = *self.accumulator_epochs.last().unwrap();
+      let mut price_cumulatives: HashMap<ResourceAddress, Decimal> = HashMap::new();
+
+      if epoch >= last_epoch {
+        let elapsed: Decimal = Decimal::from(epoch - last_epoch);
+        let empty: bool = self.vaults.values().any(|vault| vault.is_empty());
+        for (address, cumulative) in self.price_cumulatives.iter() {
+          // Prices are undefined while the pool is empty, these epochs count as a price of zero
+          let price: Decimal = if empty { Decimal::zero() } else { self.reserve_price(*address) };
+          price_cumulatives.insert(*address, *cumulative + price * elapsed);
+        }
+        return price_cumulatives;
+      }
+
+      let index: usize = self.accumulator_epochs.iter().rposition(|accumulator_epoch| *accumulator_epoch <= epoch)
+        .expect("[TWAP]: Not enough price history for the window.");
+      let start_epoch: u64 = self.accumulator_epochs[index];
+      let end_epoch: u64 = self.accumulator_epochs[index + 1];
+      let start: &HashMap<ResourceAddress, Decimal> = &self.price_accumulators[&start_epoch];
+      let end: &HashMap<ResourceAddress, Decimal> = &self.price_accumulators[&end_epoch];
+      for (address, cumulative) in start.iter() {
+        let price: Decimal = (end[address] - *cumulative) / Decimal::from(end_epoch - start_epoch);
+        price_cumulatives.insert(*address, *cumulative + price * Decimal::from(epoch - start_epoch));
+      }
+      return price_cumulatives;
+    }
+
+    /// Obtain the time-weighted average price of a token over the last epochs
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Address of the token to price
+    /// * `window_epochs`: u64 - Number of epochs to average the price over, at most `MAX_TWAP_WINDOW`
+    ///
+    /// # Returns:
+    /// * `Decimal` - Average price of a single unit of the token denominated in the other token
+    ///
+    /// # Note:
+    /// * The current epoch is not part of the window, so the price cannot be moved by trading in the same
+    ///   transaction that reads it
+    pub fn twap(
+      &self,
+      resource_address: ResourceAddress,
+      window_epochs: u64
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, resource_address, String::from("TWAP"));
+      self.assert_no_flash_loan(String::from("TWAP"));
+      assert!(
+        (window_epochs > 0) & (window_epochs <= MAX_TWAP_WINDOW),
+        "[TWAP]: Window must be between 1 and {} epochs.",
+        MAX_TWAP_WINDOW
+      );
+
+      let current_epoch: u64 = Runtime::current_epoch();
+      assert!(
+        current_epoch >= self.accumulator_epochs[0] + window_epochs,
+        "[TWAP]: Not enough price history for the window."
+      );
+
+      let end: Decimal = self.cumulative_prices_at(current_epoch)[&resource_address];
+      let start: Decimal = self.cumulative_prices_at(current_epoch - window_epochs)[&resource_address];
+      return (end - start) / Decimal::from(window_epochs);
+    }
+
     /// Calculates amount of output that can be given based on the amount of input
     /// # Arguments:
     /// * `input_address`: ResourceAddress - Input token address
@@ -204,7 +439,7 @@ blueprint! {
       input_amount: Decimal
     ) -> Decimal {
       // Checking if the passed resource address belongs to this pool.
-      self.assert_belongs_to_pool(input_resource_address, String::from("Calculate Output"));
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Output"));
 
       let x: Decimal = self.vaults[&input_resource_address].amount();
       let y: Decimal = self.vaults[&self.other_resource_address(input_resource_address)].amount();
@@ -235,7 +470,7 @@ blueprint! {
       output_amount: Decimal
     ) -> Decimal {
       // Checking if the passed resource address belongs to this pool.
-      self.assert_belongs_to_pool(output_resource_address, String::from("Calculate Input"));
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Calculate Input"));
 
       let x: Decimal = self.vaults[&self.other_resource_address(output_resource_address)].amount();
       let y: Decimal = self.vaults[&output_resource_address].amount();
@@ -246,6 +481,26 @@ blueprint! {
       return dx;
     }
 
+    /// Quotes the amount of input required for an amount of output, without failing when the pool cannot give it
+    ///
+    /// # Arguments:
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Option<Decimal>` - Calculated input amount, None if the output is not below the reserves of the pool
+    pub fn quote_input_amount(
+      &self,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Option<Decimal> {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Quote Input"));
+      if output_amount >= self.vaults[&output_resource_address].amount() {
+        return None;
+      }
+      return Some(self.calculate_input_amount(output_resource_address, output_amount));
+    }
+
     /// Deposits a bucket of tokens into this liquidity pool
     ///
     /// # Arguments:
@@ -254,9 +509,8 @@ blueprint! {
       &mut self,
       bucket: Bucket
     ) {
-      // Verify that the resource belongs to the liquidity pool
-      self.assert_belongs_to_pool(bucket.resource_address(), String::from("Deposit"));
-      self.vaults.get_mut(&bucket.resource_address()).unwrap().put(bucket);
+      self.update_price_accumulators();
+      deposit_into(&mut self.vaults, bucket);
     }
 
     /// Withdraws tokens from the liquidity pool
@@ -272,17 +526,8 @@ blueprint! {
       resource_address: ResourceAddress,
       amount: Decimal
     ) -> Bucket {
-      // Performing the checks to ensure tha the withdraw can actually go through
-      self.assert_belongs_to_pool(resource_address, String::from("Withdraw"));
-
-      // Getting the vault of that resource and checking if there is enough liquidity to perform the withdraw.
-      let vault: &mut Vault = self.vaults.get_mut(&resource_address).unwrap();
-      assert!(
-        vault.amount() >= amount,
-        "[Withdraw]: Not enough liquidity available for the withdraw."
-      );
-
-      return vault.take(amount);
+      self.update_price_accumulators();
+      return withdraw_from(&mut self.vaults, resource_address, amount);
     }
 
     /// Adds liquidity to the pool in exchange for liquidity provider tokens
@@ -290,6 +535,7 @@ blueprint! {
     /// # Arguments:
     /// * `token0`: Bucket - Contains the amount of the first token to add to the pool
     /// * `token1`: Bucket - Contains the amount of the second token to add to the pool
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
     ///
     /// # Returns:
     /// * `Bucket` - Contains remaining tokens of the `token0`
@@ -299,126 +545,257 @@ blueprint! {
       &mut self,
       token0: Bucket,
       token1: Bucket,
+      deadline: u64
     ) -> (Bucket, Bucket, Bucket) {
-      // Verify if the tokens belong to this liquidity pool.
-      self.assert_belongs_to_pool(token0.resource_address(), String::from("Add Liquidity"));
-      self.assert_belongs_to_pool(token1.resource_address(), String::from("Add Liquidity"));
+      assert_deadline(deadline, String::from("Add Liquidity"));
+      self.assert_no_flash_loan(String::from("Add Liquidity"));
 
-      // Verify that the buckets passed are not empty
-      assert!(!token0.is_empty(), "[Add Liquidity]: Cannot add liquidity from an empty bucket");
-      assert!(!token1.is_empty(), "[Add Liquidity]: Cannot add liquidity from an empty bucket");
-      info!(
-        "[Add Liquidity]: Requested adding liquidity of amounts, {}: {}, {}: {}",
-        token0.resource_address(), token0.amount(), token1.resource_address(), token1.amount()
+      // Price cumulatives are brought up to date before the reserves change
+      self.update_price_accumulators();
+      return add_pair_liquidity(
+        &mut self.vaults, &self.provider_token_admin_badge, self.provider_token_address, token0, token1
       );
+    }
 
-      // Sorting out the two buckets passed and getting the values of `dm` and `dn`.
-      let (mut bucket0, mut bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
-      let dm: Decimal = bucket0.amount();
-      let dn: Decimal = bucket1.amount();
-
-      // Getting the values of m and n from the liquidity pool vaults
-      let m: Decimal = self.vaults[&bucket0.resource_address()].amount();
-      let n: Decimal = self.vaults[&bucket1.resource_address()].amount();
-      info!(
-        "[Add Liquidity]: Current reserves: {}: {}, {}: {}",
-        bucket0.resource_address(), m, bucket1.resource_address(), n
+    /// Removes the percentage of the liquidity owed to this liquidity provider
+    /// # Arguments:
+    /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains share of liquidity provider of the first token.
+    /// * `Bucket` - Contains share of liquidity provider of the second token.
+    pub fn remove_liquidity(
+      &mut self,
+      provider_tokens: Bucket,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Remove Liquidity"));
+      self.assert_no_flash_loan(String::from("Remove Liquidity"));
+      let percentage: Decimal = burn_provider_tokens(
+        &self.provider_token_admin_badge, self.provider_token_address, provider_tokens
       );
 
-      // Compute amount to deposit into liquidity pool based on each bucket passed in
-      let (amount0, amount1): (Decimal, Decimal) = if (
-        (m == Decimal::zero()) | (n == Decimal::zero())) | ((m / n) == (dm / dn)
-      ) {
-        (dm, dn)
-      } else if (m / n) < (dm / dn) {
-        (dn * m / n, dn)
-      } else {
-        (dm, dm * n / m)
-      };
+      // Withdrawing the amount of tokens owed to this liquidity provider
+      self.update_price_accumulators();
+      return into_pair(withdraw_share(&mut self.vaults, percentage));
+    }
 
-      info!(
-        "[Add Liquidity]: Liquidity amount to add: {}: {}, {}: {}",
-        bucket0.resource_address(), amount0, bucket1.resource_address(), amount1
+    /// Calculates the amount of a token to swap so that the rest of it and the output are in the ratio of the pool
+    ///
+    /// # Arguments:
+    /// * `input_resource_address`: ResourceAddress - Address of the token held
+    /// * `input_amount`: Decimal - Amount of the token held
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amount of the token to swap for the other token
+    ///
+    /// # Note:
+    /// * `x` - The amount of reserves of the token held
+    /// * `A` - The amount of the token held
+    /// * `r` - The fee modifier where `r = (100 - fee) / 100`
+    /// * `s` - The amount to swap, the positive root of `r * s^2 + (1 + r) * x * s - A * x = 0`, computed as
+    ///   `s = x * (sqrt((1 + r)^2 + 4 * r * A / x) - (1 + r)) / (2 * r)` to keep the intermediate values small
+    pub fn calculate_zap_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      input_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Zap"));
+
+      let x: Decimal = self.vaults[&input_resource_address].amount();
+      let r: Decimal = (dec!("100") - self.pool_fee) / dec!("100");
+      let b: Decimal = Decimal::one() + r;
+
+      return x * (sqrt(b * b + dec!("4") * r * input_amount / x) - b) / (dec!("2") * r);
+    }
+
+    /// Adds liquidity from a single token by swapping the right share of it for the other token first
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the token to add to the pool
+    /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains remaining tokens of the first token of the pool, dust left by rounding
+    /// * `Bucket` - Contains remaining tokens of the second token of the pool, dust left by rounding
+    /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+    pub fn zap_in(
+      &mut self,
+      mut tokens: Bucket,
+      min_provider_tokens: Decimal,
+      deadline: u64
+    ) -> (Bucket, Bucket, Bucket) {
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Zap In"));
+      assert_deadline(deadline, String::from("Zap In"));
+      self.assert_no_flash_loan(String::from("Zap In"));
+      assert!(!tokens.is_empty(), "[Zap In]: Cannot add liquidity from an empty bucket");
+
+      let swap_amount: Decimal = self.calculate_zap_amount(tokens.resource_address(), tokens.amount());
+      info!("[Zap In]: Swapping {} of {} input tokens before adding liquidity", swap_amount, tokens.amount());
+      let swapped_tokens: Bucket = self.swap(tokens.take(swap_amount), Decimal::zero(), deadline);
+
+      let (remaining0, remaining1, provider_tokens): (Bucket, Bucket, Bucket) = self.add_liquidity(
+        tokens, swapped_tokens, deadline
       );
+      assert!(
+        provider_tokens.amount() >= min_provider_tokens,
+        "[Zap In]: min_provider_tokens not satisfied."
+      );
+      return (remaining0, remaining1, provider_tokens);
+    }
 
-      // Deposit calculated token amounts into liquidity pool
-      self.deposit(bucket0.take(amount0));
-      self.deposit(bucket1.take(amount1));
+    /// Removes liquidity and swaps the share of the other token so that only one token is returned
+    ///
+    /// # Arguments:
+    /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+    /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the share of liquidity provider, entirely in the output token
+    pub fn zap_out(
+      &mut self,
+      provider_tokens: Bucket,
+      output_resource_address: ResourceAddress,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Zap Out"));
+      self.assert_no_flash_loan(String::from("Zap Out"));
 
-      // Compute and mint the amount of provider tokens that the liquidity provider is owed
-      let provider_tokens_manager: &ResourceManager = borrow_resource_manager!(self.provider_token_address);
-      let provider_amount: Decimal = if provider_tokens_manager.total_supply() == Decimal::zero() {
-        dec!("100.00")
+      let (bucket0, bucket1): (Bucket, Bucket) = self.remove_liquidity(provider_tokens, deadline);
+      let (mut output_tokens, other_tokens): (Bucket, Bucket) = if bucket0.resource_address() == output_resource_address {
+        (bucket0, bucket1)
       } else {
-        amount0 * provider_tokens_manager.total_supply() / m
+        (bucket1, bucket0)
       };
-      let provider_tokens: Bucket = self.provider_token_admin_badge.authorize(|| {
-        provider_tokens_manager.mint(provider_amount)
-      });
-      info!("[Add Liquidity]: Owed amount of provider tokens: {}", provider_amount);
 
-      // Return remaining provider tokens, token0, and token1
-      return (bucket0, bucket1, provider_tokens);
+      // The other token is swapped against the reserves left after the liquidity was removed
+      output_tokens.put(self.swap(other_tokens, Decimal::zero(), deadline));
+      assert!(output_tokens.amount() >= min_amount_out, "[Zap Out]: min_amount_out not satisfied.");
+      info!("[Zap Out]: Amount given out: {}", output_tokens.amount());
+      return output_tokens;
     }
 
-    /// Removes the percentage of the liquidity owed to this liquidity provider
+    /// Lends tokens from the reserves of the pool that have to be repaid with a fee within the same transaction
+    ///
     /// # Arguments:
-    /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+    /// * `resource_address`: ResourceAddress - Token to borrow
+    /// * `amount`: Decimal - Amount of tokens to borrow
     ///
     /// # Returns:
-    /// * `Bucket` - Contains share of liquidity provider of the first token.
-    /// * `Bucket` - Contains share of liquidity provider of the second token.
-    pub fn remove_liquidity(
+    /// * `Bucket` - Contains the borrowed tokens
+    /// * `Bucket` - Contains the receipt of the loan, to be passed to `repay_flash_loan` with the repayment
+    ///
+    /// # Note:
+    /// * The receipt cannot be deposited into any component, the transaction fails unless the receipt is burned by
+    ///   `repay_flash_loan` before it ends
+    /// * Only one loan can be open at a time, and swaps, liquidity changes and prices of the pool are unavailable
+    ///   until it is repaid so that nothing is priced off the reserves while they are short of the loan
+    pub fn flash_loan(
       &mut self,
-      provider_tokens: Bucket
+      resource_address: ResourceAddress,
+      amount: Decimal
     ) -> (Bucket, Bucket) {
-      // Verify that the provider tokens belong to this exchange
+      assert_belongs_to_pool(&self.vaults, resource_address, String::from("Flash Loan"));
+      assert!(amount > Decimal::zero(), "[Flash Loan]: Amount borrowed must be greater than zero.");
+      self.assert_no_flash_loan(String::from("Flash Loan"));
+
+      let loan: Bucket = self.withdraw(resource_address, amount);
+      self.flash_loan_outstanding = true;
+      let fee: Decimal = amount * self.flash_loan_fee / dec!("100");
+      info!("[Flash Loan]: Lending {} of {} for a fee of {}", amount, resource_address, fee);
+
+      let receipt_id: NonFungibleId = NonFungibleId::from_u64(self.flash_loan_counter);
+      self.flash_loan_counter += 1;
+      let receipt_manager: &ResourceManager = borrow_resource_manager!(self.flash_loan_receipt_address);
+      let receipt: Bucket = self.provider_token_admin_badge.authorize(|| {
+        receipt_manager.mint_non_fungible(&receipt_id, FlashLoanReceipt {
+          resource_address: resource_address,
+          amount: amount,
+          fee: fee
+        })
+      });
+
+      return (loan, receipt);
+    }
+
+    /// Repays a flash loan and burns its receipt, the fee is kept by the pool for its liquidity providers
+    ///
+    /// # Arguments:
+    /// * `repayment`: Bucket - Contains at least the amount borrowed plus the fee, in the borrowed token
+    /// * `receipt`: Bucket - Contains the receipt of the loan
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens of the repayment above the amount owed
+    pub fn repay_flash_loan(
+      &mut self,
+      mut repayment: Bucket,
+      receipt: Bucket
+    ) -> Bucket {
       assert_eq!(
-        provider_tokens.resource_address(),
-        self.provider_token_address,
-        "[Remove Liquidity]: Provider token does not belong to this liquidity pool"
+        receipt.resource_address(), self.flash_loan_receipt_address,
+        "[Repay Flash Loan]: Receipt does not belong to this liquidity pool."
       );
+      assert_eq!(receipt.amount(), Decimal::one(), "[Repay Flash Loan]: Loans are repaid one at a time.");
 
-      // Calculating the percentage ownership that provider tokens correspond to
-      let provider_tokens_manager: &ResourceManager = borrow_resource_manager!(self.provider_token_address);
-      let percentage: Decimal = provider_tokens.amount() / provider_tokens_manager.total_supply();
+      let loan: FlashLoanReceipt = receipt.non_fungible::<FlashLoanReceipt>().data();
+      assert_eq!(
+        repayment.resource_address(), loan.resource_address,
+        "[Repay Flash Loan]: Loan must be repaid in the borrowed token."
+      );
+      let amount_owed: Decimal = loan.amount + loan.fee;
+      assert!(
+        repayment.amount() >= amount_owed,
+        "[Repay Flash Loan]: {} tokens are owed on the loan.",
+        amount_owed
+      );
 
-      // Burning the provider tokens
+      self.flash_loan_outstanding = false;
+      self.deposit(repayment.take(amount_owed));
       self.provider_token_admin_badge.authorize(|| {
-        provider_tokens.burn();
+        receipt.burn();
       });
+      info!("[Repay Flash Loan]: Repaid {} including a fee of {}", amount_owed, loan.fee);
 
-      // Withdrawing the amount of tokens owed to this liquidity provider
-      let addresses: Vec<ResourceAddress> = self.addresses();
-      let bucket0: Bucket = self.withdraw(addresses[0], self.vaults[&addresses[0]].amount() * percentage);
-      let bucket1: Bucket = self.withdraw(addresses[1], self.vaults[&addresses[1]].amount() * percentage);
-
-      return (bucket0, bucket1);
+      return repayment;
     }
 
     /// Execute token swap and take pool fee
     ///
     /// # Arguments:
     /// * `tokens`: Bucket - Contains the input tokens that will be swapped for other tokens
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains the other tokens
     pub fn swap(
       &mut self,
-      tokens: Bucket
+      mut tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
     ) -> Bucket {
-      // Verify that tokens belong to this liquidity pool
-      self.assert_belongs_to_pool(tokens.resource_address(), String::from("Swap"));
+      // Verify that tokens belong to this liquidity pool and that the swap is not late
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap"));
+      assert_deadline(deadline, String::from("Swap"));
+      self.assert_no_flash_loan(String::from("Swap"));
       info!("[Swap]: K before swap: {}", self.k());
 
       // Calculating the output amount for the given input amount of tokens and withdrawing it from the vault
       let output_amount: Decimal = self.calculate_output_amount(tokens.resource_address(), tokens.amount());
+      assert!(output_amount >= min_amount_out, "[Swap]: min_amount_out not satisfied.");
       let output_tokens: Bucket = self.withdraw(
         self.other_resource_address(tokens.resource_address()),
         output_amount
       );
 
-      // Deposit tokens into liquidity pool and return bucket of swapped tokens
+      // Deposit tokens into liquidity pool, minus the protocol fee, and return bucket of swapped tokens
+      let protocol_amount: Decimal = protocol_fee_amount(tokens.amount(), self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut tokens, protocol_amount);
       self.deposit(tokens);
       info!("[Swap]: K after swap: {}", self.k());
       return output_tokens;
@@ -429,20 +806,19 @@ blueprint! {
     /// # Arguments:
     /// * `tokens`: Bucket - Contains input tokens that will be swapped
     /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains other tokens
     pub fn swap_exact_tokens_for_tokens(
       &mut self,
       tokens: Bucket,
-      min_amount_out: Decimal
+      min_amount_out: Decimal,
+      deadline: u64
     ) -> Bucket {
       // Verify that the bucket passed belongs to liquidity pool
-      self.assert_belongs_to_pool(tokens.resource_address(), String::from("Swap Exact"));
-      let output_tokens: Bucket = self.swap(tokens);
-      assert!(output_tokens.amount() >= min_amount_out, "[Swap Exact]: min_amount_out not satisfied.");
-
-      return output_tokens;
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap Exact"));
+      return self.swap(tokens, min_amount_out, deadline);
     }
 
     /// Swaps input tokens for an exact amount of output tokens
@@ -450,6 +826,8 @@ blueprint! {
     /// # Arguments:
     /// * `tokens`: Bucket - Contains tokens that the user wishes to swap
     /// * `output_amount`: Decimal - Specific amount of output that the user wishes to receive
+    /// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains output tokens
@@ -457,24 +835,28 @@ blueprint! {
     pub fn swap_tokens_for_exact_tokens(
       &mut self,
       mut tokens: Bucket,
-      output_amount: Decimal
+      output_amount: Decimal,
+      max_input: Decimal,
+      deadline: u64
     ) -> (Bucket, Bucket) {
-      // Verify that the bucket passed does belong to this liquidity pool
-      self.assert_belongs_to_pool(tokens.resource_address(), String::from("Swap For Exact"));
+      // Verify that the bucket passed does belong to this liquidity pool and that the swap is not late
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap For Exact"));
+      assert_deadline(deadline, String::from("Swap For Exact"));
+      self.assert_no_flash_loan(String::from("Swap For Exact"));
 
       // Calculate amount of input tokens required for output token amount
       let input_required: Decimal = self.calculate_input_amount(
         self.other_resource_address(tokens.resource_address()),
         output_amount
       );
-      assert!(
-        tokens.amount() >= input_required,
-        "[Swap For Exact]: Not enough input for the desired amount of output."
-      );
+      assert_input_sufficient(tokens.amount(), input_required, max_input);
 
       // Depositing the amount of input required into the vaults and taking out the requested amount
       info!("[Swap For Exact]: K before swap: {}", self.k());
-      self.deposit(tokens.take(input_required));
+      let mut input_tokens: Bucket = tokens.take(input_required);
+      let protocol_amount: Decimal = protocol_fee_amount(input_required, self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut input_tokens, protocol_amount);
+      self.deposit(input_tokens);
       let output_tokens: Bucket = self.withdraw(
         self.other_resource_address(tokens.resource_address()),
         output_amount
@@ -1,65 +1,817 @@
 use scrypto::prelude::*;
 use sbor::*;
+use crate::utils::*;
+use crate::oracle::*;
+use crate::pricing::*;
+use crate::collateral::*;
 
+/// Non-fungible data held by the buyer of an option
+///
+/// # Contains:
+/// * `option_type`: OptionType - Whether the option is a call or a put
+/// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+/// * `amount`: Decimal - Units of the underlying token covered by the option
+/// * `expiry_epoch`: u64 - Epoch from which the option can no longer be exercised
+/// * `state`: OptionState - Marked as expired once the option has been settled without exercise
 #[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
-struct OptionTrade {
-  option_type: OptionType
+pub struct OptionTrade {
+  pub option_type: OptionType,
+  pub strike: Decimal,
+  pub amount: Decimal,
+  pub expiry_epoch: u64,
+  #[scrypto(mutable)]
+  pub state: OptionState
 }
 
-#[derive(TypeId, Encode, Decode, Describe)]
+/// Non-fungible data held by the writer of an option
+///
+/// # Contains:
+/// * `collateral_address`: ResourceAddress - Token locked as collateral for the option
+/// * `locked_amount`: Decimal - Amount of collateral locked when the option was written
+#[derive(NonFungibleData, TypeId, Encode, Decode, Describe)]
+pub struct OptionWriter {
+  pub collateral_address: ResourceAddress,
+  pub locked_amount: Decimal
+}
+
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
 pub enum OptionType {
   Call,
   Put
 }
 
-#[derive(TypeId, Encode, Decode, Describe)]
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Debug)]
 pub enum OptionState {
   Invalid,
+  Written,
   Active,
   Exercised,
-  Expired
+  Expired,
+  Canceled
 }
 
 #[derive(TypeId, Encode, Decode, Describe)]
-struct Option {
-  state: OptionState,
-  locked_amount: Decimal,
-  hedge_premium: Decimal,
-  unhedge_premium: Decimal,
-  amount: Decimal,
-  created_epoch: u64,
-  expiry_epoch: u64,
-  settlement_fee_address: ResourceAddress,
+pub struct Option {
+  pub state: OptionState,
+  pub option_type: OptionType,
+  pub locked_amount: Decimal,
+  pub premium: Decimal,
+  pub hedge_premium: Decimal,
+  pub unhedge_premium: Decimal,
+  pub amount: Decimal,
+  pub created_epoch: u64,
+  pub expiry_epoch: u64,
+  pub settlement_fee_address: ResourceAddress,
+  pub strike: Decimal,
+}
+
+/// Calculates the collateral that has to be locked to fully cover an option
+///
+/// # Arguments:
+/// * `option_type`: OptionType - Whether the option is a call or a put
+/// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+/// * `amount`: Decimal - Units of the underlying token covered by the option
+///
+/// # Returns:
+/// * `Decimal` - Amount of underlying tokens for a call, or quote tokens for a put
+pub fn collateral_required(
+  option_type: OptionType,
+  strike: Decimal,
+  amount: Decimal
+) -> Decimal {
+  return match option_type {
+    OptionType::Call => amount,
+    OptionType::Put => amount * strike
+  }
+}
+
+/// Calculates the intrinsic value of an option at the given price
+///
+/// # Arguments:
+/// * `option_type`: OptionType - Whether the option is a call or a put
+/// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+/// * `amount`: Decimal - Units of the underlying token covered by the option
+/// * `price`: Decimal - Price of the underlying token denominated in the quote token
+///
+/// # Returns:
+/// * `Decimal` - Payoff in underlying tokens for a call, or quote tokens for a put
+pub fn option_payoff(
+  option_type: OptionType,
   strike: Decimal,
+  amount: Decimal,
+  price: Decimal
+) -> Decimal {
+  return match option_type {
+    OptionType::Call if price > strike => amount * (price - strike) / price,
+    OptionType::Put if strike > price => amount * (strike - price),
+    _ => Decimal::zero()
+  }
+}
+
+/// Obtain the collateral position of the writer of an option
+///
+/// # Arguments:
+/// * `option`: &Option - Record of the option
+///
+/// # Returns:
+/// * `Position` - Locked collateral against the maximum payoff that can be owed to the buyer
+///
+/// # Note:
+/// * Both sides are in the collateral token, so the collateralization ratio is valued without the oracle
+pub fn option_position(option: &Option) -> Position {
+  return Position {
+    collateral_address: option.settlement_fee_address,
+    collateral_amount: option.locked_amount,
+    exposure_address: option.settlement_fee_address,
+    exposure_amount: collateral_required(option.option_type, option.strike, option.amount)
+  }
 }
 
+/// Number of steps of the binomial tree used by newly created options markets
+const DEFAULT_PRICING_STEPS: u64 = 32;
+
+/// Asserts that the parameters used to quote premiums can build a binomial tree
+///
+/// # Arguments:
+/// * `volatility`: Decimal - Volatility of the underlying per epoch
+/// * `risk_free_rate`: Decimal - Risk-free rate per epoch
+/// * `pricing_steps`: u64 - Number of steps of the binomial tree
+fn assert_pricing_parameters(
+  volatility: Decimal,
+  risk_free_rate: Decimal,
+  pricing_steps: u64
+) {
+  assert!(volatility > Decimal::zero(), "[Pricing Parameters]: Volatility must be greater than zero.");
+  assert!(risk_free_rate >= Decimal::zero(), "[Pricing Parameters]: Risk-free rate cannot be negative.");
+  assert!(
+    (pricing_steps > 0) & (pricing_steps <= MAX_PRICING_STEPS),
+    "[Pricing Parameters]: Number of steps must be between 1 and {}.",
+    MAX_PRICING_STEPS
+  );
+}
 
 blueprint! {
+  /// Structure representing the peer-to-peer options market for an underlying and quote token
+  ///
+  /// # Contains:
+  /// * `option_nft_address`: ResourceAddress - Non-fungible `OptionTrade` tokens issued to option buyers
+  /// * `writer_nft_address`: ResourceAddress - Non-fungible `OptionWriter` tokens issued to option writers
+  /// * `nft_minter_badge`: Vault - Badge that gives authority to mint and burn option tokens
+  /// * `underlying_address`: ResourceAddress - Token the options are written on, locked for calls
+  /// * `quote_address`: ResourceAddress - Token the strike is denominated in, locked for puts
+  /// * `ticker`: String - Symbol of the underlying and quote pair
+  /// * `price_oracle`: PriceOracle - Oracle used to price the underlying when quoting and exercising
+  /// * `volatility`: Decimal - Volatility of the underlying per epoch used to quote premiums
+  /// * `risk_free_rate`: Decimal - Risk-free rate per epoch used to quote premiums
+  /// * `pricing_steps`: u64 - Number of steps of the binomial tree used to quote premiums
+  /// * `minimum_collateralization_ratio`: Decimal - Minimum ratio of locked collateral to maximum payoff
+  /// * `settlement_fee`: Decimal - Percentage of the payoff of exercised options collected by the protocol (0-100)
+  /// * `fee_vaults`: HashMap<ResourceAddress, Vault> - Settlement fees collected in the underlying and quote tokens
+  /// * `options`: HashMap<NonFungibleId, Option> - Records of every option written on the market
+  /// * `vaults`: HashMap<NonFungibleId, Vault> - Collateral and premium held for each option
+  /// * `option_counter`: u64 - Id assigned to the next option written
   struct OptionsController {
     option_nft_address: ResourceAddress,
-    strike: Decimal,
-    premium: Decimal,
-    origin_epoch: u64,
-    expiry_epoch: u64,
-    price: Decimal,
-    exercised: bool,
-    canceled: bool,
+    writer_nft_address: ResourceAddress,
+    nft_minter_badge: Vault,
+    underlying_address: ResourceAddress,
+    quote_address: ResourceAddress,
     ticker: String,
-    options_nft_id: NonFungibleData,
-    latest_cost: Decimal
+    price_oracle: PriceOracle,
+    volatility: Decimal,
+    risk_free_rate: Decimal,
+    pricing_steps: u64,
+    minimum_collateralization_ratio: Decimal,
+    settlement_fee: Decimal,
+    fee_vaults: HashMap<ResourceAddress, Vault>,
+    options: HashMap<NonFungibleId, Option>,
+    vaults: HashMap<NonFungibleId, Vault>,
+    option_counter: u64
   }
 
-  impl OptionsTrade {
+  impl OptionsController {
+    /// Create new options market for the given underlying and quote tokens
+    ///
+    /// # Arguments:
+    /// * `underlying_address`: ResourceAddress - Token the options are written on
+    /// * `quote_address`: ResourceAddress - Token the strike and premiums of puts are denominated in
+    /// * `price_oracle`: ComponentAddress - PriceOracle component used to price the underlying
+    /// * `volatility`: Decimal - Volatility of the underlying per epoch used to quote premiums
+    /// * `risk_free_rate`: Decimal - Risk-free rate per epoch used to quote premiums
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - OptionsController component address for the new market
+    /// * `Bucket` - Contains the admin badge that has the authority to update market parameters
     pub fn new(
-      buyer: Address,
-      writer: Address,
+      underlying_address: ResourceAddress,
+      quote_address: ResourceAddress,
+      price_oracle: ComponentAddress,
+      volatility: Decimal,
+      risk_free_rate: Decimal
+    ) -> (ComponentAddress, Bucket) {
+      assert_ne!(
+        underlying_address, quote_address,
+        "[Options Creation]: Underlying and quote tokens must be different."
+      );
+
+      assert_pricing_parameters(volatility, risk_free_rate, DEFAULT_PRICING_STEPS);
+
+      let ticker: String = format!("{}/{}", token_symbol(underlying_address), token_symbol(quote_address));
+      info!("[Options Creation]: Creating new options market for {}", ticker);
+
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Options Admin Badge")
+        .metadata("symbol", "OAB")
+        .metadata("description", "Admin Badge with the authority to update the parameters of an options market")
+        .initial_supply(1);
+
+      // Create badge that gives the controller authority over option and writer tokens
+      let nft_minter_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Option Minter Badge")
+        .metadata("symbol", "OMB")
+        .metadata("description", "Badge with the authority to mint and burn option tokens")
+        .initial_supply(1);
+
+      let option_nft_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", format!("{} Option", ticker))
+        .metadata("symbol", "EOPT")
+        .metadata("description", "Token giving the holder the right to exercise an option written on Elision.")
+        .mintable(rule!(require(nft_minter_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(nft_minter_badge.resource_address())), LOCKED)
+        .updateable_non_fungible_data(rule!(require(nft_minter_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let writer_nft_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+        .metadata("name", format!("{} Option Writer", ticker))
+        .metadata("symbol", "EOPTW")
+        .metadata("description", "Token giving the holder the claim on the collateral and premium of a written option.")
+        .mintable(rule!(require(nft_minter_badge.resource_address())), LOCKED)
+        .burnable(rule!(require(nft_minter_badge.resource_address())), LOCKED)
+        .no_initial_supply();
+
+      let access_rules: AccessRules = AccessRules::new()
+        .method("set_pricing_parameters", rule!(require(admin_badge.resource_address())))
+        .method("set_minimum_collateralization_ratio", rule!(require(admin_badge.resource_address())))
+        .method("set_settlement_fee", rule!(require(admin_badge.resource_address())))
+        .method("withdraw_fees", rule!(require(admin_badge.resource_address())))
+        .default(rule!(allow_all));
+
+      let mut fee_vaults: HashMap<ResourceAddress, Vault> = HashMap::new();
+      fee_vaults.insert(underlying_address, Vault::new(underlying_address));
+      fee_vaults.insert(quote_address, Vault::new(quote_address));
+
+      let mut options_controller = Self {
+        option_nft_address: option_nft_address,
+        writer_nft_address: writer_nft_address,
+        nft_minter_badge: Vault::with_bucket(nft_minter_badge),
+        underlying_address: underlying_address,
+        quote_address: quote_address,
+        ticker: ticker,
+        price_oracle: price_oracle.into(),
+        volatility: volatility,
+        risk_free_rate: risk_free_rate,
+        pricing_steps: DEFAULT_PRICING_STEPS,
+        minimum_collateralization_ratio: Decimal::one(),
+        settlement_fee: Decimal::zero(),
+        fee_vaults: fee_vaults,
+        options: HashMap::new(),
+        vaults: HashMap::new(),
+        option_counter: 0
+      }
+      .instantiate();
+      options_controller.add_access_check(access_rules);
+
+      return (options_controller.globalize(), admin_badge);
+    }
+
+    /// Updates the parameters used to quote option premiums
+    ///
+    /// # Arguments:
+    /// * `volatility`: Decimal - Volatility of the underlying per epoch, `0.01` being 1%
+    /// * `risk_free_rate`: Decimal - Continuously compounded risk-free rate per epoch
+    /// * `pricing_steps`: u64 - Number of steps of the binomial tree
+    pub fn set_pricing_parameters(
+      &mut self,
+      volatility: Decimal,
+      risk_free_rate: Decimal,
+      pricing_steps: u64
+    ) {
+      assert_pricing_parameters(volatility, risk_free_rate, pricing_steps);
+
+      self.volatility = volatility;
+      self.risk_free_rate = risk_free_rate;
+      self.pricing_steps = pricing_steps;
+      info!(
+        "[Pricing Parameters]: Volatility: {}, Risk-free rate: {}, Steps: {}",
+        volatility, risk_free_rate, pricing_steps
+      );
+    }
+
+    /// Updates the Minimum Collateralization Ratio that written options have to satisfy
+    ///
+    /// # Arguments:
+    /// * `mcr`: Decimal - Minimum ratio of locked collateral to maximum payoff, `1` being fully covered
+    pub fn set_minimum_collateralization_ratio(
+      &mut self,
+      mcr: Decimal
+    ) {
+      assert_valid_mcr(mcr, String::from("Set MCR"));
+      self.minimum_collateralization_ratio = mcr;
+      info!("[Set MCR]: Minimum collateralization ratio set to {}", mcr);
+    }
+
+    /// Updates the percentage of the payoff of exercised options collected by the protocol
+    ///
+    /// # Arguments:
+    /// * `settlement_fee`: Decimal - Fee taken from the payoff on exercise (0-100)
+    pub fn set_settlement_fee(
+      &mut self,
+      settlement_fee: Decimal
+    ) {
+      assert!(
+        (settlement_fee >= Decimal::zero()) & (settlement_fee <= dec!("100")),
+        "[Settlement Fee]: Fee must be between 0 and 100."
+      );
+      self.settlement_fee = settlement_fee;
+      info!("[Settlement Fee]: Settlement fee set to {}", settlement_fee);
+    }
+
+    /// Withdraws the settlement fees collected in a token
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to withdraw the fees of, the underlying or quote token
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the collected settlement fees
+    pub fn withdraw_fees(
+      &mut self,
+      resource_address: ResourceAddress
+    ) -> Bucket {
+      assert!(
+        self.fee_vaults.contains_key(&resource_address),
+        "[Withdraw Fees]: No fees are collected in the token."
+      );
+      return self.fee_vaults.get_mut(&resource_address).unwrap().take_all();
+    }
+
+    /// Obtain the percentage of the payoff of exercised options collected by the protocol
+    ///
+    /// # Returns:
+    /// * `Decimal` - Settlement fee (0-100)
+    pub fn settlement_fee(&self) -> Decimal {
+      return self.settlement_fee;
+    }
+
+    /// Obtain the address of the tokens issued to option writers
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of the `OptionWriter` tokens
+    pub fn writer_nft_address(&self) -> ResourceAddress {
+      return self.writer_nft_address;
+    }
+
+    /// Obtain the Minimum Collateralization Ratio that written options have to satisfy
+    ///
+    /// # Returns:
+    /// * `Decimal` - Minimum ratio of locked collateral to maximum payoff
+    pub fn minimum_collateralization_ratio(&self) -> Decimal {
+      return self.minimum_collateralization_ratio;
+    }
+
+    /// Obtain the state of a written option
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option
+    ///
+    /// # Returns:
+    /// * `OptionState` - Current state of the option
+    pub fn option_state(
+      &self,
+      option_id: NonFungibleId
+    ) -> OptionState {
+      self.assert_option_exists(&option_id, String::from("Option State"));
+      return self.options[&option_id].state;
+    }
+
+    /// Calculates the collateralization ratio of a written option
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option
+    ///
+    /// # Returns:
+    /// * `Decimal` - Ratio of locked collateral to the maximum payoff of the option
+    pub fn collateralization_ratio(
+      &self,
+      option_id: NonFungibleId
+    ) -> Decimal {
+      self.assert_option_exists(&option_id, String::from("Collateralization Ratio"));
+      return option_position(&self.options[&option_id]).collateralization_ratio(&self.price_oracle);
+    }
+
+    /// Obtain the token that has to be locked as collateral for the given option type
+    ///
+    /// # Arguments:
+    /// * `option_type`: OptionType - Whether the option is a call or a put
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Underlying token for calls, quote token for puts
+    pub fn collateral_address(
+      &self,
+      option_type: OptionType
+    ) -> ResourceAddress {
+      return match option_type {
+        OptionType::Call => self.underlying_address,
+        OptionType::Put => self.quote_address
+      }
+    }
+
+    /// Obtain the current price of the underlying token from the oracle
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of the underlying token denominated in the quote token
+    pub fn spot_price(&self) -> Decimal {
+      return self.price_oracle.get_price(self.underlying_address) / self.price_oracle.get_price(self.quote_address);
+    }
+
+    /// Quotes the premium of an option using the Binomial Option Pricing Model, net of the settlement fee
+    ///
+    /// # Arguments:
+    /// * `option_type`: OptionType - Whether the option is a call or a put
+    /// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+    /// * `amount`: Decimal - Units of the underlying token covered by the option
+    /// * `expiry_epoch`: u64 - Epoch from which the option can no longer be exercised
+    ///
+    /// # Returns:
+    /// * `Decimal` - Premium in the collateral token, underlying tokens for a call or quote tokens for a put
+    ///
+    /// # Note:
+    /// * The settlement fee is taken from the payoff on exercise, so the model price of the option is reduced
+    ///   by the same percentage to reflect the payoff the buyer actually receives
+    pub fn quote_premium(
+      &self,
       option_type: OptionType,
-      stike: Decimal,
-      premium: Decimal,
-      expiry_epoch: u64,
-      amount: Decimal
-    ) -> ComponentAddress {
-      let option_id =
+      strike: Decimal,
+      amount: Decimal,
+      expiry_epoch: u64
+    ) -> Decimal {
+      let current_epoch: u64 = Runtime::current_epoch();
+      assert!(expiry_epoch > current_epoch, "[Quote Premium]: Expiry epoch must be in the future.");
+
+      let spot: Decimal = self.spot_price();
+      let unit_premium: Decimal = price_american_option(
+        option_type,
+        spot,
+        strike,
+        self.volatility,
+        self.risk_free_rate,
+        self.pricing_steps,
+        expiry_epoch - current_epoch
+      );
+
+      let net_unit_premium: Decimal = unit_premium * (dec!("100") - self.settlement_fee) / dec!("100");
+
+      // The model prices in the quote token, calls are paid for in the underlying token
+      return match option_type {
+        OptionType::Call => net_unit_premium * amount / spot,
+        OptionType::Put => net_unit_premium * amount
+      }
+    }
+
+    /// Asserts that an option with the given id has been written on this market
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option
+    /// * `label`: String - Label that called the assertion method
+    pub fn assert_option_exists(
+      &self,
+      option_id: &NonFungibleId,
+      label: String
+    ) {
+      assert!(
+        self.options.contains_key(option_id),
+        "[{}]: Option does not exist on this market.",
+        label
+      );
+    }
+
+    /// Locks collateral and lists a new option for sale at the quoted premium
+    ///
+    /// # Arguments:
+    /// * `collateral`: Bucket - Contains the collateral to lock, underlying tokens for a call or quote tokens for a put
+    /// * `option_type`: OptionType - Whether the option is a call or a put
+    /// * `strike`: Decimal - Exercise price of the option denominated in the quote token
+    /// * `amount`: Decimal - Units of the underlying token covered by the option
+    /// * `expiry_epoch`: u64 - Epoch from which the option can no longer be exercised
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the `OptionWriter` token of the new option
+    ///
+    /// # Note:
+    /// * Written options are not liquidated, the MCR is at least 1 and the payoff of an option never exceeds the
+    ///   collateral required to cover it, so the collateral locked here always covers what the buyer can be owed
+    pub fn write(
+      &mut self,
+      collateral: Bucket,
+      option_type: OptionType,
+      strike: Decimal,
+      amount: Decimal,
+      expiry_epoch: u64
+    ) -> Bucket {
+      assert!(strike > Decimal::zero(), "[Write Option]: Strike must be greater than zero.");
+      assert!(amount > Decimal::zero(), "[Write Option]: Amount must be greater than zero.");
+      assert!(
+        expiry_epoch > Runtime::current_epoch(),
+        "[Write Option]: Expiry epoch must be in the future."
+      );
+      assert_pricing_horizon(
+        self.volatility,
+        self.pricing_steps,
+        expiry_epoch - Runtime::current_epoch(),
+        String::from("Write Option")
+      );
+
+      // Verify the collateral matches the option type
+      let collateral_address: ResourceAddress = self.collateral_address(option_type);
+      assert_eq!(
+        collateral.resource_address(), collateral_address,
+        "[Write Option]: Incorrect collateral token for the option type."
+      );
+
+      let locked_amount: Decimal = collateral.amount();
+      let option: Option = Option {
+        state: OptionState::Written,
+        option_type: option_type,
+        locked_amount: locked_amount,
+        premium: Decimal::zero(),
+        hedge_premium: Decimal::zero(),
+        unhedge_premium: Decimal::zero(),
+        amount: amount,
+        created_epoch: Runtime::current_epoch(),
+        expiry_epoch: expiry_epoch,
+        settlement_fee_address: collateral_address,
+        strike: strike
+      };
+
+      // Verify the collateral covers the payoff of the option as required by the MCR
+      option_position(&option).assert_collateralized(
+        &self.price_oracle,
+        self.minimum_collateralization_ratio,
+        String::from("Write Option")
+      );
+
+      let option_id: NonFungibleId = NonFungibleId::from_u64(self.option_counter);
+      self.option_counter += 1;
+
+      // Lock the collateral and record the option
+      self.vaults.insert(option_id.clone(), Vault::with_bucket(collateral));
+      self.options.insert(option_id.clone(), option);
+
+      info!(
+        "[Write Option]: Written {} {:?} option {} with strike {} expiring at epoch {}",
+        self.ticker, option_type, option_id, strike, expiry_epoch
+      );
+
+      // Mint the writer token that gives claim over the collateral and premium
+      let writer_manager: &ResourceManager = borrow_resource_manager!(self.writer_nft_address);
+      let writer_nft: Bucket = self.nft_minter_badge.authorize(|| {
+        writer_manager.mint_non_fungible(&option_id, OptionWriter {
+          collateral_address: collateral_address,
+          locked_amount: locked_amount
+        })
+      });
+
+      return writer_nft;
+    }
+
+    /// Pays the quoted premium of a listed option and issues the option to the buyer
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option to buy
+    /// * `payment`: Bucket - Contains the premium, paid in the collateral token of the option
+    /// * `max_premium`: Decimal - Maximum premium the caller is willing to pay
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the `OptionTrade` token of the bought option
+    /// * `Bucket` - Contains the remaining payment tokens
+    pub fn buy(
+      &mut self,
+      option_id: NonFungibleId,
+      mut payment: Bucket,
+      max_premium: Decimal
+    ) -> (Bucket, Bucket) {
+      self.assert_option_exists(&option_id, String::from("Buy Option"));
+
+      // Quote the premium against the current oracle price
+      let premium: Decimal = {
+        let option: &Option = &self.options[&option_id];
+        assert!(
+          option.state == OptionState::Written,
+          "[Buy Option]: Option is not available for purchase."
+        );
+        self.quote_premium(option.option_type, option.strike, option.amount, option.expiry_epoch)
+      };
+      assert!(
+        premium <= max_premium,
+        "[Buy Option]: Premium of {} is above max_premium.",
+        premium
+      );
+
+      let option: &mut Option = self.options.get_mut(&option_id).unwrap();
+      assert!(
+        option.state == OptionState::Written,
+        "[Buy Option]: Option is not available for purchase."
+      );
+      assert!(
+        Runtime::current_epoch() < option.expiry_epoch,
+        "[Buy Option]: Option has already expired."
+      );
+
+      // Verify the premium and deposit it next to the collateral of the option
+      let vault: &mut Vault = self.vaults.get_mut(&option_id).unwrap();
+      assert_eq!(
+        payment.resource_address(), vault.resource_address(),
+        "[Buy Option]: Premium must be paid in the collateral token of the option."
+      );
+      assert!(
+        payment.amount() >= premium,
+        "[Buy Option]: Not enough tokens to pay the premium."
+      );
+      vault.put(payment.take(premium));
+
+      option.premium = premium;
+      option.state = OptionState::Active;
+      option.created_epoch = Runtime::current_epoch();
+      info!("[Buy Option]: Bought option {} for a premium of {}", option_id, option.premium);
+
+      // Mint the option token for the buyer
+      let option_manager: &ResourceManager = borrow_resource_manager!(self.option_nft_address);
+      let option_data: OptionTrade = OptionTrade {
+        option_type: option.option_type,
+        strike: option.strike,
+        amount: option.amount,
+        expiry_epoch: option.expiry_epoch,
+        state: OptionState::Active
+      };
+      let option_nft: Bucket = self.nft_minter_badge.authorize(|| {
+        option_manager.mint_non_fungible(&option_id, option_data)
+      });
+
+      return (option_nft, payment);
+    }
+
+    /// Cancels an option that has not been bought yet and returns its collateral
+    ///
+    /// # Arguments:
+    /// * `writer_nft`: Bucket - Contains the `OptionWriter` token of the option
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the collateral that was locked for the option
+    pub fn cancel(
+      &mut self,
+      writer_nft: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        writer_nft.resource_address(), self.writer_nft_address,
+        "[Cancel Option]: Writer token does not belong to this market."
+      );
+      assert_eq!(writer_nft.amount(), Decimal::one(), "[Cancel Option]: Only one option can be canceled at a time.");
+
+      let option_id: NonFungibleId = writer_nft.non_fungible::<OptionWriter>().id();
+      let option: &mut Option = self.options.get_mut(&option_id).unwrap();
+      assert!(
+        option.state == OptionState::Written,
+        "[Cancel Option]: Only options that have not been bought can be canceled."
+      );
+      option.state = OptionState::Canceled;
+      info!("[Cancel Option]: Canceled option {}", option_id);
+
+      self.nft_minter_badge.authorize(|| {
+        writer_nft.burn();
+      });
+
+      return self.vaults.get_mut(&option_id).unwrap().take_all();
+    }
+
+    /// Exercises an option and pays out its intrinsic value from the locked collateral, less the settlement fee
+    ///
+    /// # Arguments:
+    /// * `option_nft`: Bucket - Contains the `OptionTrade` token of the option, burned on exercise
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the net payoff, underlying tokens for a call or quote tokens for a put
+    pub fn exercise(
+      &mut self,
+      option_nft: Bucket
+    ) -> Bucket {
+      assert_eq!(
+        option_nft.resource_address(), self.option_nft_address,
+        "[Exercise Option]: Option token does not belong to this market."
+      );
+      assert_eq!(option_nft.amount(), Decimal::one(), "[Exercise Option]: Only one option can be exercised at a time.");
+
+      let price: Decimal = self.spot_price();
+      let option_id: NonFungibleId = option_nft.non_fungible::<OptionTrade>().id();
+      let option: &mut Option = self.options.get_mut(&option_id).unwrap();
+      assert!(
+        option.state == OptionState::Active,
+        "[Exercise Option]: Option is not active."
+      );
+      assert!(
+        Runtime::current_epoch() < option.expiry_epoch,
+        "[Exercise Option]: Option has already expired."
+      );
+
+      // The payoff can never exceed the collateral locked by the writer
+      let intrinsic: Decimal = option_payoff(option.option_type, option.strike, option.amount, price);
+      let payoff: Decimal = if intrinsic < option.locked_amount { intrinsic } else { option.locked_amount };
+      assert!(payoff > Decimal::zero(), "[Exercise Option]: Option is out of the money.");
+
+      option.state = OptionState::Exercised;
+      let fee: Decimal = payoff * self.settlement_fee / dec!("100");
+      info!(
+        "[Exercise Option]: Exercised option {} at price {} for a payoff of {} with a settlement fee of {}",
+        option_id, price, payoff, fee
+      );
+
+      self.nft_minter_badge.authorize(|| {
+        option_nft.burn();
+      });
+
+      // Collect the settlement fee in the token the option settles in
+      let mut payout: Bucket = self.vaults.get_mut(&option_id).unwrap().take(payoff);
+      self.fee_vaults.get_mut(&option.settlement_fee_address).unwrap().put(payout.take(fee));
+      return payout;
+    }
+
+    /// Settles an option that has reached its expiry without being exercised
+    ///
+    /// Can be called by anyone. The locked collateral becomes claimable through `claim` by the holder of the
+    /// `OptionWriter` token and the `OptionTrade` token is marked as expired.
+    ///
+    /// # Arguments:
+    /// * `option_id`: NonFungibleId - Id of the option to expire
+    pub fn expire(
+      &mut self,
+      option_id: NonFungibleId
+    ) {
+      self.assert_option_exists(&option_id, String::from("Expire Option"));
+
+      let option: &mut Option = self.options.get_mut(&option_id).unwrap();
+      assert!(
+        Runtime::current_epoch() >= option.expiry_epoch,
+        "[Expire Option]: Option has not reached its expiry epoch."
+      );
+
+      match option.state {
+        OptionState::Written => {},
+        OptionState::Active => {
+          // Mark the token of the buyer so that it can no longer be used
+          let option_manager: &ResourceManager = borrow_resource_manager!(self.option_nft_address);
+          let mut option_data: OptionTrade = option_manager.get_non_fungible_data(&option_id);
+          option_data.state = OptionState::Expired;
+          self.nft_minter_badge.authorize(|| {
+            option_manager.update_non_fungible_data(&option_id, option_data);
+          });
+        },
+        _ => panic!("[Expire Option]: Option has already been settled.")
+      }
+
+      option.state = OptionState::Expired;
+      info!("[Expire Option]: Expired option {}", option_id);
+    }
+
+    /// Withdraws the premium earned by the writer of an option, and the unused collateral once settled
+    ///
+    /// # Arguments:
+    /// * `writer_proof`: Proof - Proof of the `OptionWriter` token of the option
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the tokens that the writer can claim
+    pub fn claim(
+      &mut self,
+      writer_proof: Proof
+    ) -> Bucket {
+      assert_eq!(
+        writer_proof.resource_address(), self.writer_nft_address,
+        "[Claim]: Writer token does not belong to this market."
+      );
+      assert_eq!(writer_proof.amount(), Decimal::one(), "[Claim]: Only one option can be claimed at a time.");
+
+      let option_id: NonFungibleId = writer_proof.non_fungible::<OptionWriter>().id();
+      let option: &Option = &self.options[&option_id];
+      let vault: &mut Vault = self.vaults.get_mut(&option_id).unwrap();
+
+      // Only the premium is claimable while the collateral is locked
+      let claimable: Decimal = match option.state {
+        OptionState::Active => vault.amount() - option.locked_amount,
+        OptionState::Exercised | OptionState::Expired => vault.amount(),
+        _ => Decimal::zero()
+      };
+      info!("[Claim]: Claiming {} from option {}", claimable, option_id);
+
+      return vault.take(claimable);
     }
   }
-}
\ No newline at end of file
+}
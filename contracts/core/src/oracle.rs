@@ -0,0 +1,281 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::liquidity_pool::*;
+
+/// Price pushed to the oracle by an authorized feeder
+///
+/// # Contains:
+/// * `price`: Decimal - Price of a single unit of the token in USD
+/// * `epoch`: u64 - Epoch at which the price was observed
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, Debug)]
+pub struct PriceFeed {
+  pub price: Decimal,
+  pub epoch: u64
+}
+
+/// Liquidity pool used to derive a time-weighted price when the fed price is stale
+///
+/// # Contains:
+/// * `liquidity_pool`: LiquidityPool - Elision pool containing the token and the quote token
+/// * `quote_address`: ResourceAddress - Other token of the pool, must have a fed price itself
+/// * `window_epochs`: u64 - Number of epochs the time-weighted price is averaged over
+#[derive(TypeId, Encode, Decode, Describe)]
+pub struct TwapSource {
+  pub liquidity_pool: LiquidityPool,
+  pub quote_address: ResourceAddress,
+  pub window_epochs: u64
+}
+
+blueprint! {
+  /// Structure representing the price oracle used to value tokens on Elision
+  ///
+  /// # Contains:
+  /// * `admin_badge_address`: ResourceAddress - Badge required to manage feeders, limits and sources
+  /// * `feeder_badges`: HashSet<ResourceAddress> - Badges authorized to push prices
+  /// * `prices`: HashMap<ResourceAddress, PriceFeed> - Latest fed price of each token in USD
+  /// * `staleness_limits`: HashMap<ResourceAddress, u64> - Epochs after which the fed price of a token is stale
+  /// * `default_staleness_limit`: u64 - Staleness limit of tokens without their own limit
+  /// * `twap_sources`: HashMap<ResourceAddress, TwapSource> - Fallback pools of tokens
+  struct PriceOracle {
+    admin_badge_address: ResourceAddress,
+    feeder_badges: HashSet<ResourceAddress>,
+    prices: HashMap<ResourceAddress, PriceFeed>,
+    staleness_limits: HashMap<ResourceAddress, u64>,
+    default_staleness_limit: u64,
+    twap_sources: HashMap<ResourceAddress, TwapSource>
+  }
+
+  impl PriceOracle {
+    /// Create new price oracle
+    ///
+    /// # Arguments:
+    /// * `default_staleness_limit`: u64 - Epochs after which a fed price is stale unless set per token
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - PriceOracle component address
+    /// * `Bucket` - Contains the admin badge that has the authority to manage the oracle
+    pub fn new(default_staleness_limit: u64) -> (ComponentAddress, Bucket) {
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Price Oracle Admin Badge")
+        .metadata("symbol", "POAB")
+        .metadata("description", "Admin Badge with the authority to manage oracle feeders and sources")
+        .initial_supply(1);
+
+      let admin_rule: AccessRule = rule!(require(admin_badge.resource_address()));
+      let access_rules: AccessRules = AccessRules::new()
+        .method("add_feeder", admin_rule.clone())
+        .method("remove_feeder", admin_rule.clone())
+        .method("set_staleness_limit", admin_rule.clone())
+        .method("set_twap_source", admin_rule.clone())
+        .method("remove_twap_source", admin_rule)
+        .default(rule!(allow_all));
+
+      let mut price_oracle = Self {
+        admin_badge_address: admin_badge.resource_address(),
+        feeder_badges: HashSet::new(),
+        prices: HashMap::new(),
+        staleness_limits: HashMap::new(),
+        default_staleness_limit: default_staleness_limit,
+        twap_sources: HashMap::new()
+      }
+      .instantiate();
+      price_oracle.add_access_check(access_rules);
+
+      return (price_oracle.globalize(), admin_badge);
+    }
+
+    /// Authorizes a badge to push prices
+    ///
+    /// # Arguments:
+    /// * `badge_address`: ResourceAddress - Badge of the feeder
+    pub fn add_feeder(
+      &mut self,
+      badge_address: ResourceAddress
+    ) {
+      info!("[Add Feeder]: Authorized feeder {}", badge_address);
+      self.feeder_badges.insert(badge_address);
+    }
+
+    /// Revokes the authorization of a badge to push prices
+    ///
+    /// # Arguments:
+    /// * `badge_address`: ResourceAddress - Badge of the feeder
+    pub fn remove_feeder(
+      &mut self,
+      badge_address: ResourceAddress
+    ) {
+      assert!(
+        self.feeder_badges.remove(&badge_address),
+        "[Remove Feeder]: Badge is not an authorized feeder."
+      );
+      info!("[Remove Feeder]: Revoked feeder {}", badge_address);
+    }
+
+    /// Sets the number of epochs after which the fed price of a token is stale
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to set the limit of
+    /// * `staleness_limit`: u64 - Number of epochs a fed price remains valid
+    pub fn set_staleness_limit(
+      &mut self,
+      resource_address: ResourceAddress,
+      staleness_limit: u64
+    ) {
+      info!("[Staleness Limit]: Staleness limit of {} set to {} epochs", resource_address, staleness_limit);
+      self.staleness_limits.insert(resource_address, staleness_limit);
+    }
+
+    /// Sets the liquidity pool used to derive a time-weighted price of a token
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to derive the price of
+    /// * `liquidity_pool`: ComponentAddress - LiquidityPool containing the token and the quote token
+    /// * `quote_address`: ResourceAddress - Other token of the pool, must have a fed price itself
+    /// * `window_epochs`: u64 - Number of epochs the time-weighted price is averaged over
+    pub fn set_twap_source(
+      &mut self,
+      resource_address: ResourceAddress,
+      liquidity_pool: ComponentAddress,
+      quote_address: ResourceAddress,
+      window_epochs: u64
+    ) {
+      assert_ne!(resource_address, quote_address, "[TWAP Source]: Token and quote token must be different.");
+      assert!(
+        (window_epochs > 0) & (window_epochs <= MAX_TWAP_WINDOW),
+        "[TWAP Source]: Window must be between 1 and {} epochs.",
+        MAX_TWAP_WINDOW
+      );
+
+      let liquidity_pool: LiquidityPool = liquidity_pool.into();
+      liquidity_pool.assert_belongs_to_pool(resource_address, String::from("TWAP Source"));
+      liquidity_pool.assert_belongs_to_pool(quote_address, String::from("TWAP Source"));
+
+      info!("[TWAP Source]: Time-weighted price of {} derived over {} epochs", resource_address, window_epochs);
+      self.twap_sources.insert(resource_address, TwapSource {
+        liquidity_pool: liquidity_pool,
+        quote_address: quote_address,
+        window_epochs: window_epochs
+      });
+    }
+
+    /// Removes the liquidity pool used to derive a time-weighted price of a token
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to remove the source of
+    pub fn remove_twap_source(
+      &mut self,
+      resource_address: ResourceAddress
+    ) {
+      assert!(
+        self.twap_sources.remove(&resource_address).is_some(),
+        "[TWAP Source]: No source exists for the token."
+      );
+    }
+
+    /// Pushes the price of a token observed by a feeder
+    ///
+    /// # Arguments:
+    /// * `feeder`: Proof - Proof of an authorized feeder badge
+    /// * `resource_address`: ResourceAddress - Token to set the price of
+    /// * `price`: Decimal - Price of a single unit of the token in USD
+    /// * `epoch`: u64 - Epoch at which the price was observed
+    pub fn update_price(
+      &mut self,
+      feeder: Proof,
+      resource_address: ResourceAddress,
+      price: Decimal,
+      epoch: u64
+    ) {
+      assert!(
+        self.feeder_badges.contains(&feeder.resource_address()),
+        "[Update Price]: Badge is not an authorized feeder."
+      );
+      assert!(price > Decimal::zero(), "[Update Price]: Price must be greater than zero.");
+      assert!(epoch <= Runtime::current_epoch(), "[Update Price]: Price cannot be from a future epoch.");
+
+      // Never replace a price with an older observation
+      if let Some(feed) = self.prices.get(&resource_address) {
+        assert!(epoch >= feed.epoch, "[Update Price]: A more recent price has already been pushed.");
+      }
+
+      info!("[Update Price]: Price of {} set to {} at epoch {}", resource_address, price, epoch);
+      self.prices.insert(resource_address, PriceFeed {
+        price: price,
+        epoch: epoch
+      });
+    }
+
+    /// Checks whether a fed price of a token is available and has not gone stale
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to check
+    ///
+    /// # Returns:
+    /// * `bool` - True if a fresh price has been fed, false otherwise
+    pub fn has_fresh_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> bool {
+      let staleness_limit: u64 = *self.staleness_limits
+        .get(&resource_address)
+        .unwrap_or(&self.default_staleness_limit);
+
+      return match self.prices.get(&resource_address) {
+        Some(feed) => Runtime::current_epoch() - feed.epoch <= staleness_limit,
+        None => false
+      }
+    }
+
+    /// Obtain the time-weighted price of a token from its liquidity pool
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to get the price of
+    ///
+    /// # Returns:
+    /// * `Decimal` - Time-weighted price of the token in USD
+    ///
+    /// # Note:
+    /// * The pool averages the price over the window from its price accumulators, leaving out the current epoch
+    pub fn get_twap_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> Decimal {
+      assert!(
+        self.twap_sources.contains_key(&resource_address),
+        "[TWAP Price]: No time-weighted price source exists for the token."
+      );
+
+      let source: &TwapSource = &self.twap_sources[&resource_address];
+      assert!(
+        self.has_fresh_price(source.quote_address),
+        "[TWAP Price]: No fresh price available for the quote token."
+      );
+
+      let twap: Decimal = source.liquidity_pool.twap(resource_address, source.window_epochs);
+      return twap * self.prices[&source.quote_address].price;
+    }
+
+    /// Obtain the price of a token, falling back to its time-weighted price when the fed price is stale
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to get the price of
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of a single unit of the token in USD
+    pub fn get_price(
+      &self,
+      resource_address: ResourceAddress
+    ) -> Decimal {
+      if self.has_fresh_price(resource_address) {
+        return self.prices[&resource_address].price;
+      }
+
+      assert!(
+        self.twap_sources.contains_key(&resource_address),
+        "[Get Price]: No fresh price available for the token."
+      );
+      return self.get_twap_price(resource_address);
+    }
+  }
+}
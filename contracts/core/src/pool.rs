@@ -0,0 +1,410 @@
+use scrypto::prelude::*;
+use sbor::*;
+use crate::liquidity_pool::*;
+use crate::stable_swap_pool::*;
+use crate::weighted_pool::*;
+use crate::concentrated_pool::*;
+
+/// Type of a pool, several pools of different types can exist for the same tokens
+///
+/// # Variants:
+/// * `ConstantProduct` - Pool following the `x * y = k` invariant
+/// * `StableSwap` - Pool following the stable swap invariant, meant for pegged assets
+/// * `Weighted` - Pool of 2 to 8 tokens with custom weights
+/// * `Concentrated` - Pool whose providers choose the price range of their liquidity
+#[derive(TypeId, Encode, Decode, Describe, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PoolKind {
+  ConstantProduct,
+  StableSwap,
+  Weighted,
+  Concentrated
+}
+
+/// Key of a pool in the ElisionSwap registry, the sorted addresses of its tokens and the type of the pool
+pub type PoolKey = (Vec<ResourceAddress>, PoolKind);
+
+/// Any type of pool that can be held in the ElisionSwap registry
+///
+/// # Variants:
+/// * `ConstantProduct`: LiquidityPool - Pool following the `x * y = k` invariant
+/// * `StableSwap`: StableSwapPool - Pool following the stable swap invariant, meant for pegged assets
+/// * `Weighted`: WeightedPool - Pool of 2 to 8 tokens with custom weights
+/// * `Concentrated`: ConcentratedLiquidityPool - Pool whose providers choose the price range of their liquidity
+#[derive(TypeId, Encode, Decode, Describe)]
+pub enum Pool {
+  ConstantProduct(LiquidityPool),
+  StableSwap(StableSwapPool),
+  Weighted(WeightedPool),
+  Concentrated(ConcentratedLiquidityPool)
+}
+
+impl Pool {
+  /// Obtain the type of the pool
+  ///
+  /// # Returns:
+  /// * `PoolKind` - Type of the pool
+  pub fn kind(&self) -> PoolKind {
+    return match self {
+      Pool::ConstantProduct(_) => PoolKind::ConstantProduct,
+      Pool::StableSwap(_) => PoolKind::StableSwap,
+      Pool::Weighted(_) => PoolKind::Weighted,
+      Pool::Concentrated(_) => PoolKind::Concentrated
+    };
+  }
+
+  /// Obtain the fee imposed on swaps from the pool
+  ///
+  /// # Returns:
+  /// * `Decimal` - Fee of the pool (0-100)
+  pub fn pool_fee(&self) -> Decimal {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.pool_fee(),
+      Pool::StableSwap(pool) => pool.pool_fee(),
+      Pool::Weighted(pool) => pool.pool_fee(),
+      Pool::Concentrated(pool) => pool.pool_fee()
+    };
+  }
+
+  /// Changes the fee imposed on swaps from the pool, requires the fee admin badge
+  ///
+  /// # Arguments:
+  /// * `pool_fee`: Decimal - New fee for the pool (0-100)
+  pub fn set_pool_fee(
+    &self,
+    pool_fee: Decimal
+  ) {
+    match self {
+      Pool::ConstantProduct(pool) => pool.set_pool_fee(pool_fee),
+      Pool::StableSwap(pool) => pool.set_pool_fee(pool_fee),
+      Pool::Weighted(pool) => pool.set_pool_fee(pool_fee),
+      Pool::Concentrated(pool) => pool.set_pool_fee(pool_fee)
+    }
+  }
+
+  /// Changes the percentage of the pool fee diverted to the protocol, requires the fee admin badge
+  ///
+  /// # Arguments:
+  /// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+  pub fn set_protocol_fee(
+    &self,
+    protocol_fee: Decimal
+  ) {
+    match self {
+      Pool::ConstantProduct(pool) => pool.set_protocol_fee(protocol_fee),
+      Pool::StableSwap(pool) => pool.set_protocol_fee(protocol_fee),
+      Pool::Weighted(pool) => pool.set_protocol_fee(protocol_fee),
+      Pool::Concentrated(pool) => pool.set_protocol_fee(protocol_fee)
+    }
+  }
+
+  /// Withdraws the protocol fees collected by the pool, requires the fee admin badge
+  ///
+  /// # Returns:
+  /// * `Vec<Bucket>` - Contains the protocol fees collected in every token of the pool
+  pub fn withdraw_protocol_fees(&self) -> Vec<Bucket> {
+    return match self {
+      Pool::ConstantProduct(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.withdraw_protocol_fees();
+        vec![bucket0, bucket1]
+      }
+      Pool::StableSwap(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.withdraw_protocol_fees();
+        vec![bucket0, bucket1]
+      }
+      Pool::Weighted(pool) => pool.withdraw_protocol_fees(),
+      Pool::Concentrated(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.withdraw_protocol_fees();
+        vec![bucket0, bucket1]
+      }
+    };
+  }
+
+  /// Calculates amount of output that can be given based on the amount of input
+  ///
+  /// # Arguments:
+  /// * `input_resource_address`: ResourceAddress - Input token address
+  /// * `input_amount`: Decimal - Input amount to calculate output with
+  /// * `output_resource_address`: ResourceAddress - Output token address
+  ///
+  /// # Returns:
+  /// * `Decimal` - Calculated output amount
+  pub fn calculate_output_amount(
+    &self,
+    input_resource_address: ResourceAddress,
+    input_amount: Decimal,
+    output_resource_address: ResourceAddress
+  ) -> Decimal {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.calculate_output_amount(input_resource_address, input_amount),
+      Pool::StableSwap(pool) => pool.calculate_output_amount(input_resource_address, input_amount),
+      Pool::Weighted(pool) => pool.calculate_output_amount(input_resource_address, input_amount, output_resource_address),
+      Pool::Concentrated(pool) => pool.calculate_output_amount(input_resource_address, input_amount)
+    };
+  }
+
+  /// Quotes the amount of input required for an amount of output, without failing when the pool cannot give it
+  ///
+  /// # Arguments:
+  /// * `input_resource_address`: ResourceAddress - Input token address
+  /// * `output_resource_address`: ResourceAddress - Output token address
+  /// * `output_amount`: Decimal - Output amount to calculate input with
+  ///
+  /// # Returns:
+  /// * `Option<Decimal>` - Calculated input amount, None if the pool cannot give the output
+  pub fn quote_input_amount(
+    &self,
+    input_resource_address: ResourceAddress,
+    output_resource_address: ResourceAddress,
+    output_amount: Decimal
+  ) -> Option<Decimal> {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.quote_input_amount(output_resource_address, output_amount),
+      Pool::StableSwap(pool) => pool.quote_input_amount(output_resource_address, output_amount),
+      Pool::Weighted(pool) => pool.quote_input_amount(input_resource_address, output_resource_address, output_amount),
+      Pool::Concentrated(pool) => pool.quote_input_amount(output_resource_address, output_amount)
+    };
+  }
+
+  /// Adds liquidity in every token of the pool in exchange for liquidity provider tokens
+  ///
+  /// # Arguments:
+  /// * `tokens`: Vec<Bucket> - Contains one bucket of every token of the pool
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+  ///
+  /// # Returns:
+  /// * `Vec<Bucket>` - Contains the remaining tokens, in the order they were passed in
+  /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+  ///
+  /// # Note:
+  /// * Concentrated pools need a price range, their liquidity is added through `add_concentrated_liquidity`
+  pub fn add_liquidity(
+    &self,
+    tokens: Vec<Bucket>,
+    deadline: u64
+  ) -> (Vec<Bucket>, Bucket) {
+    match self {
+      Pool::Weighted(pool) => return pool.add_liquidity(tokens, deadline),
+      Pool::Concentrated(_) => panic!("[Pool Add Liquidity]: Liquidity is added to concentrated pools over a price range."),
+      _ => {}
+    }
+
+    assert_eq!(tokens.len(), 2, "[Pool Add Liquidity]: A bucket of both tokens of the pool must be provided.");
+    let mut tokens: Vec<Bucket> = tokens;
+    let token1: Bucket = tokens.pop().unwrap();
+    let token0: Bucket = tokens.pop().unwrap();
+    let (remaining0, remaining1, provider_tokens): (Bucket, Bucket, Bucket) = match self {
+      Pool::ConstantProduct(pool) => pool.add_liquidity(token0, token1, deadline),
+      Pool::StableSwap(pool) => pool.add_liquidity(token0, token1, deadline),
+      Pool::Weighted(_) | Pool::Concentrated(_) => unreachable!()
+    };
+    return (vec![remaining0, remaining1], provider_tokens);
+  }
+
+  /// Adds liquidity in a single token of the pool in exchange for liquidity provider tokens
+  ///
+  /// # Arguments:
+  /// * `tokens`: Bucket - Contains the token to add to the pool
+  /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+  pub fn add_single_asset_liquidity(
+    &self,
+    tokens: Bucket,
+    min_provider_tokens: Decimal,
+    deadline: u64
+  ) -> Bucket {
+    return match self {
+      Pool::Weighted(pool) => pool.add_single_asset_liquidity(tokens, min_provider_tokens, deadline),
+      _ => panic!("[Pool Add Liquidity]: Single asset liquidity can only be added to weighted pools.")
+    };
+  }
+
+  /// Opens a position providing liquidity to a concentrated pool within a price range
+  ///
+  /// # Arguments:
+  /// * `token0`: Bucket - Contains the first token to add to the pool
+  /// * `token1`: Bucket - Contains the second token to add to the pool
+  /// * `lower_tick`: i64 - Tick at which the range starts
+  /// * `upper_tick`: i64 - Tick at which the range ends
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains remaining tokens of the first sorted token
+  /// * `Bucket` - Contains remaining tokens of the second sorted token
+  /// * `Bucket` - Contains the position NFT issued to the liquidity provider
+  pub fn add_concentrated_liquidity(
+    &self,
+    token0: Bucket,
+    token1: Bucket,
+    lower_tick: i64,
+    upper_tick: i64,
+    deadline: u64
+  ) -> (Bucket, Bucket, Bucket) {
+    return match self {
+      Pool::Concentrated(pool) => pool.add_liquidity(token0, token1, lower_tick, upper_tick, deadline),
+      _ => panic!("[Pool Add Liquidity]: Liquidity over a price range can only be added to concentrated pools.")
+    };
+  }
+
+  /// Adds liquidity from a single token, swapping the right share of it for the other token first
+  ///
+  /// # Arguments:
+  /// * `tokens`: Bucket - Contains the token to add to the pool
+  /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+  ///
+  /// # Returns:
+  /// * `Vec<Bucket>` - Contains the dust of both tokens left by rounding
+  /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+  pub fn zap_in(
+    &self,
+    tokens: Bucket,
+    min_provider_tokens: Decimal,
+    deadline: u64
+  ) -> (Vec<Bucket>, Bucket) {
+    return match self {
+      Pool::ConstantProduct(pool) => {
+        let (remaining0, remaining1, provider_tokens): (Bucket, Bucket, Bucket) = pool.zap_in(
+          tokens, min_provider_tokens, deadline
+        );
+        (vec![remaining0, remaining1], provider_tokens)
+      }
+      _ => panic!("[Pool Zap In]: Zapping is only available for constant product pools.")
+    };
+  }
+
+  /// Removes liquidity and swaps it so that it is returned entirely in one token
+  ///
+  /// # Arguments:
+  /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+  /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+  /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains the share of liquidity provider in the output token
+  pub fn zap_out(
+    &self,
+    provider_tokens: Bucket,
+    output_resource_address: ResourceAddress,
+    min_amount_out: Decimal,
+    deadline: u64
+  ) -> Bucket {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.zap_out(provider_tokens, output_resource_address, min_amount_out, deadline),
+      _ => panic!("[Pool Zap Out]: Zapping is only available for constant product pools.")
+    };
+  }
+
+  /// Removes the percentage of the liquidity owed to this liquidity provider
+  ///
+  /// # Arguments:
+  /// * `provider_tokens`: Bucket - Contains provider tokens, or position NFTs of a concentrated pool
+  /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+  ///
+  /// # Returns:
+  /// * `Vec<Bucket>` - Contains share of liquidity provider of every token of the pool
+  pub fn remove_liquidity(
+    &self,
+    provider_tokens: Bucket,
+    deadline: u64
+  ) -> Vec<Bucket> {
+    return match self {
+      Pool::ConstantProduct(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.remove_liquidity(provider_tokens, deadline);
+        vec![bucket0, bucket1]
+      }
+      Pool::StableSwap(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.remove_liquidity(provider_tokens, deadline);
+        vec![bucket0, bucket1]
+      }
+      Pool::Weighted(pool) => pool.remove_liquidity(provider_tokens, deadline),
+      Pool::Concentrated(pool) => {
+        let (bucket0, bucket1): (Bucket, Bucket) = pool.remove_liquidity(provider_tokens, deadline);
+        vec![bucket0, bucket1]
+      }
+    };
+  }
+
+  /// Execute token swap and take pool fee
+  ///
+  /// # Arguments:
+  /// * `tokens`: Bucket - Contains the input tokens that will be swapped for other tokens
+  /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+  /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+  /// * `deadline`: u64 - Last epoch in which the swap can be executed
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains the output tokens
+  pub fn swap(
+    &self,
+    tokens: Bucket,
+    output_resource_address: ResourceAddress,
+    min_amount_out: Decimal,
+    deadline: u64
+  ) -> Bucket {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.swap(tokens, min_amount_out, deadline),
+      Pool::StableSwap(pool) => pool.swap(tokens, min_amount_out, deadline),
+      Pool::Weighted(pool) => pool.swap(tokens, output_resource_address, min_amount_out, deadline),
+      Pool::Concentrated(pool) => pool.swap(tokens, min_amount_out, deadline)
+    };
+  }
+
+  /// Swaps exact input tokens for output tokens
+  ///
+  /// # Arguments:
+  /// * `tokens`: Bucket - Contains input tokens that will be swapped
+  /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+  /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+  /// * `deadline`: u64 - Last epoch in which the swap can be executed
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains the output tokens
+  pub fn swap_exact_tokens_for_tokens(
+    &self,
+    tokens: Bucket,
+    output_resource_address: ResourceAddress,
+    min_amount_out: Decimal,
+    deadline: u64
+  ) -> Bucket {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.swap_exact_tokens_for_tokens(tokens, min_amount_out, deadline),
+      Pool::StableSwap(pool) => pool.swap_exact_tokens_for_tokens(tokens, min_amount_out, deadline),
+      Pool::Weighted(pool) => pool.swap_exact_tokens_for_tokens(tokens, output_resource_address, min_amount_out, deadline),
+      Pool::Concentrated(pool) => pool.swap_exact_tokens_for_tokens(tokens, min_amount_out, deadline)
+    };
+  }
+
+  /// Swaps input tokens for an exact amount of output tokens
+  ///
+  /// # Arguments:
+  /// * `tokens`: Bucket - Contains tokens that the user wishes to swap
+  /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+  /// * `output_amount`: Decimal - Specific amount of output that the user wishes to receive
+  /// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+  /// * `deadline`: u64 - Last epoch in which the swap can be executed
+  ///
+  /// # Returns:
+  /// * `Bucket` - Contains output tokens
+  /// * `Bucket` - Contains remaining input tokens
+  pub fn swap_tokens_for_exact_tokens(
+    &self,
+    tokens: Bucket,
+    output_resource_address: ResourceAddress,
+    output_amount: Decimal,
+    max_input: Decimal,
+    deadline: u64
+  ) -> (Bucket, Bucket) {
+    return match self {
+      Pool::ConstantProduct(pool) => pool.swap_tokens_for_exact_tokens(tokens, output_amount, max_input, deadline),
+      Pool::StableSwap(pool) => pool.swap_tokens_for_exact_tokens(tokens, output_amount, max_input, deadline),
+      Pool::Weighted(pool) => pool.swap_tokens_for_exact_tokens(
+        tokens, output_resource_address, output_amount, max_input, deadline
+      ),
+      Pool::Concentrated(pool) => pool.swap_tokens_for_exact_tokens(tokens, output_amount, max_input, deadline)
+    };
+  }
+}
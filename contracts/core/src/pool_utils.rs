@@ -0,0 +1,462 @@
+use scrypto::prelude::*;
+use crate::utils::*;
+
+/// Asserts that two tokens can form a pool together
+///
+/// # Arguments:
+/// * `address0`: ResourceAddress - Address of the first token
+/// * `address1`: ResourceAddress - Address of the second token
+/// * `label`: String - Label that called the assertion method
+pub fn assert_valid_pair(
+  address0: ResourceAddress,
+  address1: ResourceAddress,
+  label: String
+) {
+  assert_ne!(
+    address0, address1,
+    "[{}]: Liquidity Pool must be created using two different tokens.",
+    label
+  );
+
+  for address in [address0, address1] {
+    assert_ne!(
+      borrow_resource_manager!(address).resource_type(),
+      ResourceType::NonFungible,
+      "[{}]: Both assets must be fungible.",
+      label
+    );
+  }
+}
+
+/// Creates the rules that let only the holder of the fee admin badge change the fees of a pool after creation
+///
+/// # Arguments:
+/// * `fee_admin_badge`: ResourceAddress - Badge with the authority to change the fees and withdraw protocol fees
+/// * `extra_methods`: Vec<&str> - Methods of the pool restricted to the fee admin on top of the common fee methods
+///
+/// # Returns:
+/// * `AccessRules` - Access rules to add to the pool component
+pub fn fee_admin_access_rules(
+  fee_admin_badge: ResourceAddress,
+  extra_methods: Vec<&str>
+) -> AccessRules {
+  let fee_admin_rule: AccessRule = rule!(require(fee_admin_badge));
+  let mut access_rules: AccessRules = AccessRules::new()
+    .method("set_pool_fee", fee_admin_rule.clone())
+    .method("set_protocol_fee", fee_admin_rule.clone())
+    .method("withdraw_protocol_fees", fee_admin_rule.clone());
+  for method in extra_methods {
+    access_rules = access_rules.method(method, fee_admin_rule.clone());
+  }
+  return access_rules.default(rule!(allow_all));
+}
+
+/// Creates the provider tokens of a new pool and the badge that gives authority to mint and burn them
+///
+/// # Arguments:
+/// * `pid`: &str - Identifier of the pool, its token addresses joined together
+/// * `name`: String - Name of the provider tokens
+///
+/// # Returns:
+/// * `Bucket` - Contains the provider token admin badge, to be kept by the pool
+/// * `Bucket` - Contains the provider tokens issued to the creator of the pool
+pub fn new_provider_tokens(
+  pid: &str,
+  name: String
+) -> (Bucket, Bucket) {
+  // Create admin badge for the liquidity pool to give authority to mint and burn
+  let provider_token_admin_badge: Bucket = ResourceBuilder::new_fungible()
+    .divisibility(DIVISIBILITY_NONE)
+    .metadata("name", "Provider Token Admin Badge")
+    .metadata("symbol", "PTAB")
+    .metadata("description", "Admin Badge with the authority to mint and burn provider tokens")
+    .metadata("pid", format!("{}", pid))
+    .initial_supply(1);
+
+  // Create provider tokens and mint amount owed to initial liquidity provider
+  let provider_tokens: Bucket = ResourceBuilder::new_fungible()
+    .divisibility(DIVISIBILITY_MAXIMUM)
+    .metadata("name", name)
+    .metadata("symbol", "PT")
+    .metadata("description", "Token used to track liquidity provider ownership percentage over liquidity pool.")
+    .metadata("pid", format!("{}", pid))
+    .mintable(rule!(require(provider_token_admin_badge.resource_address())), LOCKED)
+    .burnable(rule!(require(provider_token_admin_badge.resource_address())), LOCKED)
+    .initial_supply(100);
+
+  return (provider_token_admin_badge, provider_tokens);
+}
+
+/// Creates an empty vault for every token of a pool
+///
+/// # Arguments:
+/// * `addresses`: &[ResourceAddress] - Addresses of the tokens of the pool
+///
+/// # Returns:
+/// * `HashMap<ResourceAddress, Vault>` - Empty vault of every token
+pub fn new_empty_vaults(
+  addresses: &[ResourceAddress]
+) -> HashMap<ResourceAddress, Vault> {
+  return addresses.iter().map(|address| (*address, Vault::new(*address))).collect();
+}
+
+/// Validates and changes one of the fees of a pool
+///
+/// # Arguments:
+/// * `fee`: &mut Decimal - Fee of the pool to change
+/// * `new_fee`: Decimal - New value of the fee (0-100)
+/// * `label`: String - Label of the method changing the fee
+pub fn update_fee(
+  fee: &mut Decimal,
+  new_fee: Decimal,
+  label: String
+) {
+  assert_valid_pool_fee(new_fee, label.clone());
+  info!("[{}]: Fee changed from {} to {}", label, fee, new_fee);
+  *fee = new_fee;
+}
+
+/// Obtain the addresses of the tokens held in vaults, sorted like the keys of the ElisionSwap registry
+///
+/// # Arguments:
+/// * `vaults`: &HashMap<ResourceAddress, Vault> - Vaults of the pool
+///
+/// # Returns:
+/// * `Vec<ResourceAddress>` - Sorted addresses of the tokens of the pool
+pub fn vault_addresses(
+  vaults: &HashMap<ResourceAddress, Vault>
+) -> Vec<ResourceAddress> {
+  return sort_address_list(vaults.keys().cloned().collect());
+}
+
+/// Obtain the name of a pool based on the symbols of its tokens
+///
+/// # Arguments:
+/// * `addresses`: &[ResourceAddress] - Addresses of the tokens of the pool
+///
+/// # Returns:
+/// * `String` - Symbols of the sorted tokens joined together
+pub fn pool_name(
+  addresses: &[ResourceAddress]
+) -> String {
+  return sort_address_list(addresses.to_vec()).iter()
+    .map(|address| token_symbol(*address))
+    .collect::<Vec<String>>()
+    .join("-");
+}
+
+/// Asserts that the given address belongs to the pool
+///
+/// # Arguments:
+/// * `vaults`: &HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `address`: ResourceAddress - Address to verify belongs to the pool
+/// * `label`: String - Label that called the assertion method
+pub fn assert_belongs_to_pool(
+  vaults: &HashMap<ResourceAddress, Vault>,
+  address: ResourceAddress,
+  label: String
+) {
+  assert!(
+    vaults.contains_key(&address),
+    "[{}]: Address does not belong to the pool",
+    label
+  );
+}
+
+/// Retrieve address of the other token of a two token pool
+///
+/// # Arguments:
+/// * `vaults`: &HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `resource_address`: ResourceAddress - Address for token from the pool
+///
+/// # Returns:
+/// * `ResourceAddress` - Address of other token in the pool
+pub fn other_address(
+  vaults: &HashMap<ResourceAddress, Vault>,
+  resource_address: ResourceAddress
+) -> ResourceAddress {
+  assert_belongs_to_pool(vaults, resource_address, String::from("Other Resource ResourceAddress"));
+  return *vaults.keys().find(|address| **address != resource_address).unwrap();
+}
+
+/// Deposits a bucket of tokens into the vaults of a pool
+///
+/// # Arguments:
+/// * `vaults`: &mut HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `bucket`: Bucket - Contains the tokens to deposit into the liquidity pool
+pub fn deposit_into(
+  vaults: &mut HashMap<ResourceAddress, Vault>,
+  bucket: Bucket
+) {
+  assert_belongs_to_pool(vaults, bucket.resource_address(), String::from("Deposit"));
+  vaults.get_mut(&bucket.resource_address()).unwrap().put(bucket);
+}
+
+/// Withdraws tokens from the vaults of a pool
+///
+/// # Arguments:
+/// * `vaults`: &mut HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `resource_address`: ResourceAddress - Resource address to withdraw from pool
+/// * `amount`: Decimal - Amount of withdraw from pool
+///
+/// # Returns:
+/// * `Bucket` - Contains withdrawn tokens
+pub fn withdraw_from(
+  vaults: &mut HashMap<ResourceAddress, Vault>,
+  resource_address: ResourceAddress,
+  amount: Decimal
+) -> Bucket {
+  assert_belongs_to_pool(vaults, resource_address, String::from("Withdraw"));
+
+  // Getting the vault of that resource and checking if there is enough liquidity to perform the withdraw.
+  let vault: &mut Vault = vaults.get_mut(&resource_address).unwrap();
+  assert!(
+    vault.amount() >= amount,
+    "[Withdraw]: Not enough liquidity available for the withdraw."
+  );
+
+  return vault.take(amount);
+}
+
+/// Withdraws the same share of every vault of a pool
+///
+/// # Arguments:
+/// * `vaults`: &mut HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `percentage`: Decimal - Share of every vault to withdraw, between 0 and 1
+///
+/// # Returns:
+/// * `Vec<Bucket>` - Contains the share of every token, in the order of `vault_addresses`
+pub fn withdraw_share(
+  vaults: &mut HashMap<ResourceAddress, Vault>,
+  percentage: Decimal
+) -> Vec<Bucket> {
+  let mut buckets: Vec<Bucket> = Vec::new();
+  for address in vault_addresses(vaults) {
+    let amount: Decimal = vaults[&address].amount() * percentage;
+    buckets.push(withdraw_from(vaults, address, amount));
+  }
+  return buckets;
+}
+
+/// Splits the buckets of a two token pool into a pair
+///
+/// # Arguments:
+/// * `buckets`: Vec<Bucket> - Contains exactly two buckets
+///
+/// # Returns:
+/// * `Bucket` - The first bucket
+/// * `Bucket` - The second bucket
+pub fn into_pair(
+  mut buckets: Vec<Bucket>
+) -> (Bucket, Bucket) {
+  assert_eq!(buckets.len(), 2, "[Into Pair]: Pair must be made of two buckets.");
+  let bucket1: Bucket = buckets.pop().unwrap();
+  let bucket0: Bucket = buckets.pop().unwrap();
+  return (bucket0, bucket1);
+}
+
+/// Sets aside the protocol's share of the pool fee paid on an input
+///
+/// # Arguments:
+/// * `protocol_fee_vaults`: &mut HashMap<ResourceAddress, Vault> - Protocol fees collected by the pool
+/// * `tokens`: &mut Bucket - Contains the input tokens
+/// * `protocol_amount`: Decimal - Amount of input tokens diverted to the protocol
+pub fn take_protocol_fee(
+  protocol_fee_vaults: &mut HashMap<ResourceAddress, Vault>,
+  tokens: &mut Bucket,
+  protocol_amount: Decimal
+) {
+  if protocol_amount > Decimal::zero() {
+    info!("[Protocol Fee]: Diverting {} of the input tokens to the protocol", protocol_amount);
+    protocol_fee_vaults.get_mut(&tokens.resource_address()).unwrap().put(tokens.take(protocol_amount));
+  }
+}
+
+/// Calculates the protocol's share of the pool fee paid on an input
+///
+/// # Arguments:
+/// * `input_amount`: Decimal - Amount of input tokens the pool fee is charged on
+/// * `pool_fee`: Decimal - Fee of the pool (0-100)
+/// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+///
+/// # Returns:
+/// * `Decimal` - Amount of input tokens diverted to the protocol
+pub fn protocol_fee_amount(
+  input_amount: Decimal,
+  pool_fee: Decimal,
+  protocol_fee: Decimal
+) -> Decimal {
+  return input_amount * pool_fee / dec!("100") * protocol_fee / dec!("100");
+}
+
+/// Withdraws the protocol fees collected by a pool
+///
+/// # Arguments:
+/// * `protocol_fee_vaults`: &mut HashMap<ResourceAddress, Vault> - Protocol fees collected by the pool
+///
+/// # Returns:
+/// * `Vec<Bucket>` - Contains the protocol fees collected in every token, in the order of `vault_addresses`
+pub fn take_protocol_fees(
+  protocol_fee_vaults: &mut HashMap<ResourceAddress, Vault>
+) -> Vec<Bucket> {
+  return vault_addresses(protocol_fee_vaults).iter()
+    .map(|address| protocol_fee_vaults.get_mut(address).unwrap().take_all())
+    .collect();
+}
+
+/// Mints provider tokens of a pool
+///
+/// # Arguments:
+/// * `provider_token_admin_badge`: &Vault - Badge that gives authority to mint the provider tokens
+/// * `provider_token_address`: ResourceAddress - Address of the provider tokens
+/// * `amount`: Decimal - Amount of provider tokens to mint
+///
+/// # Returns:
+/// * `Bucket` - Contains the minted provider tokens
+pub fn mint_provider_tokens(
+  provider_token_admin_badge: &Vault,
+  provider_token_address: ResourceAddress,
+  amount: Decimal
+) -> Bucket {
+  let provider_tokens_manager: &ResourceManager = borrow_resource_manager!(provider_token_address);
+  let provider_tokens: Bucket = provider_token_admin_badge.authorize(|| {
+    provider_tokens_manager.mint(amount)
+  });
+  info!("[Add Liquidity]: Owed amount of provider tokens: {}", amount);
+  return provider_tokens;
+}
+
+/// Burns provider tokens of a pool
+///
+/// # Arguments:
+/// * `provider_token_admin_badge`: &Vault - Badge that gives authority to burn the provider tokens
+/// * `provider_token_address`: ResourceAddress - Address of the provider tokens of the pool
+/// * `provider_tokens`: Bucket - Contains the provider tokens to burn
+///
+/// # Returns:
+/// * `Decimal` - Share of the pool that the burned provider tokens corresponded to, between 0 and 1
+pub fn burn_provider_tokens(
+  provider_token_admin_badge: &Vault,
+  provider_token_address: ResourceAddress,
+  provider_tokens: Bucket
+) -> Decimal {
+  assert_eq!(
+    provider_tokens.resource_address(),
+    provider_token_address,
+    "[Remove Liquidity]: Provider token does not belong to this liquidity pool"
+  );
+
+  // Calculating the percentage ownership that provider tokens correspond to
+  let provider_tokens_manager: &ResourceManager = borrow_resource_manager!(provider_token_address);
+  let percentage: Decimal = provider_tokens.amount() / provider_tokens_manager.total_supply();
+
+  provider_token_admin_badge.authorize(|| {
+    provider_tokens.burn();
+  });
+  return percentage;
+}
+
+/// Adds liquidity to a two token pool in the ratio of its reserves
+///
+/// # Arguments:
+/// * `vaults`: &mut HashMap<ResourceAddress, Vault> - Vaults of the pool
+/// * `provider_token_admin_badge`: &Vault - Badge that gives authority to mint the provider tokens
+/// * `provider_token_address`: ResourceAddress - Address of the provider tokens of the pool
+/// * `token0`: Bucket - Contains the amount of the first token to add to the pool
+/// * `token1`: Bucket - Contains the amount of the second token to add to the pool
+///
+/// # Returns:
+/// * `Bucket` - Contains remaining tokens of the first sorted token
+/// * `Bucket` - Contains remaining tokens of the second sorted token
+/// * `Bucket` - Contains provider tokens issued to the liquidity provider
+///
+/// # Note:
+/// * Liquidity is added in the ratio of the current reserves so that the deposit does not move the price
+pub fn add_pair_liquidity(
+  vaults: &mut HashMap<ResourceAddress, Vault>,
+  provider_token_admin_badge: &Vault,
+  provider_token_address: ResourceAddress,
+  token0: Bucket,
+  token1: Bucket
+) -> (Bucket, Bucket, Bucket) {
+  // Verify if the tokens belong to this liquidity pool.
+  assert_belongs_to_pool(vaults, token0.resource_address(), String::from("Add Liquidity"));
+  assert_belongs_to_pool(vaults, token1.resource_address(), String::from("Add Liquidity"));
+
+  // Verify that the buckets passed are not empty
+  assert!(!token0.is_empty(), "[Add Liquidity]: Cannot add liquidity from an empty bucket");
+  assert!(!token1.is_empty(), "[Add Liquidity]: Cannot add liquidity from an empty bucket");
+  info!(
+    "[Add Liquidity]: Requested adding liquidity of amounts, {}: {}, {}: {}",
+    token0.resource_address(), token0.amount(), token1.resource_address(), token1.amount()
+  );
+
+  // Sorting out the two buckets passed and getting the values of `dm` and `dn`.
+  let (mut bucket0, mut bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
+  let dm: Decimal = bucket0.amount();
+  let dn: Decimal = bucket1.amount();
+
+  // Getting the values of m and n from the liquidity pool vaults
+  let m: Decimal = vaults[&bucket0.resource_address()].amount();
+  let n: Decimal = vaults[&bucket1.resource_address()].amount();
+  info!(
+    "[Add Liquidity]: Current reserves: {}: {}, {}: {}",
+    bucket0.resource_address(), m, bucket1.resource_address(), n
+  );
+
+  // Compute amount to deposit into liquidity pool based on each bucket passed in
+  let (amount0, amount1): (Decimal, Decimal) = if (
+    (m == Decimal::zero()) | (n == Decimal::zero())) | ((m / n) == (dm / dn)
+  ) {
+    (dm, dn)
+  } else if (m / n) < (dm / dn) {
+    (dn * m / n, dn)
+  } else {
+    (dm, dm * n / m)
+  };
+
+  info!(
+    "[Add Liquidity]: Liquidity amount to add: {}: {}, {}: {}",
+    bucket0.resource_address(), amount0, bucket1.resource_address(), amount1
+  );
+
+  // Deposit calculated token amounts into liquidity pool
+  deposit_into(vaults, bucket0.take(amount0));
+  deposit_into(vaults, bucket1.take(amount1));
+
+  // Compute and mint the amount of provider tokens that the liquidity provider is owed
+  let total_supply: Decimal = borrow_resource_manager!(provider_token_address).total_supply();
+  let provider_amount: Decimal = if total_supply == Decimal::zero() {
+    dec!("100.00")
+  } else {
+    amount0 * total_supply / m
+  };
+  let provider_tokens: Bucket = mint_provider_tokens(
+    provider_token_admin_badge, provider_token_address, provider_amount
+  );
+
+  // Return remaining provider tokens, token0, and token1
+  return (bucket0, bucket1, provider_tokens);
+}
+
+/// Asserts that the input available for a swap to an exact output covers what the swap requires
+///
+/// # Arguments:
+/// * `available`: Decimal - Amount of input tokens passed in
+/// * `input_required`: Decimal - Amount of input tokens the swap requires
+/// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+pub fn assert_input_sufficient(
+  available: Decimal,
+  input_required: Decimal,
+  max_input: Decimal
+) {
+  assert!(
+    available >= input_required,
+    "[Swap For Exact]: Not enough input for the desired amount of output."
+  );
+  assert!(
+    input_required <= max_input,
+    "[Swap For Exact]: max_input not satisfied, {} input tokens are required.",
+    input_required
+  );
+}
@@ -0,0 +1,353 @@
+use scrypto::prelude::*;
+use crate::options::OptionType;
+
+/// Maximum number of steps of the binomial tree, bounds the cost of pricing on-ledger
+pub const MAX_PRICING_STEPS: u64 = 128;
+
+/// Largest exponent `volatility * sqrt(dt) * steps` of the binomial tree, keeps `spot * u^steps` and
+/// `spot * d^steps` within the range and precision of `Decimal`
+pub const MAX_TREE_EXPONENT: u64 = 20;
+
+/// Number of terms used when expanding the Taylor series of `e^x`
+const EXP_SERIES_TERMS: u64 = 32;
+
+/// Number of Newton iterations used when computing square roots
+const SQRT_ITERATIONS: u64 = 64;
+
+/// Number of terms used when expanding the series of `ln(x)`
+const LN_SERIES_TERMS: u64 = 32;
+
+/// Natural logarithm of 2, used to scale the range reduction of `ln(x)` back up
+fn ln_2() -> Decimal {
+  return dec!("0.693147180559945309");
+}
+
+/// Calculates `e^x` using the Taylor series
+///
+/// # Arguments:
+/// * `x`: Decimal - Exponent
+///
+/// # Returns:
+/// * `Decimal` - `e` raised to the power of `x`
+///
+/// # Note:
+/// * The exponent is halved until it is below one half and the result squared back up,
+///   which keeps the series accurate within the precision of `Decimal`
+pub fn exp(x: Decimal) -> Decimal {
+  if x < Decimal::zero() {
+    return Decimal::one() / exp(-x);
+  }
+
+  let mut reduced: Decimal = x;
+  let mut halvings: u32 = 0;
+  while reduced > dec!("0.5") {
+    reduced = reduced / dec!("2");
+    halvings += 1;
+  }
+
+  let mut result: Decimal = Decimal::one();
+  let mut term: Decimal = Decimal::one();
+  for n in 1..EXP_SERIES_TERMS {
+    term = term * reduced / Decimal::from(n);
+    if term == Decimal::zero() {
+      break;
+    }
+    result = result + term;
+  }
+
+  for _ in 0..halvings {
+    result = result * result;
+  }
+  return result;
+}
+
+/// Calculates the natural logarithm of `x` using the series of `2 * atanh(z)`
+///
+/// # Arguments:
+/// * `x`: Decimal - Value to take the logarithm of, must be greater than zero
+///
+/// # Returns:
+/// * `Decimal` - Natural logarithm of `x`
+///
+/// # Note:
+/// * `x` is halved or doubled into `m` between 1 and 2 so that `ln(x) = ln(m) + k * ln(2)`
+/// * `z` - The term `(m - 1) / (m + 1)`, at most one third, where `ln(m) = 2 * (z + z^3 / 3 + z^5 / 5 + ...)`
+pub fn ln(x: Decimal) -> Decimal {
+  assert!(x > Decimal::zero(), "[Pricing]: Cannot take the logarithm of a number that is not positive.");
+
+  let mut reduced: Decimal = x;
+  let mut powers: i64 = 0;
+  while reduced > dec!("2") {
+    reduced = reduced / dec!("2");
+    powers += 1;
+  }
+  while reduced < Decimal::one() {
+    reduced = reduced * dec!("2");
+    powers -= 1;
+  }
+
+  let z: Decimal = (reduced - Decimal::one()) / (reduced + Decimal::one());
+  let z_squared: Decimal = z * z;
+  let mut term: Decimal = z;
+  let mut sum: Decimal = Decimal::zero();
+  for n in 0..LN_SERIES_TERMS {
+    sum = sum + term / Decimal::from(2 * n + 1);
+    term = term * z_squared;
+    if term == Decimal::zero() {
+      break;
+    }
+  }
+
+  return dec!("2") * sum + Decimal::from(powers) * ln_2();
+}
+
+/// Raises `base` to a fractional power
+///
+/// # Arguments:
+/// * `base`: Decimal - Base, must be greater than zero
+/// * `exponent`: Decimal - Exponent
+///
+/// # Returns:
+/// * `Decimal` - `base^exponent`, computed as `e^(exponent * ln(base))`
+pub fn pow(
+  base: Decimal,
+  exponent: Decimal
+) -> Decimal {
+  if (base == Decimal::one()) | (exponent == Decimal::zero()) {
+    return Decimal::one();
+  }
+  return exp(exponent * ln(base));
+}
+
+/// Calculates the square root of `x` using Newton's method
+///
+/// # Arguments:
+/// * `x`: Decimal - Value to take the square root of, cannot be negative
+///
+/// # Returns:
+/// * `Decimal` - Square root of `x`
+pub fn sqrt(x: Decimal) -> Decimal {
+  assert!(x >= Decimal::zero(), "[Pricing]: Cannot take the square root of a negative number.");
+  if x == Decimal::zero() {
+    return Decimal::zero();
+  }
+
+  let mut guess: Decimal = if x > Decimal::one() { x } else { Decimal::one() };
+  for _ in 0..SQRT_ITERATIONS {
+    let next: Decimal = (guess + x / guess) / dec!("2");
+    if next == guess {
+      break;
+    }
+    guess = next;
+  }
+  return guess;
+}
+
+/// Asserts that the binomial tree of an option can be built without overflowing `Decimal`
+///
+/// # Arguments:
+/// * `volatility`: Decimal - Volatility of the underlying per epoch
+/// * `steps`: u64 - Number of steps of the binomial tree
+/// * `expiry_epochs`: u64 - Number of epochs until the option expires
+/// * `label`: String - Label that called the assertion method
+///
+/// # Note:
+/// * The outermost nodes of the tree are `spot * e^(+-volatility * sqrt(dt) * steps)`, which is
+///   `e^(+-volatility * sqrt(expiry_epochs * steps))`
+pub fn assert_pricing_horizon(
+  volatility: Decimal,
+  steps: u64,
+  expiry_epochs: u64,
+  label: String
+) {
+  let exponent: Decimal = volatility * sqrt(Decimal::from(expiry_epochs) * Decimal::from(steps));
+  assert!(
+    exponent <= Decimal::from(MAX_TREE_EXPONENT),
+    "[{}]: Volatility over the time to expiry is too high to price the option, choose an earlier expiry.",
+    label
+  );
+}
+
+/// Calculates the value of exercising a single unit of an option immediately
+///
+/// # Arguments:
+/// * `option_type`: OptionType - Whether the option is a call or a put
+/// * `spot`: Decimal - Price of the underlying token
+/// * `strike`: Decimal - Exercise price of the option
+///
+/// # Returns:
+/// * `Decimal` - `max(0, S - Px)` for a call, `max(Px - S, 0)` for a put
+pub fn intrinsic_value(
+  option_type: OptionType,
+  spot: Decimal,
+  strike: Decimal
+) -> Decimal {
+  let value: Decimal = match option_type {
+    OptionType::Call => spot - strike,
+    OptionType::Put => strike - spot
+  };
+  return if value > Decimal::zero() { value } else { Decimal::zero() };
+}
+
+/// Prices a single unit of an American option using the Binomial Option Pricing Model
+///
+/// # Arguments:
+/// * `option_type`: OptionType - Whether the option is a call or a put
+/// * `spot`: Decimal - Current price of the underlying token
+/// * `strike`: Decimal - Exercise price of the option
+/// * `volatility`: Decimal - Volatility of the underlying per epoch, `0.01` being 1%
+/// * `risk_free_rate`: Decimal - Continuously compounded risk-free rate per epoch
+/// * `steps`: u64 - Number of steps of the binomial tree, between 1 and `MAX_PRICING_STEPS`
+/// * `expiry_epochs`: u64 - Number of epochs until the option expires
+///
+/// # Returns:
+/// * `Decimal` - Premium of a single unit of the option, denominated like `spot` and `strike`
+///
+/// # Note:
+/// * `dt` - The length of a single step in epochs, `expiry_epochs / steps`
+/// * `u` - The up factor where `u = e^(volatility * sqrt(dt))`
+/// * `d` - The down factor where `d = 1 / u`
+/// * `p` - The risk-neutral probability of an up move where `p = (e^(rate * dt) - d) / (u - d)`
+/// * Every node takes the larger of its discounted continuation value and its intrinsic value,
+///   which accounts for early exercise
+pub fn price_american_option(
+  option_type: OptionType,
+  spot: Decimal,
+  strike: Decimal,
+  volatility: Decimal,
+  risk_free_rate: Decimal,
+  steps: u64,
+  expiry_epochs: u64
+) -> Decimal {
+  assert!(spot > Decimal::zero(), "[Pricing]: Spot price must be greater than zero.");
+  assert!(strike > Decimal::zero(), "[Pricing]: Strike must be greater than zero.");
+  assert!(volatility > Decimal::zero(), "[Pricing]: Volatility must be greater than zero.");
+  assert!(risk_free_rate >= Decimal::zero(), "[Pricing]: Risk-free rate cannot be negative.");
+  assert!(
+    (steps > 0) & (steps <= MAX_PRICING_STEPS),
+    "[Pricing]: Number of steps must be between 1 and {}.",
+    MAX_PRICING_STEPS
+  );
+
+  if expiry_epochs == 0 {
+    return intrinsic_value(option_type, spot, strike);
+  }
+  assert_pricing_horizon(volatility, steps, expiry_epochs, String::from("Pricing"));
+
+  // Compute the factors of the tree
+  let dt: Decimal = Decimal::from(expiry_epochs) / Decimal::from(steps);
+  let u: Decimal = exp(volatility * sqrt(dt));
+  let d: Decimal = Decimal::one() / u;
+  let growth: Decimal = exp(risk_free_rate * dt);
+  assert!(
+    (d < growth) & (growth < u),
+    "[Pricing]: Volatility is too low for the risk-free rate, the tree has no risk-neutral probability."
+  );
+  let p: Decimal = (growth - d) / (u - d);
+  let discount: Decimal = Decimal::one() / growth;
+
+  // Option values at expiry, where node `j` has seen `j` up moves and `steps - j` down moves
+  let mut lowest_price: Decimal = spot;
+  for _ in 0..steps {
+    lowest_price = lowest_price * d;
+  }
+  let up_ratio: Decimal = u / d;
+
+  let mut values: Vec<Decimal> = Vec::with_capacity((steps + 1) as usize);
+  let mut node_price: Decimal = lowest_price;
+  for _ in 0..=steps {
+    values.push(intrinsic_value(option_type, node_price, strike));
+    node_price = node_price * up_ratio;
+  }
+
+  // Walk back through the tree checking for early exercise at every node
+  for step in (0..steps).rev() {
+    lowest_price = lowest_price * u;
+    node_price = lowest_price;
+    for j in 0..=(step as usize) {
+      let continuation: Decimal = discount * (p * values[j + 1] + (Decimal::one() - p) * values[j]);
+      let exercise: Decimal = intrinsic_value(option_type, node_price, strike);
+      values[j] = if exercise > continuation { exercise } else { continuation };
+      node_price = node_price * up_ratio;
+    }
+  }
+
+  return values[0];
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Asserts that two values are within `tolerance` of each other
+  fn assert_close(
+    actual: Decimal,
+    expected: Decimal,
+    tolerance: Decimal
+  ) {
+    let difference: Decimal = if actual > expected { actual - expected } else { expected - actual };
+    assert!(difference <= tolerance, "Expected {} but got {}", expected, actual);
+  }
+
+  #[test]
+  fn exp_matches_known_values() {
+    assert_eq!(exp(Decimal::zero()), Decimal::one());
+    assert_close(exp(Decimal::one()), dec!("2.718281828459045235"), dec!("0.000000000001"));
+    assert_close(exp(dec!("-1")), dec!("0.367879441171442321"), dec!("0.000000000001"));
+    assert_close(exp(dec!("10")), dec!("22026.465794806716516957"), dec!("0.00000001"));
+  }
+
+  #[test]
+  fn ln_inverts_exp() {
+    assert_eq!(ln(Decimal::one()), Decimal::zero());
+    assert_close(ln(dec!("10")), dec!("2.302585092994045684"), dec!("0.000000000001"));
+    assert_close(ln(exp(dec!("3"))), dec!("3"), dec!("0.000000000001"));
+  }
+
+  #[test]
+  fn sqrt_matches_known_values() {
+    assert_eq!(sqrt(Decimal::zero()), Decimal::zero());
+    assert_close(sqrt(dec!("4")), dec!("2"), dec!("0.000000000001"));
+    assert_close(sqrt(dec!("2")), dec!("1.414213562373095048"), dec!("0.000000000001"));
+    assert_close(sqrt(dec!("0.25")), dec!("0.5"), dec!("0.000000000001"));
+  }
+
+  #[test]
+  fn option_at_expiry_is_worth_its_intrinsic_value() {
+    let call: Decimal = price_american_option(OptionType::Call, dec!("110"), dec!("100"), dec!("0.1"), dec!("0.01"), 8, 0);
+    let put: Decimal = price_american_option(OptionType::Put, dec!("110"), dec!("100"), dec!("0.1"), dec!("0.01"), 8, 0);
+    assert_eq!(call, dec!("10"));
+    assert_eq!(put, Decimal::zero());
+  }
+
+  #[test]
+  fn single_step_call_matches_replication() {
+    // u = e^0.1, d = e^-0.1, p = (e^0.01 - d) / (u - d), C = e^-0.01 * p * (100 * u - 100)
+    let call: Decimal = price_american_option(OptionType::Call, dec!("100"), dec!("100"), dec!("0.1"), dec!("0.01"), 1, 1);
+    assert_close(call, dec!("5.468491101506975"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn american_put_matches_reference_tree() {
+    // Five monthly steps with S = 50, K = 50, 40% annual volatility and a 10% annual rate,
+    // the reference American put is worth 4.49
+    let volatility: Decimal = dec!("0.4") / sqrt(dec!("12"));
+    let rate: Decimal = dec!("0.1") / dec!("12");
+    let put: Decimal = price_american_option(OptionType::Put, dec!("50"), dec!("50"), volatility, rate, 5, 5);
+    assert_close(put, dec!("4.488458534725916"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn american_call_without_dividends_is_never_exercised_early() {
+    let volatility: Decimal = dec!("0.4") / sqrt(dec!("12"));
+    let rate: Decimal = dec!("0.1") / dec!("12");
+    let call: Decimal = price_american_option(OptionType::Call, dec!("50"), dec!("50"), volatility, rate, 5, 5);
+    assert_close(call, dec!("6.35954586105892"), dec!("0.000001"));
+  }
+
+  #[test]
+  #[should_panic(expected = "[Pricing]: Volatility over the time to expiry is too high to price the option")]
+  fn tree_that_would_overflow_is_rejected() {
+    price_american_option(OptionType::Call, dec!("100"), dec!("100"), dec!("0.5"), dec!("0.01"), 128, 100000);
+  }
+}
@@ -0,0 +1,568 @@
+use scrypto::prelude::*;
+use crate::utils::*;
+use crate::pool_utils::*;
+
+/// Maximum number of Newton iterations used when solving the stable swap invariant
+const MAX_ITERATIONS: u64 = 255;
+
+/// Difference between two Newton iterations, relative to the solution, below which the solution is considered converged
+fn convergence_threshold() -> Decimal {
+  return dec!("0.000000000001");
+}
+
+/// Calculates the stable swap invariant `D` of a two token pool
+///
+/// # Arguments:
+/// * `x0`: Decimal - Reserves of the first token
+/// * `x1`: Decimal - Reserves of the second token
+/// * `amplification`: Decimal - Amplification coefficient of the pool (`A`)
+///
+/// # Returns:
+/// * `Decimal` - Invariant `D`, the total amount of tokens when both reserves are equal
+///
+/// # Note:
+/// * Solves `A * n^n * (x0 + x1) + D = A * D * n^n + D^(n + 1) / (n^n * x0 * x1)` for `D` using Newton's method
+/// * `n` - The number of tokens in the pool, always 2
+/// * `D_P` - The term `D^(n + 1) / (n^n * x0 * x1)`, computed one reserve at a time and dividing before multiplying
+///   so that it never grows past `D`
+pub fn compute_d(
+  x0: Decimal,
+  x1: Decimal,
+  amplification: Decimal
+) -> Decimal {
+  let s: Decimal = x0 + x1;
+  if s == Decimal::zero() {
+    return Decimal::zero();
+  }
+
+  let ann: Decimal = amplification * dec!("4");
+  let threshold: Decimal = convergence_threshold();
+  let mut d: Decimal = s;
+  for _ in 0..MAX_ITERATIONS {
+    let mut d_p: Decimal = d;
+    d_p = d_p * (d / (x0 * dec!("2")));
+    d_p = d_p * (d / (x1 * dec!("2")));
+
+    let previous_d: Decimal = d;
+    d = (ann * s + d_p * dec!("2")) * (d / ((ann - Decimal::one()) * d + dec!("3") * d_p));
+    let difference: Decimal = if d > previous_d { d - previous_d } else { previous_d - d };
+    if difference <= threshold * d {
+      return d;
+    }
+  }
+  panic!("[Stable Swap]: Invariant did not converge.");
+}
+
+/// Calculates the reserves of one token that keep the stable swap invariant for given reserves of the other
+///
+/// # Arguments:
+/// * `x`: Decimal - Reserves of the other token
+/// * `d`: Decimal - Invariant `D` of the pool
+/// * `amplification`: Decimal - Amplification coefficient of the pool (`A`)
+///
+/// # Returns:
+/// * `Decimal` - Reserves of the token such that the invariant stays at `D`
+///
+/// # Note:
+/// * Solves `y^2 + (b - D) * y - c = 0` for `y` using Newton's method, the step `(y^2 + c) / (2 * y + b - D)` is
+///   divided through by `y` so that `y^2` is never formed
+/// * `b` - The term `x + D / (A * n^n)`
+/// * `c` - The term `D^(n + 1) / (n^n * x * A * n^n)`, dividing before multiplying so that it stays near `D^2 / A`
+pub fn compute_y(
+  x: Decimal,
+  d: Decimal,
+  amplification: Decimal
+) -> Decimal {
+  assert!(x > Decimal::zero(), "[Stable Swap]: Reserves must be greater than zero.");
+
+  let ann: Decimal = amplification * dec!("4");
+  let threshold: Decimal = convergence_threshold();
+  let mut c: Decimal = d * (d / (x * dec!("2")));
+  c = c * (d / (ann * dec!("2")));
+  let b: Decimal = x + d / ann;
+
+  let mut y: Decimal = d;
+  for _ in 0..MAX_ITERATIONS {
+    let previous_y: Decimal = y;
+    y = (y + c / y) / (dec!("2") + (b - d) / y);
+    let difference: Decimal = if y > previous_y { y - previous_y } else { previous_y - y };
+    if difference <= threshold * y {
+      return y;
+    }
+  }
+  panic!("[Stable Swap]: Reserves did not converge.");
+}
+
+blueprint! {
+  /// Structure representing a Stable Swap Pool for the Elision Exchange, meant for pegged assets
+  ///
+  /// # Contains:
+  /// * `vaults`: HashMap<ResourceAddress, Vault> - Collection of addresses and associated vaults
+  /// * `provider_token_address`: ResourceAddress - Token that providers receive for adding liquidity
+  /// * `provider_token_admin_badge`: Vault - Badge that gives authority to mint and burn tokens
+  /// * `amplification`: Decimal - Amplification coefficient, the higher the flatter the curve around the peg
+  /// * `pool_fee`: Decimal - Value between 0 and 100 defining fees paid to liquidity pool
+  /// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100), off by default
+  /// * `protocol_fee_vaults`: HashMap<ResourceAddress, Vault> - Protocol fees collected until they are withdrawn
+  struct StableSwapPool {
+    vaults: HashMap<ResourceAddress, Vault>,
+    provider_token_address: ResourceAddress,
+    provider_token_admin_badge: Vault,
+    amplification: Decimal,
+    pool_fee: Decimal,
+    protocol_fee: Decimal,
+    protocol_fee_vaults: HashMap<ResourceAddress, Vault>
+  }
+
+  impl StableSwapPool {
+    /// Create new Stable Swap Pool from given tokens passed into the function
+    ///
+    /// # Arguments:
+    /// * `token0`: Bucket - Contains first token to initialize the pool
+    /// * `token1`: Bucket - Contains second token to initialize the pool
+    /// * `amplification`: Decimal - Amplification coefficient of the pool, at least 1
+    /// * `pool_fee`: Decimal - Fee imposed on all swaps from this liquidity pool (0-100).
+    /// * `fee_admin_badge`: ResourceAddress - Badge with the authority to change the fees and withdraw protocol fees
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - StableSwapPool component address for initialized pool
+    /// * `Bucket` - Bucket containing the issued provider tokens to the liquidity pool creator
+    pub fn new(
+      token0: Bucket,
+      token1: Bucket,
+      amplification: Decimal,
+      pool_fee: Decimal,
+      fee_admin_badge: ResourceAddress
+    ) -> (ComponentAddress, Bucket) {
+      assert_valid_pair(token0.resource_address(), token1.resource_address(), String::from("Stable Pool Creation"));
+      assert!(
+        !token0.is_empty() & !token1.is_empty(),
+        "[Stable Pool Creation]: Cannot create a pool from an empty bucket."
+      );
+
+      assert!(
+        amplification >= Decimal::one(),
+        "[Stable Pool Creation]: Amplification coefficient must be at least 1."
+      );
+
+      assert_valid_pool_fee(pool_fee, String::from("Stable Pool Creation"));
+
+      // Sort buckets and create hashmap between vaults and buckets
+      let (bucket0, bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
+      let addresses: (ResourceAddress, ResourceAddress) = (bucket0.resource_address(), bucket1.resource_address());
+      let pid: String = format!("{}-{}", addresses.0, addresses.1);
+      let pair_name: String = pair_symbol(addresses.0, addresses.1);
+
+      info!(
+        "[Stable Pool Creation]: Creating new pool from Tokens: {}, Name: {}, Ratio: {}:{}, A: {}",
+        pid, pair_name, bucket0.amount(), bucket1.amount(), amplification
+      );
+
+      let mut vaults: HashMap<ResourceAddress, Vault> = HashMap::new();
+      vaults.insert(bucket0.resource_address(), Vault::with_bucket(bucket0));
+      vaults.insert(bucket1.resource_address(), Vault::with_bucket(bucket1));
+
+      let (provider_token_admin_badge, provider_tokens): (Bucket, Bucket) = new_provider_tokens(
+        &pid, format!("{} Stable LP Provider Token", pair_name)
+      );
+      let access_rules: AccessRules = fee_admin_access_rules(fee_admin_badge, Vec::new());
+
+      // Create and instantiate stable swap pool component
+      let mut stable_swap_pool = Self {
+        vaults: vaults,
+        provider_token_address: provider_tokens.resource_address(),
+        provider_token_admin_badge: Vault::with_bucket(provider_token_admin_badge),
+        amplification: amplification,
+        pool_fee: pool_fee,
+        protocol_fee: Decimal::zero(),
+        protocol_fee_vaults: new_empty_vaults(&[addresses.0, addresses.1])
+      }
+      .instantiate();
+      stable_swap_pool.add_access_check(access_rules);
+
+      return (stable_swap_pool.globalize(), provider_tokens);
+    }
+
+    /// Changes the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - New fee for the pool (0-100)
+    pub fn set_pool_fee(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      update_fee(&mut self.pool_fee, pool_fee, String::from("Set Pool Fee"));
+    }
+
+    /// Obtain the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fee of the pool (0-100)
+    pub fn pool_fee(&self) -> Decimal {
+      return self.pool_fee;
+    }
+
+    /// Changes the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Arguments:
+    /// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn set_protocol_fee(
+      &mut self,
+      protocol_fee: Decimal
+    ) {
+      update_fee(&mut self.protocol_fee, protocol_fee, String::from("Set Protocol Fee"));
+    }
+
+    /// Obtain the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn protocol_fee(&self) -> Decimal {
+      return self.protocol_fee;
+    }
+
+    /// Withdraws the protocol fees collected by the pool
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the protocol fees collected in the first token
+    /// * `Bucket` - Contains the protocol fees collected in the second token
+    pub fn withdraw_protocol_fees(&mut self) -> (Bucket, Bucket) {
+      return into_pair(take_protocol_fees(&mut self.protocol_fee_vaults));
+    }
+
+    /// Obtain the amplification coefficient of the pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Amplification coefficient (`A`)
+    pub fn amplification(&self) -> Decimal {
+      return self.amplification;
+    }
+
+    /// Verifies that the given address belongs to liquidity pool or not
+    ///
+    /// # Arguments:
+    /// * `address`: ResourceAddress - Address that is going to be verified
+    ///
+    /// # Returns:
+    /// * `bool` - True if address belongs to pool, false otherwise
+    pub fn belongs_to_pool(
+      &self,
+      address: ResourceAddress
+    ) -> bool {
+      return self.vaults.contains_key(&address);
+    }
+
+    /// Obtain token addresses in liquidity pool, sorted like the keys of the ElisionSwap registry
+    ///
+    /// # Returns:
+    /// * `Vec<ResourceAddress>` - Vector of addresses that belong to the pool
+    pub fn addresses(&self) -> Vec<ResourceAddress> {
+      return vault_addresses(&self.vaults);
+    }
+
+    /// Obtain name of liquidity pool based on pair symbol.
+    ///
+    /// # Returns:
+    /// * `String` - Pair symbol string
+    pub fn name(&self) -> String {
+      return pool_name(&self.addresses());
+    }
+
+    /// Retrieve address of other resource if address belongs to the pool
+    /// # Arguments
+    /// * `resource_address`: ResourceAddress - Address for token from the pool
+    ///
+    /// # Returns:
+    /// * `ResourceAddress` - Address of other token in the pool
+    pub fn other_resource_address(
+      &self,
+      resource_address: ResourceAddress
+    ) -> ResourceAddress {
+      return other_address(&self.vaults, resource_address);
+    }
+
+    /// Calculates the stable swap invariant `D` of the pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Invariant `D` for the current reserves
+    pub fn d(&self) -> Decimal {
+      let addresses: Vec<ResourceAddress> = self.addresses();
+      return compute_d(
+        self.vaults[&addresses[0]].amount(),
+        self.vaults[&addresses[1]].amount(),
+        self.amplification
+      );
+    }
+
+    /// Calculates amount of output that can be given based on the amount of input
+    /// # Arguments:
+    /// * `input_address`: ResourceAddress - Input token address
+    /// * `input_amount`: Decimal - Input amount to calculate output with
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated output amount
+    ///
+    /// # Note:
+    /// * `x` - The amount of reserves of token x (the input token)
+    /// * `y` - The amount of reserves of token y (the output token)
+    /// * `dx` - The amount of input tokens
+    /// * `dy` - The amount of output tokens where `dy = y - compute_y(x + r * dx, D)`
+    /// * `r` - The fee modifier where `r = (100 - fee) / 100`
+    pub fn calculate_output_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      input_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Output"));
+
+      let x: Decimal = self.vaults[&input_resource_address].amount();
+      let y: Decimal = self.vaults[&self.other_resource_address(input_resource_address)].amount();
+      let r: Decimal = (dec!("100") - self.pool_fee) / dec!("100");
+
+      let new_y: Decimal = compute_y(x + r * input_amount, self.d(), self.amplification);
+      return if y > new_y { y - new_y } else { Decimal::zero() };
+    }
+
+    /// Calculates amount of input that can be given based on the amount of output
+    /// # Arguments:
+    /// * `output_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated input amount
+    ///
+    /// # Note:
+    /// * `x` - The amount of reserves of token x (the input token)
+    /// * `y` - The amount of reserves of token y (the output token)
+    /// * `dx` - The amount of input tokens where `dx = (compute_y(y - dy, D) - x) / r`
+    /// * `dy` - The amount of output tokens
+    /// * `r` - The fee modifier where `r = (100 - fee) / 100`
+    pub fn calculate_input_amount(
+      &self,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Calculate Input"));
+
+      let x: Decimal = self.vaults[&self.other_resource_address(output_resource_address)].amount();
+      let y: Decimal = self.vaults[&output_resource_address].amount();
+      assert!(
+        output_amount < y,
+        "[Calculate Input]: Not enough liquidity available for the requested output."
+      );
+      let r: Decimal = (dec!("100") - self.pool_fee) / dec!("100");
+
+      let new_x: Decimal = compute_y(y - output_amount, self.d(), self.amplification);
+      return (new_x - x) / r;
+    }
+
+    /// Quotes the amount of input required for an amount of output, without failing when the pool cannot give it
+    ///
+    /// # Arguments:
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Option<Decimal>` - Calculated input amount, None if the output is not below the reserves of the pool
+    pub fn quote_input_amount(
+      &self,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Option<Decimal> {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Quote Input"));
+      if output_amount >= self.vaults[&output_resource_address].amount() {
+        return None;
+      }
+      return Some(self.calculate_input_amount(output_resource_address, output_amount));
+    }
+
+    /// Adds liquidity to the pool in exchange for liquidity provider tokens
+    ///
+    /// # Arguments:
+    /// * `token0`: Bucket - Contains the amount of the first token to add to the pool
+    /// * `token1`: Bucket - Contains the amount of the second token to add to the pool
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains remaining tokens of the `token0`
+    /// * `Bucket` - Contains remaining tokens of the `token1`
+    /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+    ///
+    /// # Note:
+    /// * Liquidity is added in the ratio of the current reserves so that the deposit does not move the price
+    pub fn add_liquidity(
+      &mut self,
+      token0: Bucket,
+      token1: Bucket,
+      deadline: u64
+    ) -> (Bucket, Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Add Liquidity"));
+      return add_pair_liquidity(
+        &mut self.vaults, &self.provider_token_admin_badge, self.provider_token_address, token0, token1
+      );
+    }
+
+    /// Removes the percentage of the liquidity owed to this liquidity provider
+    /// # Arguments:
+    /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains share of liquidity provider of the first token.
+    /// * `Bucket` - Contains share of liquidity provider of the second token.
+    pub fn remove_liquidity(
+      &mut self,
+      provider_tokens: Bucket,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Remove Liquidity"));
+      let percentage: Decimal = burn_provider_tokens(
+        &self.provider_token_admin_badge, self.provider_token_address, provider_tokens
+      );
+
+      // Withdrawing the amount of tokens owed to this liquidity provider
+      return into_pair(withdraw_share(&mut self.vaults, percentage));
+    }
+
+    /// Execute token swap and take pool fee
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the input tokens that will be swapped for other tokens
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the other tokens
+    pub fn swap(
+      &mut self,
+      mut tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap"));
+      assert_deadline(deadline, String::from("Swap"));
+      info!("[Swap]: D before swap: {}", self.d());
+
+      let output_amount: Decimal = self.calculate_output_amount(tokens.resource_address(), tokens.amount());
+      assert!(output_amount >= min_amount_out, "[Swap]: min_amount_out not satisfied.");
+      let output_address: ResourceAddress = self.other_resource_address(tokens.resource_address());
+      let output_tokens: Bucket = withdraw_from(&mut self.vaults, output_address, output_amount);
+
+      // Deposit tokens into liquidity pool, minus the protocol fee, and return bucket of swapped tokens
+      let protocol_amount: Decimal = protocol_fee_amount(tokens.amount(), self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut tokens, protocol_amount);
+      deposit_into(&mut self.vaults, tokens);
+      info!("[Swap]: D after swap: {}", self.d());
+      return output_tokens;
+    }
+
+    /// Swaps exact input tokens for output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains input tokens that will be swapped
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains other tokens
+    pub fn swap_exact_tokens_for_tokens(
+      &mut self,
+      tokens: Bucket,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      return self.swap(tokens, min_amount_out, deadline);
+    }
+
+    /// Swaps input tokens for an exact amount of output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains tokens that the user wishes to swap
+    /// * `output_amount`: Decimal - Specific amount of output that the user wishes to receive
+    /// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains output tokens
+    /// * `Bucket` - Contains remaining input tokens
+    pub fn swap_tokens_for_exact_tokens(
+      &mut self,
+      mut tokens: Bucket,
+      output_amount: Decimal,
+      max_input: Decimal,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap For Exact"));
+      assert_deadline(deadline, String::from("Swap For Exact"));
+
+      let input_required: Decimal = self.calculate_input_amount(
+        self.other_resource_address(tokens.resource_address()),
+        output_amount
+      );
+      assert_input_sufficient(tokens.amount(), input_required, max_input);
+
+      info!("[Swap For Exact]: D before swap: {}", self.d());
+      let mut input_tokens: Bucket = tokens.take(input_required);
+      let protocol_amount: Decimal = protocol_fee_amount(input_required, self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut input_tokens, protocol_amount);
+      deposit_into(&mut self.vaults, input_tokens);
+      let output_address: ResourceAddress = self.other_resource_address(tokens.resource_address());
+      let output_tokens: Bucket = withdraw_from(&mut self.vaults, output_address, output_amount);
+      info!("[Swap For Exact]: D after swap: {}", self.d());
+      return (output_tokens, tokens);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Asserts that two values are within `tolerance` of each other
+  fn assert_close(
+    actual: Decimal,
+    expected: Decimal,
+    tolerance: Decimal
+  ) {
+    let difference: Decimal = if actual > expected { actual - expected } else { expected - actual };
+    assert!(difference <= tolerance, "Expected {} but got {}", expected, actual);
+  }
+
+  #[test]
+  fn balanced_reserves_give_their_sum() {
+    assert_eq!(compute_d(Decimal::zero(), Decimal::zero(), dec!("100")), Decimal::zero());
+    assert_close(compute_d(dec!("1000"), dec!("1000"), dec!("100")), dec!("2000"), dec!("0.000000001"));
+    assert_close(compute_d(Decimal::one(), Decimal::one(), Decimal::one()), dec!("2"), dec!("0.000000001"));
+  }
+
+  #[test]
+  fn imbalanced_reserves_give_less_than_their_sum() {
+    let d: Decimal = compute_d(dec!("1000"), dec!("1500"), dec!("100"));
+    assert!(d < dec!("2500"));
+    assert!(d > dec!("2499"));
+  }
+
+  #[test]
+  fn swap_on_compute_y_keeps_d() {
+    let d: Decimal = compute_d(dec!("1000"), dec!("1500"), dec!("100"));
+    let y: Decimal = compute_y(dec!("1100"), d, dec!("100"));
+    assert!(y < dec!("1500"));
+    assert_close(compute_d(dec!("1100"), y, dec!("100")), d, dec!("0.000000001"));
+  }
+
+  #[test]
+  fn compute_y_inverts_compute_d() {
+    let d: Decimal = compute_d(dec!("5000000"), dec!("1000"), dec!("50"));
+    assert_close(compute_y(dec!("5000000"), d, dec!("50")), dec!("1000"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn large_reserves_do_not_overflow() {
+    let x: Decimal = dec!("10000000000");
+    let d: Decimal = compute_d(x, x, dec!("100"));
+    assert_close(d, dec!("20000000000"), dec!("0.0001"));
+
+    let y: Decimal = compute_y(x + dec!("1000000000"), d, dec!("100"));
+    assert_close(compute_d(x + dec!("1000000000"), y, dec!("100")), d, dec!("0.0001"));
+  }
+}
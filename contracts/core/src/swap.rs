@@ -1,18 +1,39 @@
 use scrypto::prelude::*;
 use crate::liquidity_pool::*;
+use crate::stable_swap_pool::*;
+use crate::weighted_pool::*;
+use crate::concentrated_pool::*;
+use crate::pool::*;
 use crate::utils::*;
 
+/// Maximum number of pools a multi-hop swap can route through
+pub const MAX_HOPS: usize = 4;
+
+/// Fee of pools created implicitly through `add_liquidity`, in percent
+pub fn default_pool_fee() -> Decimal {
+  return dec!("0.3");
+}
+
 blueprint! {
   /// Struct used to define the ElisionSwap
   ///
   /// # Contains:
-  /// * `liquidity_pools`: Hashmap<(ResourceAddress, ResourceAddress), LiquidityPool>
-  ///   - Collection of pools that exist on the Swap
-  /// * `address_pair_map`: Hashmap<ResourceAddress,(ResourceAddress, ResourceAddress)>
-  ///   - Collection of token pairs and associated provider tokens
+  /// * `liquidity_pools`: Hashmap<PoolKey, Pool>
+  ///   - Collection of pools of any type that exist on the Swap, keyed by the sorted addresses of their tokens and
+  ///     their type
+  /// * `address_pair_map`: Hashmap<ResourceAddress, PoolKey>
+  ///   - Collection of provider tokens and the key of their associated pool
+  /// * `pool_admin_badge`: Vault - Badge with the authority to change the fee of the pools
+  /// * `fee_tiers`: Vec<Decimal> - Fees that pools can be created with or moved to (0-100)
+  /// * `protocol_fee`: Decimal - Percentage of every pool fee diverted to the treasury (0-100), off by default
+  /// * `treasury`: HashMap<ResourceAddress, Vault> - Protocol fees collected from the pools
   struct ElisionSwap {
-    liquidity_pools: HashMap<(ResourceAddress, ResourceAddress), LiquidityPool>,
-    address_pair_map: HashMap<ResourceAddress,(ResourceAddress, ResourceAddress)>
+    liquidity_pools: HashMap<PoolKey, Pool>,
+    address_pair_map: HashMap<ResourceAddress, PoolKey>,
+    pool_admin_badge: Vault,
+    fee_tiers: Vec<Decimal>,
+    protocol_fee: Decimal,
+    treasury: HashMap<ResourceAddress, Vault>
   }
 
   impl ElisionSwap {
@@ -20,115 +41,540 @@ blueprint! {
     ///
     /// # Returns:
     /// * `ComponentAddress`: Returns new ElisionSwap component address
-    pub fn new() -> ComponentAddress {
-      return Self {
+    /// * `Bucket`: Admin badge with the authority to manage fee tiers, pool fees and the treasury
+    pub fn new() -> (ComponentAddress, Bucket) {
+      let admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Elision Swap Admin Badge")
+        .metadata("symbol", "ESAB")
+        .metadata("description", "Admin Badge with the authority to manage fee tiers, pool fees and the treasury")
+        .initial_supply(1);
+
+      let pool_admin_badge: Bucket = ResourceBuilder::new_fungible()
+        .divisibility(DIVISIBILITY_NONE)
+        .metadata("name", "Elision Swap Pool Admin Badge")
+        .metadata("symbol", "ESPAB")
+        .metadata("description", "Badge held by the Swap with the authority to change the fee of its pools")
+        .initial_supply(1);
+
+      let admin_rule: AccessRule = rule!(require(admin_badge.resource_address()));
+      let access_rules: AccessRules = AccessRules::new()
+        .method("add_fee_tier", admin_rule.clone())
+        .method("remove_fee_tier", admin_rule.clone())
+        .method("set_pool_fee", admin_rule.clone())
+        .method("set_protocol_fee", admin_rule.clone())
+        .method("withdraw_protocol_fees", admin_rule)
+        .default(rule!(allow_all));
+
+      // Stablecoin pairs, regular pairs and exotic pairs
+      let fee_tiers: Vec<Decimal> = vec![dec!("0.01"), dec!("0.05"), default_pool_fee(), dec!("1")];
+
+      let mut elision_swap = Self {
         liquidity_pools: HashMap::new(),
-        address_pair_map: HashMap::new()
+        address_pair_map: HashMap::new(),
+        pool_admin_badge: Vault::with_bucket(pool_admin_badge),
+        fee_tiers: fee_tiers,
+        protocol_fee: Decimal::zero(),
+        treasury: HashMap::new()
       }
-      .instantiate()
-      .globalize();
+      .instantiate();
+      elision_swap.add_access_check(access_rules);
+
+      return (elision_swap.globalize(), admin_badge);
+    }
+
+    /// Obtain the fees that pools can be created with
+    ///
+    /// # Returns:
+    /// * `Vec<Decimal>` - Available fee tiers (0-100)
+    pub fn fee_tiers(&self) -> Vec<Decimal> {
+      return self.fee_tiers.clone();
+    }
+
+    /// Assert that a fee is one of the available fee tiers
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - Fee to check
+    /// * `label`: String - Label for the assertion output
+    pub fn assert_fee_tier(
+      &self,
+      pool_fee: Decimal,
+      label: String
+    ) {
+      assert!(
+        self.fee_tiers.contains(&pool_fee),
+        "[{}]: Fee {} is not one of the available fee tiers {:?}.",
+        label, pool_fee, self.fee_tiers
+      );
+    }
+
+    /// Makes a new fee available to pools
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - Fee of the new tier (0-100)
+    pub fn add_fee_tier(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      assert_valid_pool_fee(pool_fee, String::from("Add Fee Tier"));
+      assert!(
+        !self.fee_tiers.contains(&pool_fee),
+        "[Add Fee Tier]: Fee tier already exists."
+      );
+      info!("[Add Fee Tier]: Added fee tier {}", pool_fee);
+      self.fee_tiers.push(pool_fee);
     }
 
-    /// Check to ensure that liquidity pool exists for given token pair
+    /// Stops a fee from being used by new pools, existing pools keep their fee
     ///
     /// # Arguments:
-    /// * `address`: ResourceAddress - First token address
-    /// * `address`: ResourceAddress - Second token address
+    /// * `pool_fee`: Decimal - Fee of the tier to remove
+    pub fn remove_fee_tier(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      self.assert_fee_tier(pool_fee, String::from("Remove Fee Tier"));
+      info!("[Remove Fee Tier]: Removed fee tier {}", pool_fee);
+      self.fee_tiers.retain(|fee| *fee != pool_fee);
+    }
+
+    /// Obtain the fee of a pool
+    ///
+    /// # Arguments:
+    /// * `addresses`: Vec<ResourceAddress> - Addresses of the tokens of the pool, in any order
+    /// * `kind`: PoolKind - Type of the pool
     ///
     /// # Returns:
-    /// * `bool` - True if exists for trading pair, false otherwise
+    /// * `Decimal` - Fee of the pool (0-100)
+    pub fn pool_fee(
+      &self,
+      addresses: Vec<ResourceAddress>,
+      kind: PoolKind
+    ) -> Decimal {
+      let key: PoolKey = (sort_address_list(addresses), kind);
+      self.assert_exists(key.clone(), String::from("Pool Fee"));
+      return self.liquidity_pools[&key].pool_fee();
+    }
+
+    /// Moves a pool to another fee tier
+    ///
+    /// # Arguments:
+    /// * `addresses`: Vec<ResourceAddress> - Addresses of the tokens of the pool, in any order
+    /// * `kind`: PoolKind - Type of the pool
+    /// * `pool_fee`: Decimal - New fee of the pool, must be one of the fee tiers
+    pub fn set_pool_fee(
+      &mut self,
+      addresses: Vec<ResourceAddress>,
+      kind: PoolKind,
+      pool_fee: Decimal
+    ) {
+      let key: PoolKey = (sort_address_list(addresses), kind);
+      self.assert_exists(key.clone(), String::from("Set Pool Fee"));
+      self.assert_fee_tier(pool_fee, String::from("Set Pool Fee"));
+
+      let liquidity_pool: &Pool = &self.liquidity_pools[&key];
+      self.pool_admin_badge.authorize(|| {
+        liquidity_pool.set_pool_fee(pool_fee);
+      });
+    }
+
+    /// Check to ensure that a pool of the given type exists for exactly the given tokens
+    ///
+    /// # Arguments:
+    /// * `addresses`: Vec<ResourceAddress> - Addresses of the tokens of the pool, in any order
+    /// * `kind`: PoolKind - Type of the pool
+    ///
+    /// # Returns:
+    /// * `bool` - True if a pool of the type exists for the tokens, false otherwise
     pub fn pool_exists(
       &self,
-      address0: ResourceAddress,
-      address1: ResourceAddress
+      addresses: Vec<ResourceAddress>,
+      kind: PoolKind
     ) -> bool {
-      let addresses: (ResourceAddress, ResourceAddress) = sort_addresses(address0, address1);
-      self.liquidity_pools.contains_key(&addresses)
+      self.liquidity_pools.contains_key(&(sort_address_list(addresses), kind))
     }
 
-    /// Assert that liquidity pool exists on Swap
+    /// Assert that a pool exists on Swap for the given key
     ///
     /// # Arguments:
-    /// * `address`: ResourceAddress - First token address
-    /// * `address`: ResourceAddress - Second token address
+    /// * `key`: PoolKey - Sorted addresses of the tokens of the pool and its type
     /// * `label`: String - Label for the assertion output
     pub fn assert_exists(
       &self,
-      address0: ResourceAddress,
-      address1: ResourceAddress,
+      key: PoolKey,
       label: String
     ) {
       assert!(
-        self.pool_exists(address0, address1),
-        "[{}]: Liquidity pool already exists with given address pair.",
-        label
+        self.liquidity_pools.contains_key(&key),
+        "[{}]: {:?} liquidity pool does not exist with given addresses.",
+        label, key.1
       );
     }
 
 
-    /// Assert that liquidity pool does not exist on Swap
+    /// Assert that a pool does not exist on Swap for the given key
     ///
     /// # Arguments:
-    /// * `address`: ResourceAddress - First token address
-    /// * `address`: ResourceAddress - Second token address
+    /// * `key`: PoolKey - Sorted addresses of the tokens of the pool and its type
     /// * `label`: String - Label for the assertion output
     pub fn assert_not_exists(
       &self,
-      address0: ResourceAddress,
-      address1: ResourceAddress,
+      key: PoolKey,
       label: String
     ) {
       assert!(
-        !self.pool_exists(address0, address1),
-        "[{}]: Liquidity pool does not exist with given address pair.",
-        label
+        !self.liquidity_pools.contains_key(&key),
+        "[{}]: {:?} liquidity pool already exists with given addresses.",
+        label, key.1
       );
     }
 
+    /// Obtain every pool in which two tokens can be swapped for each other
+    ///
+    /// # Arguments:
+    /// * `address0`: ResourceAddress - First token address
+    /// * `address1`: ResourceAddress - Second token address
+    ///
+    /// # Returns:
+    /// * `Vec<PoolKey>` - Keys of the pools holding both tokens, the pools of the pair itself first
+    pub fn pools_with_pair(
+      &self,
+      address0: ResourceAddress,
+      address1: ResourceAddress
+    ) -> Vec<PoolKey> {
+      let pair: Vec<ResourceAddress> = sort_address_list(vec![address0, address1]);
+      let (mut pools, others): (Vec<PoolKey>, Vec<PoolKey>) = self.liquidity_pools.keys()
+        .filter(|(addresses, _)| addresses.contains(&address0) & addresses.contains(&address1))
+        .cloned()
+        .partition(|(addresses, _)| *addresses == pair);
+      pools.extend(others);
+      return pools;
+    }
+
+    /// Finds the pool giving the highest output when swapping an amount of one token for another
+    ///
+    /// # Arguments:
+    /// * `input_address`: ResourceAddress - Address of the input token
+    /// * `input_amount`: Decimal - Amount of input tokens to swap
+    /// * `output_address`: ResourceAddress - Address of the output token
+    ///
+    /// # Returns:
+    /// * `Option<(PoolKey, Decimal)>` - Key of the best pool and its output, None if no pool holds both tokens
+    fn best_pool(
+      &self,
+      input_address: ResourceAddress,
+      input_amount: Decimal,
+      output_address: ResourceAddress
+    ) -> Option<(PoolKey, Decimal)> {
+      let mut best_pool: Option<(PoolKey, Decimal)> = None;
+      for key in self.pools_with_pair(input_address, output_address) {
+        let output_amount: Decimal = self.liquidity_pools[&key].calculate_output_amount(
+          input_address, input_amount, output_address
+        );
+        let is_better: bool = match &best_pool {
+          Some((_, best_amount)) => output_amount > *best_amount,
+          None => true
+        };
+        if is_better {
+          best_pool = Some((key, output_amount));
+        }
+      }
+      return best_pool;
+    }
+
+    /// Finds the pool requiring the lowest input when swapping one token for an exact amount of another
+    ///
+    /// # Arguments:
+    /// * `input_address`: ResourceAddress - Address of the input token
+    /// * `output_address`: ResourceAddress - Address of the output token
+    /// * `output_amount`: Decimal - Exact amount of output tokens to receive
+    ///
+    /// # Returns:
+    /// * `Option<(PoolKey, Decimal)>` - Key of the cheapest pool and its input, None if no pool can give the output
+    fn cheapest_pool(
+      &self,
+      input_address: ResourceAddress,
+      output_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Option<(PoolKey, Decimal)> {
+      let mut cheapest_pool: Option<(PoolKey, Decimal)> = None;
+      for key in self.pools_with_pair(input_address, output_address) {
+        let input_amount: Decimal = match self.liquidity_pools[&key].quote_input_amount(
+          input_address, output_address, output_amount
+        ) {
+          Some(input_amount) => input_amount,
+          None => continue
+        };
+        let is_better: bool = match &cheapest_pool {
+          Some((_, cheapest_amount)) => input_amount < *cheapest_amount,
+          None => true
+        };
+        if is_better {
+          cheapest_pool = Some((key, input_amount));
+        }
+      }
+      return cheapest_pool;
+    }
+
+    /// Obtain the pool with the best output for a swap, panicking if no pool holds both tokens
+    ///
+    /// # Arguments:
+    /// * `input_address`: ResourceAddress - Address of the input token
+    /// * `input_amount`: Decimal - Amount of input tokens to swap
+    /// * `output_address`: ResourceAddress - Address of the output token
+    /// * `label`: String - Label for the assertion output
+    ///
+    /// # Returns:
+    /// * `PoolKey` - Key of the pool to swap through
+    fn swap_pool(
+      &self,
+      input_address: ResourceAddress,
+      input_amount: Decimal,
+      output_address: ResourceAddress,
+      label: String
+    ) -> PoolKey {
+      return match self.best_pool(input_address, input_amount, output_address) {
+        Some((key, _)) => key,
+        None => panic!("[{}]: No liquidity pool exists for the given token pair.", label)
+      };
+    }
+
+    /// Obtain the percentage of every pool fee that is diverted to the treasury
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the pool fees taken by the protocol (0-100)
+    pub fn protocol_fee(&self) -> Decimal {
+      return self.protocol_fee;
+    }
+
+    /// Changes the percentage of every pool fee that is diverted to the treasury
+    ///
+    /// # Arguments:
+    /// * `protocol_fee`: Decimal - Percentage of the pool fees taken by the protocol (0-100), zero turns it off
+    pub fn set_protocol_fee(
+      &mut self,
+      protocol_fee: Decimal
+    ) {
+      assert_valid_pool_fee(protocol_fee, String::from("Set Protocol Fee"));
+      self.protocol_fee = protocol_fee;
+
+      let liquidity_pools: &HashMap<PoolKey, Pool> = &self.liquidity_pools;
+      self.pool_admin_badge.authorize(|| {
+        for liquidity_pool in liquidity_pools.values() {
+          liquidity_pool.set_protocol_fee(protocol_fee);
+        }
+      });
+    }
+
+    /// Moves the protocol fees collected by a pool into the treasury
+    ///
+    /// # Arguments:
+    /// * `addresses`: Vec<ResourceAddress> - Addresses of the tokens of the pool, in any order
+    /// * `kind`: PoolKind - Type of the pool
+    pub fn collect_protocol_fees(
+      &mut self,
+      addresses: Vec<ResourceAddress>,
+      kind: PoolKind
+    ) {
+      let key: PoolKey = (sort_address_list(addresses), kind);
+      self.assert_exists(key.clone(), String::from("Collect Protocol Fees"));
+
+      let liquidity_pool: &Pool = &self.liquidity_pools[&key];
+      let buckets: Vec<Bucket> = self.pool_admin_badge.authorize(|| {
+        liquidity_pool.withdraw_protocol_fees()
+      });
+
+      for bucket in buckets {
+        self.treasury
+          .entry(bucket.resource_address())
+          .or_insert(Vault::new(bucket.resource_address()))
+          .put(bucket);
+      }
+    }
+
+    /// Withdraws the protocol fees of a token from the treasury, collecting them from every pool of the token first
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Token to withdraw the protocol fees of
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the protocol fees
+    pub fn withdraw_protocol_fees(
+      &mut self,
+      resource_address: ResourceAddress
+    ) -> Bucket {
+      let pools: Vec<PoolKey> = self.liquidity_pools.keys()
+        .filter(|(addresses, _)| addresses.contains(&resource_address))
+        .cloned()
+        .collect();
+      for (addresses, kind) in pools {
+        self.collect_protocol_fees(addresses, kind);
+      }
+
+      return match self.treasury.get_mut(&resource_address) {
+        Some(vault) => vault.take_all(),
+        None => Bucket::new(resource_address)
+      };
+    }
+
     /// Create new Liquidity Pool for the Swap
     /// # Arguments:
     /// * `token0`: Bucket - Contains first token to initialize the pool
     /// * `token1`: Bucket - Contains second token to initialize the pool
+    /// * `pool_fee`: Decimal - Fee imposed on swaps from the pool, must be one of the fee tiers
     ///
     /// # Returns:
     /// * `Bucket` - Contains the provider tokens issued to the liquidity pool creator
     pub fn new_liquidity_pool(
       &mut self,
       token0: Bucket,
-      token1: Bucket
+      token1: Bucket,
+      pool_fee: Decimal
     ) -> Bucket {
-      // Check if liquidity pool already exists for token pair
-      self.assert_not_exists(
-        token0.resource_address(),
-        token1.resource_address(),
-        String::from("New Liquidity Pool")
-      );
-
-      // Sort the two buckets based and create liquidity pool from them
+      // Check if a constant product pool already exists for token pair
       let (bucket0, bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
-      let addresses: (ResourceAddress, ResourceAddress) = (
-        bucket0.resource_address(),
-        bucket1.resource_address()
+      let key: PoolKey = (
+        vec![bucket0.resource_address(), bucket1.resource_address()], PoolKind::ConstantProduct
       );
+      self.assert_not_exists(key.clone(), String::from("New Liquidity Pool"));
+      self.assert_fee_tier(pool_fee, String::from("New Liquidity Pool"));
+
+      // Create liquidity pool from the sorted buckets
       let (liquidity_pool, provider_tokens): (ComponentAddress, Bucket) = LiquidityPool::new(
-        bucket0, bucket1, dec!("0.3")
+        bucket0, bucket1, pool_fee, self.pool_admin_badge.resource_address()
       );
 
-      // Add new liquidity pool to hashmap of all pools
-      self.liquidity_pools.insert(addresses, liquidity_pool.into());
+      self.register_pool(key, Pool::ConstantProduct(liquidity_pool.into()), provider_tokens.resource_address());
+      return provider_tokens;
+    }
 
-      // Add resource address of the provider tokens to the token pairs hashmap
-      self.address_pair_map.insert(provider_tokens.resource_address(), addresses);
+    /// Create new Stable Swap Pool for the Swap, meant for pegged assets
+    /// # Arguments:
+    /// * `token0`: Bucket - Contains first token to initialize the pool
+    /// * `token1`: Bucket - Contains second token to initialize the pool
+    /// * `amplification`: Decimal - Amplification coefficient of the pool, at least 1
+    /// * `pool_fee`: Decimal - Fee imposed on swaps from the pool, must be one of the fee tiers
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the provider tokens issued to the liquidity pool creator
+    pub fn new_stable_swap_pool(
+      &mut self,
+      token0: Bucket,
+      token1: Bucket,
+      amplification: Decimal,
+      pool_fee: Decimal
+    ) -> Bucket {
+      // Check if a stable swap pool already exists for token pair
+      let (bucket0, bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
+      let key: PoolKey = (vec![bucket0.resource_address(), bucket1.resource_address()], PoolKind::StableSwap);
+      self.assert_not_exists(key.clone(), String::from("New Stable Swap Pool"));
+      self.assert_fee_tier(pool_fee, String::from("New Stable Swap Pool"));
 
+      let (stable_swap_pool, provider_tokens): (ComponentAddress, Bucket) = StableSwapPool::new(
+        bucket0, bucket1, amplification, pool_fee, self.pool_admin_badge.resource_address()
+      );
+
+      self.register_pool(key, Pool::StableSwap(stable_swap_pool.into()), provider_tokens.resource_address());
+      return provider_tokens;
+    }
+
+    /// Create new Weighted Pool of 2 to 8 tokens for the Swap
+    /// # Arguments:
+    /// * `tokens`: Vec<Bucket> - Contains the tokens to initialize the pool
+    /// * `weights`: Vec<Decimal> - Weight of each token in the same order, normalized by the pool
+    /// * `pool_fee`: Decimal - Fee imposed on swaps from the pool, must be one of the fee tiers
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the provider tokens issued to the liquidity pool creator
+    pub fn new_weighted_pool(
+      &mut self,
+      tokens: Vec<Bucket>,
+      weights: Vec<Decimal>,
+      pool_fee: Decimal
+    ) -> Bucket {
+      // Check if a weighted pool already exists for the same set of tokens
+      let key: PoolKey = (
+        sort_address_list(tokens.iter().map(|bucket| bucket.resource_address()).collect()), PoolKind::Weighted
+      );
+      self.assert_not_exists(key.clone(), String::from("New Weighted Pool"));
+      self.assert_fee_tier(pool_fee, String::from("New Weighted Pool"));
+
+      let (weighted_pool, provider_tokens): (ComponentAddress, Bucket) = WeightedPool::new(
+        tokens, weights, pool_fee, self.pool_admin_badge.resource_address()
+      );
+
+      self.register_pool(key, Pool::Weighted(weighted_pool.into()), provider_tokens.resource_address());
       return provider_tokens;
     }
 
+    /// Create new Concentrated Liquidity Pool for the Swap, liquidity is added afterwards over price ranges
+    /// # Arguments:
+    /// * `address0`: ResourceAddress - Address of the first token
+    /// * `address1`: ResourceAddress - Address of the second token
+    /// * `initial_price`: Decimal - Price of a single unit of the first token denominated in the second token
+    /// * `tick_spacing`: i64 - Ticks that positions can start and end at are multiples of this spacing
+    /// * `pool_fee`: Decimal - Fee imposed on swaps from the pool, must be one of the fee tiers
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - Address of the new pool
+    pub fn new_concentrated_liquidity_pool(
+      &mut self,
+      address0: ResourceAddress,
+      address1: ResourceAddress,
+      initial_price: Decimal,
+      tick_spacing: i64,
+      pool_fee: Decimal
+    ) -> ComponentAddress {
+      // Check if a concentrated pool already exists for token pair, pools of other types can live next to it
+      let key: PoolKey = (sort_address_list(vec![address0, address1]), PoolKind::Concentrated);
+      self.assert_not_exists(key.clone(), String::from("New Concentrated Liquidity Pool"));
+      self.assert_fee_tier(pool_fee, String::from("New Concentrated Liquidity Pool"));
+
+      let concentrated_pool_address: ComponentAddress = ConcentratedLiquidityPool::new(
+        address0, address1, initial_price, tick_spacing, pool_fee, self.pool_admin_badge.resource_address()
+      );
+      let concentrated_pool: ConcentratedLiquidityPool = concentrated_pool_address.into();
+      let position_nft_address: ResourceAddress = concentrated_pool.position_nft_address();
+
+      // Position NFTs take the place of provider tokens when removing liquidity through the Swap
+      self.register_pool(key, Pool::Concentrated(concentrated_pool), position_nft_address);
+      return concentrated_pool_address;
+    }
+
+    /// Adds a newly created pool to the registry
+    ///
+    /// # Arguments:
+    /// * `key`: PoolKey - Sorted addresses of the tokens of the pool and its type
+    /// * `pool`: Pool - The newly created pool
+    /// * `provider_token_address`: ResourceAddress - Address of the provider tokens of the pool
+    fn register_pool(
+      &mut self,
+      key: PoolKey,
+      pool: Pool,
+      provider_token_address: ResourceAddress
+    ) {
+      // New pools divert the same share of their fees to the treasury as existing ones
+      if self.protocol_fee > Decimal::zero() {
+        let protocol_fee: Decimal = self.protocol_fee;
+        self.pool_admin_badge.authorize(|| {
+          pool.set_protocol_fee(protocol_fee);
+        });
+      }
+
+      // Add resource address of the provider tokens to the pool keys hashmap
+      self.address_pair_map.insert(provider_token_address, key.clone());
+
+      // Add new pool to hashmap of all pools
+      self.liquidity_pools.insert(key, pool);
+    }
+
 
-    /// Adds liquidity to existing pool or creates new pool if it does not exist
+    /// Adds liquidity to the constant product pool of a pair or creates it with the default fee if it does not exist
     ///
     /// # Arguments:
     /// * `token0`: Bucket - Contains first token to add to pool
     /// * `token1`: Bucket - Contains second token to add to pool
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
     ///
     /// # Returns:
     /// * `Bucket` - Remaining tokens from `token0`
@@ -137,49 +583,177 @@ blueprint! {
     pub fn add_liquidity(
       &mut self,
       token0: Bucket,
-      token1: Bucket
+      token1: Bucket,
+      deadline: u64
     ) -> (Option<Bucket>, Option<Bucket>, Bucket) {
+      assert_deadline(deadline, String::from("Swap Add Liquidity"));
       let (bucket0, bucket1): (Bucket, Bucket) = sort_buckets(token0, token1);
-      let addresses: (ResourceAddress, ResourceAddress) = (bucket0.resource_address(), bucket1.resource_address());
+      let key: PoolKey = (vec![bucket0.resource_address(), bucket1.resource_address()], PoolKind::ConstantProduct);
 
       // Obtain LP component for given address pair
-      let optional_lp: Option<&LiquidityPool> = self.liquidity_pools.get(&addresses);
+      let optional_lp: Option<&Pool> = self.liquidity_pools.get(&key);
       match optional_lp {
         Some(liquidity_pool) => {
-          info!("[Swap Add Liquidity]: Pool for {:?} already exists - Adding liquidity.", addresses);
-          let results: (Bucket, Bucket, Bucket) = liquidity_pool.add_liquidity(bucket0, bucket1);
-          (Some(results.0), Some(results.1), results.2)
+          info!("[Swap Add Liquidity]: Pool for {:?} already exists - Adding liquidity.", key.0);
+          let (mut remaining, provider_tokens): (Vec<Bucket>, Bucket) = liquidity_pool.add_liquidity(
+            vec![bucket0, bucket1], deadline
+          );
+          let remaining1: Bucket = remaining.pop().unwrap();
+          let remaining0: Bucket = remaining.pop().unwrap();
+          (Some(remaining0), Some(remaining1), provider_tokens)
         }
 
         None => {
-          info!("[Swap Add Liquidity]: Pool for {:?} does not exist - Creating new one.", addresses);
-          (None, None, self.new_liquidity_pool(bucket0, bucket1))
+          info!("[Swap Add Liquidity]: Pool for {:?} does not exist - Creating new one.", key.0);
+          (None, None, self.new_liquidity_pool(bucket0, bucket1, default_pool_fee()))
         }
       }
     }
 
+    /// Adds liquidity in every token of an existing pool of any type
+    ///
+    /// # Arguments:
+    /// * `tokens`: Vec<Bucket> - Contains one bucket of every token of the pool
+    /// * `kind`: PoolKind - Type of the pool
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Vec<Bucket>` - Contains the remaining tokens, in the order they were passed in
+    /// * `Bucket` - Tracks tokens issued to the provider
+    pub fn add_liquidity_to_pool(
+      &mut self,
+      tokens: Vec<Bucket>,
+      kind: PoolKind,
+      deadline: u64
+    ) -> (Vec<Bucket>, Bucket) {
+      let key: PoolKey = (sort_address_list(tokens.iter().map(|bucket| bucket.resource_address()).collect()), kind);
+      self.assert_exists(key.clone(), String::from("Swap Add Liquidity"));
+      return self.liquidity_pools[&key].add_liquidity(tokens, deadline);
+    }
 
-    /// Removes liquidity from existing pool
+    /// Adds liquidity in a single token of an existing weighted pool
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the token to add to the pool
+    /// * `pool_addresses`: Vec<ResourceAddress> - Addresses of the tokens of the pool, in any order
+    /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Tracks tokens issued to the provider
+    pub fn add_single_asset_liquidity(
+      &mut self,
+      tokens: Bucket,
+      pool_addresses: Vec<ResourceAddress>,
+      min_provider_tokens: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      let key: PoolKey = (sort_address_list(pool_addresses), PoolKind::Weighted);
+      self.assert_exists(key.clone(), String::from("Swap Add Single Asset Liquidity"));
+      return self.liquidity_pools[&key].add_single_asset_liquidity(tokens, min_provider_tokens, deadline);
+    }
+
+    /// Opens a position in the concentrated liquidity pool of a token pair
+    ///
+    /// # Arguments:
+    /// * `token0`: Bucket - Contains first token to add to pool
+    /// * `token1`: Bucket - Contains second token to add to pool
+    /// * `lower_tick`: i64 - Tick at which the price range of the position starts
+    /// * `upper_tick`: i64 - Tick at which the price range of the position ends
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Remaining tokens of the first sorted token
+    /// * `Bucket` - Remaining tokens of the second sorted token
+    /// * `Bucket` - Position NFT issued to the provider
+    pub fn add_concentrated_liquidity(
+      &mut self,
+      token0: Bucket,
+      token1: Bucket,
+      lower_tick: i64,
+      upper_tick: i64,
+      deadline: u64
+    ) -> (Bucket, Bucket, Bucket) {
+      let key: PoolKey = (
+        sort_address_list(vec![token0.resource_address(), token1.resource_address()]), PoolKind::Concentrated
+      );
+      self.assert_exists(key.clone(), String::from("Swap Add Concentrated Liquidity"));
+      return self.liquidity_pools[&key].add_concentrated_liquidity(
+        token0, token1, lower_tick, upper_tick, deadline
+      );
+    }
+
+    /// Adds liquidity to the constant product pool of a token pair from only one of its tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the token to add to the pool
+    /// * `other_address`: ResourceAddress - Address of the other token of the pool
+    /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Vec<Bucket>` - Dust of both tokens left by rounding
+    /// * `Bucket` - Tracks tokens issued to the provider
+    pub fn zap_in(
+      &mut self,
+      tokens: Bucket,
+      other_address: ResourceAddress,
+      min_provider_tokens: Decimal,
+      deadline: u64
+    ) -> (Vec<Bucket>, Bucket) {
+      let key: PoolKey = (sort_address_list(vec![tokens.resource_address(), other_address]), PoolKind::ConstantProduct);
+      self.assert_exists(key.clone(), String::from("Swap Zap In"));
+      return self.liquidity_pools[&key].zap_in(tokens, min_provider_tokens, deadline);
+    }
+
+    /// Removes liquidity from existing pool and returns it entirely in one of the tokens of the pool
     ///
     /// # Arguments:
     /// * `provider_tokens`: Bucket - Contains tokens that provider wants to swap for liquidity
+    /// * `output_address`: ResourceAddress - Address of the token to receive
+    /// * `min_output_amount`: Decimal - Minimum amount of output tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Provider's share of the pool in the output token
+    pub fn zap_out(
+      &mut self,
+      provider_tokens: Bucket,
+      output_address: ResourceAddress,
+      min_output_amount: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert!(
+        self.address_pair_map.contains_key(&provider_tokens.resource_address()),
+        "[Swap Zap Out]: Incorrect resource address for provider tokens"
+      );
+
+      let key: &PoolKey = &self.address_pair_map[&provider_tokens.resource_address()];
+      return self.liquidity_pools[key].zap_out(provider_tokens, output_address, min_output_amount, deadline);
+    }
+
+    /// Removes liquidity from existing pool
+    ///
+    /// # Arguments:
+    /// * `provider_tokens`: Bucket - Contains tokens that provider wants to swap for liquidity, or position NFTs
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
     ///
     /// # Returns:
-    /// * `Bucket` - Provider's share of the first token
-    /// * `Bucket` - Provider's share of the second token
+    /// * `Vec<Bucket>` - Provider's share of every token of the pool
     pub fn remove_liquidity(
       &mut self,
-      provider_tokens: Bucket
-    ) -> (Bucket, Bucket) {
+      provider_tokens: Bucket,
+      deadline: u64
+    ) -> Vec<Bucket> {
       // Ensure that the provider tokens are valid for the Swap
       assert!(
         self.address_pair_map.contains_key(&provider_tokens.resource_address()),
         "[Swap Remove Liquidity]: Incorrect resource address for provider tokens"
       );
 
-      // Obtain address pair of provider tokens and remove them from the pool
-      let addresses: (ResourceAddress, ResourceAddress) = self.address_pair_map[&provider_tokens.resource_address()];
-      return self.liquidity_pools[&addresses].remove_liquidity(provider_tokens);
+      // Obtain the pool of the provider tokens and remove them from the pool
+      let key: &PoolKey = &self.address_pair_map[&provider_tokens.resource_address()];
+      return self.liquidity_pools[key].remove_liquidity(provider_tokens, deadline);
     }
 
 
@@ -187,23 +761,23 @@ blueprint! {
     /// # Arguments:
     /// * `tokens`: Bucket - Bucket containing input tokens for swap
     /// * `output_address`: ResourceAddress - Address of receiving token from swap
+    /// * `min_output_amount`: Decimal - Minimum amount of output tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains the other tokens
     pub fn swap(
       &mut self,
       tokens: Bucket,
-      output_address: ResourceAddress
+      output_address: ResourceAddress,
+      min_output_amount: Decimal,
+      deadline: u64
     ) -> Bucket {
-      // Check if liquidity pool exists for token pair
-      self.assert_exists(tokens.resource_address(), output_address, String::from("Swap"));
-
-      // Sort given addresses, locate liquidity pool, and execute swap
-      let addresses: (ResourceAddress, ResourceAddress) = sort_addresses(
-        tokens.resource_address(),
-        output_address
+      // Locate the pool holding both tokens with the best output and execute swap
+      let key: PoolKey = self.swap_pool(
+        tokens.resource_address(), tokens.amount(), output_address, String::from("Swap")
       );
-      return self.liquidity_pools[&addresses].swap(tokens);
+      return self.liquidity_pools[&key].swap(tokens, output_address, min_output_amount, deadline);
     }
 
 
@@ -211,6 +785,8 @@ blueprint! {
     /// # Arguments:
     /// * `tokens`: Bucket - Bucket containing input tokens for swap
     /// * `output_address`: ResourceAddress - Address of receiving token from swap
+    /// * `min_output_amount`: Decimal - Minimum amount of output tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains the other tokens
@@ -218,17 +794,16 @@ blueprint! {
       &mut self,
       tokens: Bucket,
       output_address: ResourceAddress,
-      min_output_amount: Decimal
+      min_output_amount: Decimal,
+      deadline: u64
     ) -> Bucket {
-      // Check if liquidity pool exists for token pair
-      self.assert_exists(tokens.resource_address(), output_address, String::from("Swap Exact for Tokens"));
-
-      // Sort given addresses, locate liquidity pool, and execute exact for tokens swap
-      let addresses: (ResourceAddress, ResourceAddress) = sort_addresses(
-        tokens.resource_address(),
-        output_address
+      // Locate the pool holding both tokens with the best output and execute exact for tokens swap
+      let key: PoolKey = self.swap_pool(
+        tokens.resource_address(), tokens.amount(), output_address, String::from("Swap Exact for Tokens")
+      );
+      return self.liquidity_pools[&key].swap_exact_tokens_for_tokens(
+        tokens, output_address, min_output_amount, deadline
       );
-      return self.liquidity_pools[&addresses].swap_exact_tokens_for_tokens(tokens, min_output_amount);
     }
 
 
@@ -237,24 +812,178 @@ blueprint! {
     /// # Arguments:
     /// * `tokens`: Bucket - Bucket containing input tokens for swap
     /// * `output_address`: ResourceAddress - Address of receiving token from swap
+    /// * `output_amount`: Decimal - Exact amount of output tokens to receive
+    /// * `max_input`: Decimal - Maximum amount of input tokens the caller is willing to pay
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
     ///
     /// # Returns:
     /// * `Bucket` - Contains the other tokens
+    /// * `Bucket` - Contains the remaining input tokens
     pub fn swap_tokens_for_exact_tokens(
       &mut self,
       tokens: Bucket,
       output_address: ResourceAddress,
-      output_amount: Decimal
+      output_amount: Decimal,
+      max_input: Decimal,
+      deadline: u64
     ) -> (Bucket, Bucket) {
-      // Check if liquidity pool exists for token pair
-      self.assert_exists(tokens.resource_address(), output_address, String::from("Swap Tokens for Exact"));
+      // Locate the pool requiring the lowest input for the output and execute tokens for exact swap
+      let key: PoolKey = match self.cheapest_pool(tokens.resource_address(), output_address, output_amount) {
+        Some((key, _)) => key,
+        None => panic!("[Swap Tokens for Exact]: No liquidity pool can give the output for the given token pair.")
+      };
+      return self.liquidity_pools[&key].swap_tokens_for_exact_tokens(
+        tokens, output_address, output_amount, max_input, deadline
+      );
+    }
+
+    /// Swaps input tokens through a chain of liquidity pools
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Bucket containing input tokens for swap
+    /// * `path`: Vec<ResourceAddress> - Tokens to swap through in order, ending with the output token, every token
+    ///   different from the one before it
+    /// * `min_output_amount`: Decimal - Minimum amount of output tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the output tokens
+    pub fn swap_with_path(
+      &mut self,
+      tokens: Bucket,
+      path: Vec<ResourceAddress>,
+      min_output_amount: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert!(
+        (!path.is_empty()) & (path.len() <= MAX_HOPS),
+        "[Swap With Path]: Path must contain between 1 and {} tokens.",
+        MAX_HOPS
+      );
+
+      // Swap through every pool along the path, each output being the input of the next hop. Slippage is only
+      // checked on the final output since intermediate amounts do not matter to the caller.
+      let mut output_tokens: Bucket = tokens;
+      for output_address in path.iter().copied() {
+        assert_ne!(
+          output_tokens.resource_address(), output_address,
+          "[Swap With Path]: Path cannot swap a token for itself."
+        );
+        let key: PoolKey = self.swap_pool(
+          output_tokens.resource_address(), output_tokens.amount(), output_address, String::from("Swap With Path")
+        );
+        output_tokens = self.liquidity_pools[&key].swap(output_tokens, output_address, Decimal::zero(), deadline);
+      }
 
-      // Sort given addresses, locate liquidity pool, and execute tokens for exact swap
-      let addresses: (ResourceAddress, ResourceAddress) = sort_addresses(
-        tokens.resource_address(),
-        output_address
+      assert_eq!(
+        output_tokens.resource_address(), *path.last().unwrap(),
+        "[Swap With Path]: Output is not the last token of the path."
+      );
+      assert!(
+        output_tokens.amount() >= min_output_amount,
+        "[Swap With Path]: min_output_amount not satisfied."
+      );
+      return output_tokens;
+    }
+
+    /// Finds the path through the liquidity pools that gives the highest output for a swap
+    ///
+    /// # Arguments:
+    /// * `input_address`: ResourceAddress - Address of the input token
+    /// * `input_amount`: Decimal - Amount of input tokens to swap
+    /// * `output_address`: ResourceAddress - Address of the output token
+    /// * `max_hops`: usize - Maximum number of pools to route through, at most `MAX_HOPS`
+    ///
+    /// # Returns:
+    /// * `Vec<ResourceAddress>` - Tokens to swap through, to be passed to `swap_with_path`
+    /// * `Decimal` - Amount of output tokens the path gives
+    pub fn find_best_route(
+      &self,
+      input_address: ResourceAddress,
+      input_amount: Decimal,
+      output_address: ResourceAddress,
+      max_hops: usize
+    ) -> (Vec<ResourceAddress>, Decimal) {
+      assert_ne!(input_address, output_address, "[Find Best Route]: Input and output tokens must be different.");
+      assert!(
+        (max_hops > 0) & (max_hops <= MAX_HOPS),
+        "[Find Best Route]: Number of hops must be between 1 and {}.",
+        MAX_HOPS
       );
-      return self.liquidity_pools[&addresses].swap_tokens_for_exact_tokens(tokens, output_amount);
+
+      // Build the graph of tokens that share a liquidity pool, every token of a multi-asset pool being linked to the
+      // others
+      let mut neighbours: HashMap<ResourceAddress, Vec<ResourceAddress>> = HashMap::new();
+      for (addresses, _) in self.liquidity_pools.keys() {
+        for address in addresses.iter() {
+          let linked: &mut Vec<ResourceAddress> = neighbours.entry(*address).or_insert(Vec::new());
+          for other_address in addresses.iter() {
+            if (other_address != address) & !linked.contains(other_address) {
+              linked.push(*other_address);
+            }
+          }
+        }
+      }
+
+      // The path starts with the input token so that no route ever cycles back through it
+      let mut path: Vec<ResourceAddress> = vec![input_address];
+      let mut best_route: (Vec<ResourceAddress>, Decimal) = (Vec::new(), Decimal::zero());
+      self.search_routes(
+        &neighbours, input_address, input_amount, output_address, max_hops, &mut path, &mut best_route
+      );
+
+      assert!(!best_route.0.is_empty(), "[Find Best Route]: No route exists between the given tokens.");
+      best_route.0.remove(0);
+      info!("[Find Best Route]: Best route {:?} gives {}", best_route.0, best_route.1);
+      return best_route;
+    }
+
+    /// Depth-first search through the token graph keeping the route with the highest output
+    ///
+    /// # Arguments:
+    /// * `neighbours`: &HashMap<ResourceAddress, Vec<ResourceAddress>> - Tokens that share a pool with each token
+    /// * `current_address`: ResourceAddress - Token held at this point of the route
+    /// * `current_amount`: Decimal - Amount of the token held at this point of the route
+    /// * `output_address`: ResourceAddress - Address of the output token
+    /// * `hops_left`: usize - Number of pools the route can still go through
+    /// * `path`: &mut Vec<ResourceAddress> - Tokens swapped through so far, starting with the input token
+    /// * `best_route`: &mut (Vec<ResourceAddress>, Decimal) - Best route found so far and its output
+    fn search_routes(
+      &self,
+      neighbours: &HashMap<ResourceAddress, Vec<ResourceAddress>>,
+      current_address: ResourceAddress,
+      current_amount: Decimal,
+      output_address: ResourceAddress,
+      hops_left: usize,
+      path: &mut Vec<ResourceAddress>,
+      best_route: &mut (Vec<ResourceAddress>, Decimal)
+    ) {
+      if (hops_left == 0) | !neighbours.contains_key(&current_address) {
+        return;
+      }
+
+      for next_address in neighbours[&current_address].iter() {
+        // Tokens are never visited twice within the same route
+        if path.contains(next_address) {
+          continue;
+        }
+
+        // Each hop goes through whichever pool holding both tokens gives the most
+        let next_amount: Decimal = match self.best_pool(current_address, current_amount, *next_address) {
+          Some((_, amount)) => amount,
+          None => continue
+        };
+
+        path.push(*next_address);
+        if *next_address == output_address {
+          if next_amount > best_route.1 {
+            *best_route = (path.clone(), next_amount);
+          }
+        } else {
+          self.search_routes(neighbours, *next_address, next_amount, output_address, hops_left - 1, path, best_route);
+        }
+        path.pop();
+      }
     }
   }
 }
\ No newline at end of file
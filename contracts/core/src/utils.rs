@@ -10,6 +10,15 @@ pub fn sort_addresses(
   }
 }
 
+pub fn sort_address_list(
+  addresses: Vec<ResourceAddress>
+) -> Vec<ResourceAddress> {
+  // Same order as `sort_addresses`, the largest address first
+  let mut sorted: Vec<ResourceAddress> = addresses;
+  sorted.sort_by(|a, b| b.to_vec().cmp(&a.to_vec()));
+  return sorted;
+}
+
 pub fn sort_buckets(
   bucket0: Bucket,
   bucket1: Bucket
@@ -25,22 +34,44 @@ pub fn sort_buckets(
   }
 }
 
+pub fn token_symbol(
+  address: ResourceAddress
+) -> String {
+  return match borrow_resource_manager!(address).metadata().get("symbol") {
+    Some(s) => format!("{}", s),
+    None => format!("{}", address)
+  }
+}
+
 pub fn pair_symbol(
   address0: ResourceAddress,
   address1: ResourceAddress
 ) -> String {
   let addresses: (ResourceAddress, ResourceAddress) = sort_addresses(address0, address1);
-  let names: (String, String) = (
-    match borrow_resource_manager!(addresses.0).metadata().get("symbol") {
-      Some(s) => format!("{}", s),
-      None => format!("{}", addresses.0)
-    },
-    match borrow_resource_manager!(addresses.1).metadata().get("symbol") {
-      Some(s) => format!("{}", s),
-      None => format!("{}", addresses.1)
-    }
-  );
+  let names: (String, String) = (token_symbol(addresses.0), token_symbol(addresses.1));
 
   // Format the names and return them.
   return format!("{}-{}", names.0, names.1);
-}
\ No newline at end of file
+}
+
+pub fn assert_deadline(
+  deadline: u64,
+  label: String
+) {
+  assert!(
+    Runtime::current_epoch() <= deadline,
+    "[{}]: Transaction deadline of epoch {} has passed.",
+    label, deadline
+  );
+}
+
+pub fn assert_valid_pool_fee(
+  pool_fee: Decimal,
+  label: String
+) {
+  assert!(
+    (pool_fee >= Decimal::zero()) & (pool_fee <= dec!("100")),
+    "[{}]: Fee must be between 0 and 100.",
+    label
+  );
+}
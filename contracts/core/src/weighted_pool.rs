@@ -0,0 +1,734 @@
+use scrypto::prelude::*;
+use crate::pricing::pow;
+use crate::utils::*;
+use crate::pool_utils::*;
+
+/// Minimum number of tokens in a weighted pool
+pub const MIN_WEIGHTED_ASSETS: usize = 2;
+
+/// Maximum number of tokens in a weighted pool
+pub const MAX_WEIGHTED_ASSETS: usize = 8;
+
+/// Minimum normalized weight of a token in a weighted pool
+fn min_weight() -> Decimal {
+  return dec!("0.01");
+}
+
+/// Maximum share of the reserves of a token that can be paid in or taken out in a single operation
+fn max_ratio() -> Decimal {
+  return dec!("0.3");
+}
+
+/// Calculates the output of a swap between two tokens of a weighted pool
+///
+/// # Arguments:
+/// * `b_i`: Decimal - Reserves of the input token
+/// * `w_i`: Decimal - Weight of the input token
+/// * `b_o`: Decimal - Reserves of the output token
+/// * `w_o`: Decimal - Weight of the output token
+/// * `input_amount`: Decimal - Amount of input tokens
+/// * `pool_fee`: Decimal - Fee of the pool (0-100)
+///
+/// # Returns:
+/// * `Decimal` - Amount of output tokens where `A_o = B_o * (1 - (B_i / (B_i + r * A_i))^(W_i / W_o))`
+pub fn weighted_output_amount(
+  b_i: Decimal,
+  w_i: Decimal,
+  b_o: Decimal,
+  w_o: Decimal,
+  input_amount: Decimal,
+  pool_fee: Decimal
+) -> Decimal {
+  let r: Decimal = (dec!("100") - pool_fee) / dec!("100");
+  return b_o * (Decimal::one() - pow(b_i / (b_i + r * input_amount), w_i / w_o));
+}
+
+/// Calculates the input of a swap between two tokens of a weighted pool required for an amount of output
+///
+/// # Arguments:
+/// * `b_i`: Decimal - Reserves of the input token
+/// * `w_i`: Decimal - Weight of the input token
+/// * `b_o`: Decimal - Reserves of the output token
+/// * `w_o`: Decimal - Weight of the output token
+/// * `output_amount`: Decimal - Amount of output tokens, less than `b_o`
+/// * `pool_fee`: Decimal - Fee of the pool (0-100)
+///
+/// # Returns:
+/// * `Decimal` - Amount of input tokens where `A_i = B_i * ((B_o / (B_o - A_o))^(W_o / W_i) - 1) / r`
+pub fn weighted_input_amount(
+  b_i: Decimal,
+  w_i: Decimal,
+  b_o: Decimal,
+  w_o: Decimal,
+  output_amount: Decimal,
+  pool_fee: Decimal
+) -> Decimal {
+  let r: Decimal = (dec!("100") - pool_fee) / dec!("100");
+  return b_i * (pow(b_o / (b_o - output_amount), w_o / w_i) - Decimal::one()) / r;
+}
+
+/// Calculates the provider tokens issued for liquidity added in a single token of a weighted pool
+///
+/// # Arguments:
+/// * `supply`: Decimal - Total supply of provider tokens
+/// * `b_t`: Decimal - Reserves of the token
+/// * `w_t`: Decimal - Weight of the token
+/// * `a_t`: Decimal - Amount of tokens added
+/// * `pool_fee`: Decimal - Fee of the pool (0-100)
+///
+/// # Returns:
+/// * `Decimal` - Provider tokens where `P = supply * ((1 + A_t * (1 - (1 - W_t) * fee) / B_t)^W_t - 1)`
+///
+/// # Note:
+/// * Only `A_t * (1 - W_t)`, the part of the deposit that is implicitly swapped into the other tokens, pays the fee
+pub fn single_asset_provider_amount(
+  supply: Decimal,
+  b_t: Decimal,
+  w_t: Decimal,
+  a_t: Decimal,
+  pool_fee: Decimal
+) -> Decimal {
+  let swapped_amount: Decimal = a_t * (Decimal::one() - w_t);
+  let amount_after_fee: Decimal = a_t - swapped_amount * pool_fee / dec!("100");
+  return supply * (pow((b_t + amount_after_fee) / b_t, w_t) - Decimal::one());
+}
+
+blueprint! {
+  /// Structure representing a Weighted Pool of 2 to 8 tokens for the Elision Exchange
+  ///
+  /// # Contains:
+  /// * `vaults`: HashMap<ResourceAddress, Vault> - Collection of addresses and associated vaults
+  /// * `weights`: HashMap<ResourceAddress, Decimal> - Normalized weight of every token, summing up to 1
+  /// * `provider_token_address`: ResourceAddress - Token that providers receive for adding liquidity
+  /// * `provider_token_admin_badge`: Vault - Badge that gives authority to mint and burn tokens
+  /// * `pool_fee`: Decimal - Value between 0 and 100 defining fees paid to liquidity pool
+  /// * `protocol_fee`: Decimal - Percentage of the pool fee diverted to the protocol (0-100), off by default
+  /// * `protocol_fee_vaults`: HashMap<ResourceAddress, Vault> - Protocol fees collected until they are withdrawn
+  struct WeightedPool {
+    vaults: HashMap<ResourceAddress, Vault>,
+    weights: HashMap<ResourceAddress, Decimal>,
+    provider_token_address: ResourceAddress,
+    provider_token_admin_badge: Vault,
+    pool_fee: Decimal,
+    protocol_fee: Decimal,
+    protocol_fee_vaults: HashMap<ResourceAddress, Vault>
+  }
+
+  impl WeightedPool {
+    /// Create new Weighted Pool from given tokens passed into the function
+    ///
+    /// # Arguments:
+    /// * `tokens`: Vec<Bucket> - Contains the tokens to initialize the pool, 2 to 8 different tokens
+    /// * `weights`: Vec<Decimal> - Weight of each token in the same order, normalized so that they sum up to 1
+    /// * `pool_fee`: Decimal - Fee imposed on all swaps from this liquidity pool (0-100).
+    /// * `fee_admin_badge`: ResourceAddress - Badge with the authority to change the fees and withdraw protocol fees
+    ///
+    /// # Returns:
+    /// * `ComponentAddress` - WeightedPool component address for initialized pool
+    /// * `Bucket` - Bucket containing the issued provider tokens to the liquidity pool creator
+    ///
+    /// # Note:
+    /// * The initial reserves should be in the ratio of the weights, an 80/20 pool holding four times the value of
+    ///   the first token than of the second
+    pub fn new(
+      tokens: Vec<Bucket>,
+      weights: Vec<Decimal>,
+      pool_fee: Decimal,
+      fee_admin_badge: ResourceAddress
+    ) -> (ComponentAddress, Bucket) {
+      assert!(
+        (tokens.len() >= MIN_WEIGHTED_ASSETS) & (tokens.len() <= MAX_WEIGHTED_ASSETS),
+        "[Weighted Pool Creation]: Pool must be created from {} to {} tokens.",
+        MIN_WEIGHTED_ASSETS, MAX_WEIGHTED_ASSETS
+      );
+      assert_eq!(
+        tokens.len(), weights.len(),
+        "[Weighted Pool Creation]: Every token must be given a weight."
+      );
+      assert_valid_pool_fee(pool_fee, String::from("Weighted Pool Creation"));
+
+      let total_weight: Decimal = weights.iter().fold(Decimal::zero(), |total, weight| total + *weight);
+      assert!(
+        weights.iter().all(|weight| *weight > Decimal::zero()),
+        "[Weighted Pool Creation]: Weights must be greater than zero."
+      );
+
+      let mut vaults: HashMap<ResourceAddress, Vault> = HashMap::new();
+      let mut normalized_weights: HashMap<ResourceAddress, Decimal> = HashMap::new();
+      for (bucket, weight) in tokens.into_iter().zip(weights.into_iter()) {
+        let address: ResourceAddress = bucket.resource_address();
+        assert!(
+          !vaults.contains_key(&address),
+          "[Weighted Pool Creation]: Liquidity Pool must be created using different tokens."
+        );
+        assert_ne!(
+          borrow_resource_manager!(address).resource_type(),
+          ResourceType::NonFungible,
+          "[Weighted Pool Creation]: All assets must be fungible."
+        );
+        assert!(
+          !bucket.is_empty(),
+          "[Weighted Pool Creation]: Cannot create a pool from an empty bucket."
+        );
+
+        let normalized_weight: Decimal = weight / total_weight;
+        assert!(
+          normalized_weight >= min_weight(),
+          "[Weighted Pool Creation]: Every normalized weight must be at least {}.",
+          min_weight()
+        );
+
+        normalized_weights.insert(address, normalized_weight);
+        vaults.insert(address, Vault::with_bucket(bucket));
+      }
+
+      let addresses: Vec<ResourceAddress> = vault_addresses(&vaults);
+      let pid: String = addresses.iter().map(|address| format!("{}", address)).collect::<Vec<String>>().join("-");
+      let name: String = pool_name(&addresses);
+      info!(
+        "[Weighted Pool Creation]: Creating new pool from Tokens: {}, Name: {}, Weights: {:?}",
+        pid, name, addresses.iter().map(|address| normalized_weights[address]).collect::<Vec<Decimal>>()
+      );
+
+      let (provider_token_admin_badge, provider_tokens): (Bucket, Bucket) = new_provider_tokens(
+        &pid, format!("{} Weighted LP Provider Token", name)
+      );
+      let access_rules: AccessRules = fee_admin_access_rules(fee_admin_badge, Vec::new());
+
+      let mut weighted_pool = Self {
+        vaults: vaults,
+        weights: normalized_weights,
+        provider_token_address: provider_tokens.resource_address(),
+        provider_token_admin_badge: Vault::with_bucket(provider_token_admin_badge),
+        pool_fee: pool_fee,
+        protocol_fee: Decimal::zero(),
+        protocol_fee_vaults: new_empty_vaults(&addresses)
+      }
+      .instantiate();
+      weighted_pool.add_access_check(access_rules);
+
+      return (weighted_pool.globalize(), provider_tokens);
+    }
+
+    /// Changes the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Arguments:
+    /// * `pool_fee`: Decimal - New fee for the pool (0-100)
+    pub fn set_pool_fee(
+      &mut self,
+      pool_fee: Decimal
+    ) {
+      update_fee(&mut self.pool_fee, pool_fee, String::from("Set Pool Fee"));
+    }
+
+    /// Obtain the fee imposed on swaps from this liquidity pool
+    ///
+    /// # Returns:
+    /// * `Decimal` - Fee of the pool (0-100)
+    pub fn pool_fee(&self) -> Decimal {
+      return self.pool_fee;
+    }
+
+    /// Changes the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Arguments:
+    /// * `protocol_fee`: Decimal - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn set_protocol_fee(
+      &mut self,
+      protocol_fee: Decimal
+    ) {
+      update_fee(&mut self.protocol_fee, protocol_fee, String::from("Set Protocol Fee"));
+    }
+
+    /// Obtain the percentage of the pool fee that is diverted to the protocol
+    ///
+    /// # Returns:
+    /// * `Decimal` - Percentage of the pool fee taken by the protocol (0-100)
+    pub fn protocol_fee(&self) -> Decimal {
+      return self.protocol_fee;
+    }
+
+    /// Withdraws the protocol fees collected by the pool
+    ///
+    /// # Returns:
+    /// * `Vec<Bucket>` - Contains the protocol fees collected in every token of the pool
+    pub fn withdraw_protocol_fees(&mut self) -> Vec<Bucket> {
+      return take_protocol_fees(&mut self.protocol_fee_vaults);
+    }
+
+    /// Verifies that the given address belongs to liquidity pool or not
+    ///
+    /// # Arguments:
+    /// * `address`: ResourceAddress - Address that is going to be verified
+    ///
+    /// # Returns:
+    /// * `bool` - True if address belongs to pool, false otherwise
+    pub fn belongs_to_pool(
+      &self,
+      address: ResourceAddress
+    ) -> bool {
+      return self.vaults.contains_key(&address);
+    }
+
+    /// Obtain token addresses in liquidity pool, sorted like the keys of the ElisionSwap registry
+    ///
+    /// # Returns:
+    /// * `Vec<ResourceAddress>` - Vector of addresses that belong to the pool
+    pub fn addresses(&self) -> Vec<ResourceAddress> {
+      return vault_addresses(&self.vaults);
+    }
+
+    /// Obtain the normalized weight of every token in the pool
+    ///
+    /// # Returns:
+    /// * `HashMap<ResourceAddress, Decimal>` - Weight of every token, summing up to 1
+    pub fn weights(&self) -> HashMap<ResourceAddress, Decimal> {
+      return self.weights.clone();
+    }
+
+    /// Obtain name of liquidity pool based on the symbols of its tokens
+    ///
+    /// # Returns:
+    /// * `String` - Symbols of the tokens joined together
+    pub fn name(&self) -> String {
+      return pool_name(&self.addresses());
+    }
+
+    /// Calculates the value function of the pool: `V = Π B_t^W_t`
+    ///
+    /// # Returns:
+    /// * `Decimal` - Product of the reserves of every token raised to its weight
+    pub fn invariant(&self) -> Decimal {
+      return self.addresses().iter().fold(Decimal::one(), |value, address| {
+        value * pow(self.vaults[address].amount(), self.weights[address])
+      });
+    }
+
+    /// Calculates the current price of a token in the pool based on the reserves and weights
+    ///
+    /// # Arguments:
+    /// * `resource_address`: ResourceAddress - Address of the token to price
+    /// * `quote_address`: ResourceAddress - Address of the token to denominate the price in
+    ///
+    /// # Returns:
+    /// * `Decimal` - Price of a single unit of the token where `price = (B_q / W_q) / (B_t / W_t)`
+    pub fn spot_price(
+      &self,
+      resource_address: ResourceAddress,
+      quote_address: ResourceAddress
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, resource_address, String::from("Spot Price"));
+      assert_belongs_to_pool(&self.vaults, quote_address, String::from("Spot Price"));
+
+      let token_ratio: Decimal = self.vaults[&resource_address].amount() / self.weights[&resource_address];
+      let quote_ratio: Decimal = self.vaults[&quote_address].amount() / self.weights[&quote_address];
+      return quote_ratio / token_ratio;
+    }
+
+    /// Calculates amount of output that can be given based on the amount of input
+    /// # Arguments:
+    /// * `input_resource_address`: ResourceAddress - Input token address
+    /// * `input_amount`: Decimal - Input amount to calculate output with
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated output amount
+    ///
+    /// # Note:
+    /// * `B_i`, `W_i` - The reserves and weight of the input token
+    /// * `B_o`, `W_o` - The reserves and weight of the output token
+    /// * `A_i` - The amount of input tokens
+    /// * `A_o` - The amount of output tokens where `A_o = B_o * (1 - (B_i / (B_i + r * A_i))^(W_i / W_o))`
+    /// * `r` - The fee modifier where `r = (100 - fee) / 100`
+    /// * Inputs above `max_ratio()` of the reserves of the input token give an output of zero
+    pub fn calculate_output_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      input_amount: Decimal,
+      output_resource_address: ResourceAddress
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Output"));
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Calculate Output"));
+      assert_ne!(
+        input_resource_address, output_resource_address,
+        "[Calculate Output]: Input and output tokens must be different."
+      );
+
+      // Swaps above the maximum ratio cannot be executed, quoting nothing keeps them out of route searches
+      let b_i: Decimal = self.vaults[&input_resource_address].amount();
+      let b_o: Decimal = self.vaults[&output_resource_address].amount();
+      if input_amount > b_i * max_ratio() {
+        return Decimal::zero();
+      }
+      return weighted_output_amount(
+        b_i, self.weights[&input_resource_address],
+        b_o, self.weights[&output_resource_address],
+        input_amount, self.pool_fee
+      );
+    }
+
+    /// Calculates amount of input that is required for an amount of output
+    /// # Arguments:
+    /// * `input_resource_address`: ResourceAddress - Input token address
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Decimal` - Calculated input amount
+    ///
+    /// # Note:
+    /// * `A_i` - The amount of input tokens where `A_i = B_i * ((B_o / (B_o - A_o))^(W_o / W_i) - 1) / r`
+    /// * `r` - The fee modifier where `r = (100 - fee) / 100`
+    pub fn calculate_input_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Decimal {
+      assert_belongs_to_pool(&self.vaults, input_resource_address, String::from("Calculate Input"));
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Calculate Input"));
+      assert_ne!(
+        input_resource_address, output_resource_address,
+        "[Calculate Input]: Input and output tokens must be different."
+      );
+
+      let b_i: Decimal = self.vaults[&input_resource_address].amount();
+      let b_o: Decimal = self.vaults[&output_resource_address].amount();
+      assert!(
+        output_amount <= b_o * max_ratio(),
+        "[Calculate Input]: Output cannot be more than {} of the reserves.",
+        max_ratio()
+      );
+      return weighted_input_amount(
+        b_i, self.weights[&input_resource_address],
+        b_o, self.weights[&output_resource_address],
+        output_amount, self.pool_fee
+      );
+    }
+
+    /// Quotes the amount of input required for an amount of output, without failing when the pool cannot give it
+    ///
+    /// # Arguments:
+    /// * `input_resource_address`: ResourceAddress - Input token address
+    /// * `output_resource_address`: ResourceAddress - Output token address
+    /// * `output_amount`: Decimal - Output amount to calculate input with
+    ///
+    /// # Returns:
+    /// * `Option<Decimal>` - Calculated input amount, None if the output is more than `max_ratio()` of the reserves
+    pub fn quote_input_amount(
+      &self,
+      input_resource_address: ResourceAddress,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal
+    ) -> Option<Decimal> {
+      assert_belongs_to_pool(&self.vaults, output_resource_address, String::from("Quote Input"));
+      if output_amount > self.vaults[&output_resource_address].amount() * max_ratio() {
+        return None;
+      }
+      return Some(self.calculate_input_amount(input_resource_address, output_resource_address, output_amount));
+    }
+
+    /// Adds liquidity in every token of the pool in exchange for liquidity provider tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Vec<Bucket> - Contains one bucket of every token of the pool
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Vec<Bucket>` - Contains the remaining tokens, in the order they were passed in
+    /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+    ///
+    /// # Note:
+    /// * Liquidity is added in the ratio of the current reserves, limited by the scarcest token
+    pub fn add_liquidity(
+      &mut self,
+      tokens: Vec<Bucket>,
+      deadline: u64
+    ) -> (Vec<Bucket>, Bucket) {
+      assert_deadline(deadline, String::from("Add Liquidity"));
+      assert_eq!(
+        tokens.len(), self.vaults.len(),
+        "[Add Liquidity]: A bucket of every token of the pool must be provided."
+      );
+
+      // Find the largest share of the reserves that every bucket can cover
+      let mut ratio: Option<Decimal> = None;
+      let mut seen: HashSet<ResourceAddress> = HashSet::new();
+      for bucket in tokens.iter() {
+        assert_belongs_to_pool(&self.vaults, bucket.resource_address(), String::from("Add Liquidity"));
+        assert!(
+          seen.insert(bucket.resource_address()),
+          "[Add Liquidity]: Every token of the pool must be provided once."
+        );
+        assert!(!bucket.is_empty(), "[Add Liquidity]: Cannot add liquidity from an empty bucket");
+
+        let bucket_ratio: Decimal = bucket.amount() / self.vaults[&bucket.resource_address()].amount();
+        ratio = match ratio {
+          Some(current) if current <= bucket_ratio => Some(current),
+          _ => Some(bucket_ratio)
+        };
+      }
+      let ratio: Decimal = ratio.unwrap();
+
+      // Deposit the share of the reserves from every bucket
+      let mut remaining_tokens: Vec<Bucket> = Vec::new();
+      for mut bucket in tokens.into_iter() {
+        let amount: Decimal = self.vaults[&bucket.resource_address()].amount() * ratio;
+        info!("[Add Liquidity]: Liquidity amount to add: {}: {}", bucket.resource_address(), amount);
+        deposit_into(&mut self.vaults, bucket.take(amount));
+        remaining_tokens.push(bucket);
+      }
+
+      // Mint provider tokens for the same share of the supply
+      let provider_amount: Decimal = borrow_resource_manager!(self.provider_token_address).total_supply() * ratio;
+      let provider_tokens: Bucket = mint_provider_tokens(
+        &self.provider_token_admin_badge, self.provider_token_address, provider_amount
+      );
+
+      return (remaining_tokens, provider_tokens);
+    }
+
+    /// Adds liquidity in a single token of the pool in exchange for liquidity provider tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the token to add to the pool
+    /// * `min_provider_tokens`: Decimal - Minimum amount of provider tokens the caller will accept
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be added
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains provider tokens issued to the liquidity provider
+    ///
+    /// # Note:
+    /// * `B_t`, `W_t` - The reserves and weight of the token
+    /// * `A_t` - The amount of tokens added, of which `A_t * (1 - W_t)` is implicitly swapped and pays the pool fee
+    /// * `P` - The amount of provider tokens issued where `P = supply * ((1 + A_t * (1 - (1 - W_t) * fee) / B_t)^W_t - 1)`
+    pub fn add_single_asset_liquidity(
+      &mut self,
+      mut tokens: Bucket,
+      min_provider_tokens: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_deadline(deadline, String::from("Add Single Asset Liquidity"));
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Add Single Asset Liquidity"));
+      assert!(!tokens.is_empty(), "[Add Single Asset Liquidity]: Cannot add liquidity from an empty bucket");
+
+      let b_t: Decimal = self.vaults[&tokens.resource_address()].amount();
+      let w_t: Decimal = self.weights[&tokens.resource_address()];
+      let a_t: Decimal = tokens.amount();
+      assert!(
+        a_t <= b_t * max_ratio(),
+        "[Add Single Asset Liquidity]: Input cannot be more than {} of the reserves.",
+        max_ratio()
+      );
+
+      let provider_amount: Decimal = single_asset_provider_amount(
+        borrow_resource_manager!(self.provider_token_address).total_supply(), b_t, w_t, a_t, self.pool_fee
+      );
+      assert!(
+        provider_amount >= min_provider_tokens,
+        "[Add Single Asset Liquidity]: min_provider_tokens not satisfied."
+      );
+
+      // Only the part of the deposit that is implicitly swapped into the other tokens pays the fee
+      let swapped_amount: Decimal = a_t * (Decimal::one() - w_t);
+      let protocol_amount: Decimal = protocol_fee_amount(swapped_amount, self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut tokens, protocol_amount);
+      deposit_into(&mut self.vaults, tokens);
+
+      return mint_provider_tokens(&self.provider_token_admin_badge, self.provider_token_address, provider_amount);
+    }
+
+    /// Removes the percentage of the liquidity owed to this liquidity provider
+    /// # Arguments:
+    /// * `provider_tokens`: Bucket - Contains provider tokens to exchange for share of liquidity
+    /// * `deadline`: u64 - Last epoch in which the liquidity can be removed
+    ///
+    /// # Returns:
+    /// * `Vec<Bucket>` - Contains share of liquidity provider of every token of the pool
+    pub fn remove_liquidity(
+      &mut self,
+      provider_tokens: Bucket,
+      deadline: u64
+    ) -> Vec<Bucket> {
+      assert_deadline(deadline, String::from("Remove Liquidity"));
+      let percentage: Decimal = burn_provider_tokens(
+        &self.provider_token_admin_badge, self.provider_token_address, provider_tokens
+      );
+
+      // Withdrawing the amount of every token owed to this liquidity provider
+      return withdraw_share(&mut self.vaults, percentage);
+    }
+
+    /// Execute token swap between any two tokens of the pool and take pool fee
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains the input tokens that will be swapped for other tokens
+    /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the output tokens
+    pub fn swap(
+      &mut self,
+      mut tokens: Bucket,
+      output_resource_address: ResourceAddress,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      assert_deadline(deadline, String::from("Swap"));
+      assert_belongs_to_pool(&self.vaults, tokens.resource_address(), String::from("Swap"));
+      assert!(
+        tokens.amount() <= self.vaults[&tokens.resource_address()].amount() * max_ratio(),
+        "[Swap]: Input cannot be more than {} of the reserves.",
+        max_ratio()
+      );
+
+      let output_amount: Decimal = self.calculate_output_amount(
+        tokens.resource_address(), tokens.amount(), output_resource_address
+      );
+      assert!(output_amount >= min_amount_out, "[Swap]: min_amount_out not satisfied.");
+      let output_tokens: Bucket = withdraw_from(&mut self.vaults, output_resource_address, output_amount);
+
+      // Deposit tokens into liquidity pool, minus the protocol fee, and return bucket of swapped tokens
+      let protocol_amount: Decimal = protocol_fee_amount(tokens.amount(), self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut tokens, protocol_amount);
+      deposit_into(&mut self.vaults, tokens);
+      return output_tokens;
+    }
+
+    /// Swaps exact input tokens for output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains input tokens that will be swapped
+    /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+    /// * `min_amount_out`: Decimal - Minimum amount of tokens caller will accept
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains the output tokens
+    pub fn swap_exact_tokens_for_tokens(
+      &mut self,
+      tokens: Bucket,
+      output_resource_address: ResourceAddress,
+      min_amount_out: Decimal,
+      deadline: u64
+    ) -> Bucket {
+      return self.swap(tokens, output_resource_address, min_amount_out, deadline);
+    }
+
+    /// Swaps input tokens for an exact amount of output tokens
+    ///
+    /// # Arguments:
+    /// * `tokens`: Bucket - Contains tokens that the user wishes to swap
+    /// * `output_resource_address`: ResourceAddress - Address of the token to receive
+    /// * `output_amount`: Decimal - Specific amount of output that the user wishes to receive
+    /// * `max_input`: Decimal - Maximum amount of input tokens the user is willing to pay
+    /// * `deadline`: u64 - Last epoch in which the swap can be executed
+    ///
+    /// # Returns:
+    /// * `Bucket` - Contains output tokens
+    /// * `Bucket` - Contains remaining input tokens
+    pub fn swap_tokens_for_exact_tokens(
+      &mut self,
+      mut tokens: Bucket,
+      output_resource_address: ResourceAddress,
+      output_amount: Decimal,
+      max_input: Decimal,
+      deadline: u64
+    ) -> (Bucket, Bucket) {
+      assert_deadline(deadline, String::from("Swap For Exact"));
+
+      let input_required: Decimal = self.calculate_input_amount(
+        tokens.resource_address(), output_resource_address, output_amount
+      );
+      assert_input_sufficient(tokens.amount(), input_required, max_input);
+
+      let mut input_tokens: Bucket = tokens.take(input_required);
+      let protocol_amount: Decimal = protocol_fee_amount(input_required, self.pool_fee, self.protocol_fee);
+      take_protocol_fee(&mut self.protocol_fee_vaults, &mut input_tokens, protocol_amount);
+      deposit_into(&mut self.vaults, input_tokens);
+      let output_tokens: Bucket = withdraw_from(&mut self.vaults, output_resource_address, output_amount);
+      return (output_tokens, tokens);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Asserts that two values are within `tolerance` of each other
+  fn assert_close(
+    actual: Decimal,
+    expected: Decimal,
+    tolerance: Decimal
+  ) {
+    let difference: Decimal = if actual > expected { actual - expected } else { expected - actual };
+    assert!(difference <= tolerance, "Expected {} but got {}", expected, actual);
+  }
+
+  #[test]
+  fn pow_takes_fractional_exponents_below_one() {
+    assert_close(pow(dec!("16"), dec!("0.25")), dec!("2"), dec!("0.000000001"));
+    assert_close(pow(dec!("0.81"), dec!("0.5")), dec!("0.9"), dec!("0.000000001"));
+    assert_close(pow(dec!("0.5"), dec!("0.25")), dec!("0.840896415253714543"), dec!("0.000000001"));
+  }
+
+  #[test]
+  fn equal_weights_quote_like_constant_product() {
+    // x * y = k gives `dy = y * r * dx / (x + r * dx)` for the same reserves and fee
+    let r: Decimal = dec!("0.997");
+    let expected: Decimal = dec!("2000") * r * dec!("100") / (dec!("1000") + r * dec!("100"));
+    let output: Decimal = weighted_output_amount(
+      dec!("1000"), dec!("0.5"), dec!("2000"), dec!("0.5"), dec!("100"), dec!("0.3")
+    );
+    assert_close(output, expected, dec!("0.000001"));
+  }
+
+  #[test]
+  fn heavier_input_weight_quotes_more_output() {
+    let equal: Decimal = weighted_output_amount(
+      dec!("1000"), dec!("0.5"), dec!("1000"), dec!("0.5"), dec!("100"), Decimal::zero()
+    );
+    let heavier: Decimal = weighted_output_amount(
+      dec!("1000"), dec!("0.8"), dec!("1000"), dec!("0.2"), dec!("100"), Decimal::zero()
+    );
+    assert!(heavier > equal);
+  }
+
+  #[test]
+  fn input_amount_inverts_output_amount() {
+    let output: Decimal = weighted_output_amount(
+      dec!("1000"), dec!("0.2"), dec!("3000"), dec!("0.8"), dec!("150"), dec!("0.3")
+    );
+    let input: Decimal = weighted_input_amount(
+      dec!("1000"), dec!("0.2"), dec!("3000"), dec!("0.8"), output, dec!("0.3")
+    );
+    assert_close(input, dec!("150"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn single_asset_liquidity_without_fee_grows_the_invariant_share() {
+    // Adding 21% of the reserves of a token of weight 0.5 grows the invariant by `1.21^0.5 = 1.1`
+    let provider_amount: Decimal = single_asset_provider_amount(
+      dec!("100"), dec!("1000"), dec!("0.5"), dec!("210"), Decimal::zero()
+    );
+    assert_close(provider_amount, dec!("10"), dec!("0.000001"));
+  }
+
+  #[test]
+  fn single_asset_liquidity_pays_the_fee_on_the_swapped_part() {
+    let without_fee: Decimal = single_asset_provider_amount(
+      dec!("100"), dec!("1000"), dec!("0.5"), dec!("210"), Decimal::zero()
+    );
+    let with_fee: Decimal = single_asset_provider_amount(
+      dec!("100"), dec!("1000"), dec!("0.5"), dec!("210"), dec!("1")
+    );
+    let all_weight: Decimal = single_asset_provider_amount(
+      dec!("100"), dec!("1000"), Decimal::one(), dec!("210"), dec!("1")
+    );
+    assert!(with_fee < without_fee);
+    assert_close(all_weight, dec!("21"), dec!("0.000001"));
+  }
+}